@@ -1,22 +1,27 @@
+//! A quick-and-dirty example of what it looks like to use the library.
+//!
+//! Provide a path to a sequencing directory (such as one of the test_data subdirs),
+//! a maximum number of iterations, and a transition probability (0-100).
+//!
+//! NOTE: This will create files on disk. If allowed to run to completion, it will clean up the
+//! files it creates.
+
 use rand::prelude::*;
 use seqdir::DirManager;
 
 use std::fs::OpenOptions;
 use std::path::{Path, PathBuf};
+use std::thread;
 use std::time::Duration;
 use std::{env, io};
-use std::{thread, u16};
-
-/// A quick-and-dirty example of what it looks like to use the library.
-///
-/// Provide a path to a sequencing directory (such as one of the test_data subdirs),
-/// a maximum number of iterations, and a transition probability (0-100).
-///
-/// NOTE: This will create files on disk. If allowed to run to completion, it will clean up the
-/// files it creates.
 
 fn touch(path: &Path) -> io::Result<()> {
-    match OpenOptions::new().create(true).write(true).open(path) {
+    match OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+    {
         Ok(_) => Ok(()),
         Err(e) => Err(e),
     }
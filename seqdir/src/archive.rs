@@ -0,0 +1,258 @@
+//! Read-only access to sequencing directories archived as tarballs
+//!
+//! Enabled by the `archive` feature. An [ArchiveIndex] records where each entry of an
+//! uncompressed tar archive is stored, so that individual files can be read without extracting
+//! (or even reading through) the rest of the archive. Compressed tarballs cannot be read this
+//! way, since they do not allow random access.
+//!
+//! See [SeqDir::from_archive](crate::SeqDir::from_archive()).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use tar::EntryType;
+
+use crate::{SeqDirError, RUN_INFO_XML};
+
+const BASECALLS_PREFIX: &str = "Data/Intensities/BaseCalls/";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ArchiveEntry {
+    /// Position of the entry's contents in the archive
+    offset: u64,
+    size: u64,
+    mtime: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The files of a tar archive containing a single sequencing directory.
+///
+/// Names are relative to the root of the sequencing directory and `/`-separated. Archives
+/// commonly contain the run folder itself (e.g. `20240101_A00123_0001_AHABCDEFXY/RunInfo.xml`).
+/// If RunInfo.xml is not at the top level of the archive and every entry shares the same first
+/// component, that component is stripped.
+pub struct ArchiveIndex {
+    archive: PathBuf,
+    files: BTreeMap<String, ArchiveEntry>,
+}
+
+/// Join the normal components of `path` with `/`, ignoring e.g. a leading `./`
+fn entry_name(path: &Path) -> Option<String> {
+    let parts = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_str()),
+            _ => None,
+        })
+        .collect::<Option<Vec<&str>>>()?;
+    Some(parts.join("/"))
+}
+
+impl ArchiveIndex {
+    /// Index the regular files of the tar archive at `path`.
+    ///
+    /// Only entry headers are read; file contents are skipped over.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, SeqDirError> {
+        let mut archive = tar::Archive::new(File::open(&path)?);
+        let mut files = BTreeMap::new();
+        for entry in archive.entries_with_seek()? {
+            let entry = entry?;
+            if entry.header().entry_type() != EntryType::Regular {
+                continue;
+            }
+            let Some(name) = entry_name(&entry.path()?) else {
+                continue;
+            };
+            files.insert(
+                name,
+                ArchiveEntry {
+                    offset: entry.raw_file_position(),
+                    size: entry.size(),
+                    mtime: entry.header().mtime().unwrap_or_default(),
+                },
+            );
+        }
+        let files = strip_run_folder(files);
+        Ok(ArchiveIndex {
+            archive: path.as_ref().to_owned(),
+            files,
+        })
+    }
+
+    /// Returns the path to the archive itself
+    pub fn archive_path(&self) -> &Path {
+        &self.archive
+    }
+
+    /// Returns true if the archive contains a regular file named `name`
+    pub fn contains_file(&self, name: &str) -> bool {
+        self.files.contains_key(name)
+    }
+
+    /// Iterate over the names of every file in the archive, in sorted order
+    pub fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    /// Open the file named `name` for reading, or None if the archive does not contain it.
+    ///
+    /// Each call opens the archive anew, so readers are independent of each other.
+    pub fn open_file(&self, name: &str) -> Option<std::io::Result<impl Read>> {
+        let entry = self.files.get(name)?;
+        Some(File::open(&self.archive).and_then(|mut f| {
+            f.seek(SeekFrom::Start(entry.offset))?;
+            Ok(f.take(entry.size))
+        }))
+    }
+
+    /// Returns the modification time recorded for `name`, in seconds since the Unix epoch
+    pub fn mtime(&self, name: &str) -> Option<u64> {
+        self.files.get(name).map(|entry| entry.mtime)
+    }
+
+    /// Find the lanes with at least one file under `Data/Intensities/BaseCalls/L00N/`.
+    ///
+    /// Lanes are returned in ascending order. Basecalls written under `Analysis/` (e.g. by
+    /// NextSeq 1000/2000) are not considered.
+    pub fn lanes(&self) -> Vec<u8> {
+        self.files
+            .keys()
+            .filter_map(|name| {
+                let lane = name.strip_prefix(BASECALLS_PREFIX)?.split_once('/')?.0;
+                let lane = lane.strip_prefix("L00")?.parse::<u8>().ok()?;
+                (1..=8).contains(&lane).then_some(lane)
+            })
+            .collect::<BTreeSet<u8>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Strip the run folder from every name, if the archive contains one
+fn strip_run_folder(files: BTreeMap<String, ArchiveEntry>) -> BTreeMap<String, ArchiveEntry> {
+    if files.contains_key(RUN_INFO_XML) {
+        return files;
+    }
+    let first = |name: &str| name.split_once('/').map(|(first, _)| first.to_string());
+    let Some(Some(folder)) = files.keys().next().map(|name| first(name)) else {
+        return files;
+    };
+    if !files
+        .keys()
+        .all(|name| first(name).as_ref() == Some(&folder))
+    {
+        return files;
+    }
+    files
+        .into_iter()
+        .map(|(name, entry)| (name[folder.len() + 1..].to_string(), entry))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    use super::ArchiveIndex;
+    use crate::test_dir::TestDir;
+    use crate::{CompletionStatus, SeqDir, SeqDirError, COPY_COMPLETE_TXT};
+
+    const COMPLETE: &str = "test_data/seq_complete/";
+    const TRANSFERRING: &str = "test_data/seq_transferring/";
+
+    /// Archive `dir` into a tarball under `folder` in a new [TestDir] named `name`, returning the
+    /// TestDir and the path to the tarball
+    fn archive(dir: &str, name: &str, folder: &str) -> (TestDir, PathBuf) {
+        let tmp = TestDir::new(name);
+        let path = tmp.join("run.tar");
+        let mut builder = tar::Builder::new(std::fs::File::create(&path).unwrap());
+        builder.append_dir_all(folder, dir).unwrap();
+        builder.finish().unwrap();
+        (tmp, path)
+    }
+
+    #[test]
+    fn indexes_archive() {
+        let (_tmp, path) = archive(COMPLETE, "index", "20231231_foo_ABCXYZ");
+        let index = ArchiveIndex::open(&path).unwrap();
+        assert!(index.contains_file("RunInfo.xml"));
+        assert!(index.contains_file("Data/Intensities/BaseCalls/L001/C1.1/1.cbcl"));
+        assert!(!index.contains_file("20231231_foo_ABCXYZ/RunInfo.xml"));
+        assert_eq!(index.lanes(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn archived_seq_dir() {
+        let (tmp, path) = archive(COMPLETE, "complete_archive", "20231231_foo_ABCXYZ");
+        let seq_dir = SeqDir::from_archive(&path).unwrap();
+        let mut contents = String::new();
+        let expected = std::fs::read_to_string(format!("{COMPLETE}/{COPY_COMPLETE_TXT}")).unwrap();
+        seq_dir
+            .archive_index()
+            .unwrap()
+            .open_file(COPY_COMPLETE_TXT)
+            .unwrap()
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        assert_eq!(contents, expected);
+        assert!(seq_dir.is_copy_complete());
+        assert!(seq_dir.is_sequence_complete());
+        assert!(seq_dir.is_available());
+        assert_eq!(
+            seq_dir.read_run_info().unwrap().run_id,
+            "20231231_foo_ABCXYZ"
+        );
+        assert!(matches!(
+            seq_dir.get_completion_status(),
+            Some(Ok(CompletionStatus::CompletedAsPlanned(..)))
+        ));
+        assert!(seq_dir
+            .copy_complete_info()
+            .unwrap()
+            .unwrap()
+            .completed_at
+            .is_some());
+        drop(tmp);
+        // served from the index once the archive is gone
+        assert_eq!(seq_dir.lane_numbers().unwrap(), vec![1, 2, 3, 4]);
+        assert!(!seq_dir.is_available());
+    }
+
+    #[test]
+    fn canonicalize_archived() {
+        let (_tmp, path) = archive(COMPLETE, "canonicalize_archive", "20231231_foo_ABCXYZ");
+        let mut seq_dir = SeqDir::from_archive(&path).unwrap();
+        seq_dir.canonicalize().unwrap();
+        assert_eq!(seq_dir.root(), std::fs::canonicalize(&path).unwrap());
+        assert!(seq_dir.archive_index().is_some());
+        assert!(seq_dir.is_copy_complete());
+    }
+
+    #[test]
+    fn move_archived() {
+        let (_tmp, path) = archive(COMPLETE, "move_archive", "20231231_foo_ABCXYZ");
+        let mut seq_dir = SeqDir::from_archive(&path).unwrap();
+        seq_dir.set_root(PathBuf::from(TRANSFERRING));
+        assert!(seq_dir.archive_index().is_none());
+        assert!(!seq_dir.is_copy_complete());
+        assert!(seq_dir.is_available());
+    }
+
+    #[test]
+    fn archived_without_folder() {
+        let (_tmp, path) = archive(TRANSFERRING, "transferring_archive", ".");
+        let seq_dir = SeqDir::from_archive(&path).unwrap();
+        assert!(!seq_dir.is_copy_complete());
+        assert!(seq_dir.is_sequence_complete());
+        assert!(seq_dir.get_completion_status().is_none());
+        assert!(matches!(
+            SeqDir::from_archive(COMPLETE),
+            Err(SeqDirError::NotFound(..))
+        ));
+    }
+}
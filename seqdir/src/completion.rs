@@ -0,0 +1,267 @@
+//! Declarative definitions of when a sequencing directory is complete
+//!
+//! A [CompletionRule] is a small tree of conditions combined with AND/OR. Rules are serializable
+//! so that site-specific definitions of "complete" can live in configuration rather than code.
+//!
+//! [CompletionCriteria] covers the common case of a set of marker files plus a requirement on
+//! RunCompletionStatus.xml, and reports which condition was not met.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CompletionStatus, Platform, SeqDir, SeqDirError, COPY_COMPLETE_TXT, RTA_COMPLETE_TXT,
+    RUN_COMPLETION_STATUS_XML,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// A condition that a [SeqDir] must satisfy to be considered complete.
+pub enum CompletionRule {
+    /// A file exists at the given path, relative to the root of the sequencing directory.
+    FileExists(String),
+    /// RunCompletionStatus.xml exists and is CompletedAsPlanned.
+    StatusSuccess,
+    /// The run has not failed, as determined by [is_failed](SeqDir::is_failed()).
+    ///
+    /// Unlike `StatusSuccess`, this is satisfied when RunCompletionStatus.xml is absent.
+    NotFailed,
+    /// Every inner rule is satisfied. An empty list is always satisfied.
+    AllOf(Vec<CompletionRule>),
+    /// At least one inner rule is satisfied. An empty list is never satisfied.
+    AnyOf(Vec<CompletionRule>),
+}
+
+impl CompletionRule {
+    /// A rule satisfied when every rule in `rules` is satisfied
+    pub fn all_of<I: IntoIterator<Item = CompletionRule>>(rules: I) -> Self {
+        CompletionRule::AllOf(rules.into_iter().collect())
+    }
+
+    /// A rule satisfied when any rule in `rules` is satisfied
+    pub fn any_of<I: IntoIterator<Item = CompletionRule>>(rules: I) -> Self {
+        CompletionRule::AnyOf(rules.into_iter().collect())
+    }
+
+    /// A rule satisfied when `name` exists relative to the root of the sequencing directory
+    pub fn file_exists<S: Into<String>>(name: S) -> Self {
+        CompletionRule::FileExists(name.into())
+    }
+
+    /// The default Illumina definition of complete, matching [from_completed](SeqDir::from_completed()).
+    ///
+    /// CopyComplete.txt is present and the run has not failed.
+    pub fn illumina_default() -> Self {
+        CompletionRule::all_of([
+            CompletionRule::file_exists(COPY_COMPLETE_TXT),
+            CompletionRule::NotFailed,
+        ])
+    }
+
+    /// Evaluate the rule against `seq_dir`.
+    ///
+    /// Errors if RunCompletionStatus.xml must be parsed and cannot be.
+    /// `AllOf` and `AnyOf` short-circuit, so later rules may not be evaluated.
+    pub fn evaluate(&self, seq_dir: &SeqDir) -> Result<bool, SeqDirError> {
+        match self {
+            CompletionRule::FileExists(name) => Ok(seq_dir.has_file(name)),
+            CompletionRule::StatusSuccess => match seq_dir.get_completion_status() {
+                None => Ok(false),
+                Some(status) => Ok(matches!(status?, CompletionStatus::CompletedAsPlanned(..))),
+            },
+            CompletionRule::NotFailed => Ok(!seq_dir.is_failed()?),
+            CompletionRule::AllOf(rules) => {
+                for rule in rules {
+                    if !rule.evaluate(seq_dir)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            CompletionRule::AnyOf(rules) => {
+                for rule in rules {
+                    if rule.evaluate(seq_dir)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+/// What [CompletionCriteria] requires of RunCompletionStatus.xml
+pub enum StatusRequirement {
+    /// RunCompletionStatus.xml is not consulted
+    Ignore,
+    /// If RunCompletionStatus.xml exists, it must be CompletedAsPlanned
+    #[default]
+    IfPresent,
+    /// RunCompletionStatus.xml must exist and be CompletedAsPlanned
+    Required,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+/// Which marker files and completion status a completed sequencing directory must have.
+///
+/// The default matches [from_completed](SeqDir::from_completed()): CopyComplete.txt is present,
+/// and RunCompletionStatus.xml, if present, is CompletedAsPlanned. See
+/// [from_completed_with](SeqDir::from_completed_with()).
+pub struct CompletionCriteria {
+    /// Files that must all exist, relative to the root of the sequencing directory
+    pub marker_files: Vec<String>,
+    pub status: StatusRequirement,
+}
+
+impl Default for CompletionCriteria {
+    fn default() -> Self {
+        CompletionCriteria {
+            marker_files: vec![COPY_COMPLETE_TXT.to_string()],
+            status: StatusRequirement::IfPresent,
+        }
+    }
+}
+
+impl CompletionCriteria {
+    /// NovaSeq runs are complete once both RTAComplete.txt and CopyComplete.txt are written
+    pub fn novaseq() -> Self {
+        CompletionCriteria {
+            marker_files: vec![RTA_COMPLETE_TXT.to_string(), COPY_COMPLETE_TXT.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// MiSeq runs never write CopyComplete.txt, and are complete once RTAComplete.txt is written
+    pub fn miseq() -> Self {
+        CompletionCriteria {
+            marker_files: vec![RTA_COMPLETE_TXT.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Criteria requiring the [completion marker](Platform::completion_marker()) of `platform`
+    pub fn for_platform(platform: Platform) -> Self {
+        CompletionCriteria {
+            marker_files: vec![platform.completion_marker().to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Check that `seq_dir` meets the criteria.
+    ///
+    /// Marker files are checked first, in order. Returns SeqDirError::NotFound for the first
+    /// missing marker file, or for RunCompletionStatus.xml if it is required and absent, and
+    /// SeqDirError::CompletionStatus if the status is not CompletedAsPlanned.
+    pub fn check(&self, seq_dir: &SeqDir) -> Result<(), SeqDirError> {
+        if let Some(missing) = self.marker_files.iter().find(|f| !seq_dir.has_file(f)) {
+            return Err(SeqDirError::NotFound(seq_dir.root().join(missing)));
+        }
+        if self.status == StatusRequirement::Ignore {
+            return Ok(());
+        }
+        match seq_dir.get_completion_status() {
+            None if self.status == StatusRequirement::Required => Err(SeqDirError::NotFound(
+                seq_dir.root().join(RUN_COMPLETION_STATUS_XML),
+            )),
+            None | Some(Ok(CompletionStatus::CompletedAsPlanned(..))) => Ok(()),
+            Some(Ok(status)) => Err(SeqDirError::CompletionStatus(status)),
+            Some(Err(e)) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompletionCriteria, CompletionRule, StatusRequirement};
+    use crate::{
+        Platform, SeqDir, SeqDirError, COPY_COMPLETE_TXT, RTA_COMPLETE_TXT, SAMPLESHEET_CSV,
+    };
+
+    const COMPLETE: &str = "test_data/seq_complete/";
+    const FAILED: &str = "test_data/seq_failed/";
+    const TRANSFERRING: &str = "test_data/seq_transferring/";
+
+    #[test]
+    fn illumina_default() {
+        let rule = CompletionRule::illumina_default();
+        assert!(rule
+            .evaluate(&SeqDir::from_path(COMPLETE).unwrap())
+            .unwrap());
+        assert!(!rule.evaluate(&SeqDir::from_path(FAILED).unwrap()).unwrap());
+        assert!(!rule
+            .evaluate(&SeqDir::from_path(TRANSFERRING).unwrap())
+            .unwrap());
+    }
+
+    #[test]
+    fn combinators() {
+        let rule = CompletionRule::all_of([
+            CompletionRule::any_of([
+                CompletionRule::file_exists(COPY_COMPLETE_TXT),
+                CompletionRule::file_exists(RTA_COMPLETE_TXT),
+            ]),
+            CompletionRule::file_exists(SAMPLESHEET_CSV),
+            CompletionRule::StatusSuccess,
+        ]);
+        assert!(rule
+            .evaluate(&SeqDir::from_path(COMPLETE).unwrap())
+            .unwrap());
+        // no RunCompletionStatus.xml
+        assert!(!rule
+            .evaluate(&SeqDir::from_path(TRANSFERRING).unwrap())
+            .unwrap());
+        assert!(CompletionRule::all_of([])
+            .evaluate(&SeqDir::from_path(TRANSFERRING).unwrap())
+            .unwrap());
+        assert!(!CompletionRule::any_of([])
+            .evaluate(&SeqDir::from_path(TRANSFERRING).unwrap())
+            .unwrap());
+    }
+
+    #[test]
+    fn rule_round_trip() {
+        let rule = CompletionRule::illumina_default();
+        let json = serde_json::to_string(&rule).unwrap();
+        assert_eq!(serde_json::from_str::<CompletionRule>(&json).unwrap(), rule);
+    }
+
+    #[test]
+    fn criteria_presets() {
+        let complete = SeqDir::from_path(COMPLETE).unwrap();
+        let transferring = SeqDir::from_path(TRANSFERRING).unwrap();
+        CompletionCriteria::default().check(&complete).unwrap();
+        CompletionCriteria::novaseq().check(&complete).unwrap();
+        // RTAComplete.txt but no CopyComplete.txt
+        CompletionCriteria::miseq().check(&transferring).unwrap();
+        assert!(matches!(
+            CompletionCriteria::novaseq().check(&transferring),
+            Err(SeqDirError::NotFound(path)) if path.ends_with(COPY_COMPLETE_TXT)
+        ));
+        assert_eq!(
+            CompletionCriteria::for_platform(Platform::MiSeq),
+            CompletionCriteria::miseq()
+        );
+    }
+
+    #[test]
+    fn criteria_status() {
+        let failed = SeqDir::from_path(FAILED).unwrap();
+        let transferring = SeqDir::from_path(TRANSFERRING).unwrap();
+        let mut criteria = CompletionCriteria {
+            marker_files: Vec::new(),
+            status: StatusRequirement::IfPresent,
+        };
+        assert!(matches!(
+            criteria.check(&failed),
+            Err(SeqDirError::CompletionStatus(..))
+        ));
+        criteria.check(&transferring).unwrap();
+        criteria.status = StatusRequirement::Required;
+        assert!(matches!(
+            criteria.check(&transferring),
+            Err(SeqDirError::NotFound(..))
+        ));
+        criteria.status = StatusRequirement::Ignore;
+        criteria.check(&failed).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+//! Locate and parse BCLConvert demultiplexing reports
+//!
+//! After demultiplexing, BCLConvert writes a `Reports/` directory summarizing the yield of each
+//! sample. Only Demultiplex_Stats.csv is parsed; the other reports can be found with
+//! [find_reports_dir].
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lane::latest_analysis_dir;
+use crate::SeqDirError;
+
+pub const REPORTS_DIR: &str = "Reports";
+pub const DEMUX_STATS_CSV: &str = "Demultiplex_Stats.csv";
+pub const QUALITY_METRICS_CSV: &str = "Quality_Metrics.csv";
+
+const ANALYSIS_REPORTS: &str = "Data/Reports/";
+const LANE: &str = "Lane";
+const SAMPLE_ID: &str = "SampleID";
+const INDEX: &str = "Index";
+const READS: &str = "# Reads";
+const PERCENT_READS: &str = "% Reads";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// The yield of a single sample in a single lane, from Demultiplex_Stats.csv
+pub struct DemuxStat {
+    /// The sample id, `Undetermined` for reads that could not be assigned to a sample
+    pub sample_id: String,
+    pub lane: u8,
+    /// The index sequence(s), None for undetermined reads
+    pub index: Option<String>,
+    /// The number of reads assigned to the sample
+    pub reads: u64,
+    /// The `% Reads` column as written by BCLConvert, which is the fraction (0 to 1) of the
+    /// lane's reads assigned to the sample
+    pub percent_reads: f64,
+}
+
+/// Find the `Reports/` directory of a sequencing directory.
+///
+/// The highest-numbered `Analysis/N/Data/Reports/` is preferred, falling back to a top-level
+/// `Reports/`. Returns None if neither is a directory.
+pub fn find_reports_dir<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+    latest_analysis_dir(&dir, ANALYSIS_REPORTS).or_else(|| {
+        let top_level = dir.as_ref().join(REPORTS_DIR);
+        top_level.is_dir().then_some(top_level)
+    })
+}
+
+fn malformed(reason: String) -> SeqDirError {
+    SeqDirError::DemuxReport(reason)
+}
+
+/// Attempts to parse a file in the format of Demultiplex_Stats.csv
+///
+/// Columns are found by name, so their order does not matter and unused columns are ignored.
+pub fn parse_demux_stats<P: AsRef<Path>>(path: P) -> Result<Vec<DemuxStat>, SeqDirError> {
+    let raw_contents = read_to_string(&path)?;
+    let mut lines = raw_contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|line| !line.trim().is_empty());
+    let header: Vec<&str> = lines
+        .next()
+        .ok_or_else(|| malformed(String::from("missing header")))?
+        .split(',')
+        .map(str::trim)
+        .collect();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| *h == name)
+            .ok_or_else(|| malformed(format!("missing {name} column")))
+    };
+    let (lane, sample_id, index, reads, percent_reads) = (
+        column(LANE)?,
+        column(SAMPLE_ID)?,
+        column(INDEX)?,
+        column(READS)?,
+        column(PERCENT_READS)?,
+    );
+
+    lines
+        .map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+            let parse_err = |name: &str| malformed(format!("invalid {name} in row: {line}"));
+            Ok(DemuxStat {
+                sample_id: field(sample_id).to_string(),
+                lane: field(lane).parse().map_err(|_| parse_err(LANE))?,
+                index: Some(field(index))
+                    .filter(|i| !i.is_empty())
+                    .map(str::to_string),
+                reads: field(reads).parse().map_err(|_| parse_err(READS))?,
+                percent_reads: field(percent_reads)
+                    .parse()
+                    .map_err(|_| parse_err(PERCENT_READS))?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_reports_dir, parse_demux_stats, DEMUX_STATS_CSV};
+    use crate::test_dir::TestDir;
+    use crate::SeqDirError;
+
+    const NEXTSEQ2000: &str = "test_data/seq_nextseq2000/";
+    const COMPLETE: &str = "test_data/seq_complete/";
+
+    #[test]
+    fn finds_reports_dir() {
+        let reports = find_reports_dir(NEXTSEQ2000).unwrap();
+        assert!(reports.ends_with("Analysis/1/Data/Reports"));
+        assert!(find_reports_dir(COMPLETE).is_none());
+
+        let root = TestDir::new("top_level_reports");
+        std::fs::create_dir_all(root.join("Reports")).unwrap();
+        assert_eq!(find_reports_dir(&root), Some(root.join("Reports")));
+    }
+
+    #[test]
+    fn parses_demux_stats() {
+        let path = find_reports_dir(NEXTSEQ2000).unwrap().join(DEMUX_STATS_CSV);
+        let stats = parse_demux_stats(path).unwrap();
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats[0].sample_id, "sample_1");
+        assert_eq!(stats[0].index.as_deref(), Some("ACGTACGT-TGCATGCA"));
+        assert_eq!(stats[1].reads, 1_100_000);
+        assert_eq!(stats[2].index, None);
+        assert_eq!(stats[3].lane, 2);
+        assert_eq!(stats[3].percent_reads, 0.5);
+    }
+
+    #[test]
+    fn malformed_demux_stats() {
+        let path = find_reports_dir(NEXTSEQ2000)
+            .unwrap()
+            .join(super::QUALITY_METRICS_CSV);
+        assert!(matches!(
+            parse_demux_stats(path),
+            Err(SeqDirError::DemuxReport(..))
+        ));
+    }
+}
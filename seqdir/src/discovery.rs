@@ -0,0 +1,418 @@
+//! Discover new sequencing directories as they appear
+//!
+//! Enabled by the `watch` feature. A [RunDiscovery] periodically lists a parent directory (e.g.
+//! `/seq/incoming/`) and reports each subdirectory that has become a sequencing run since the
+//! previous scan. Like [DirManager](crate::DirManager), it polls rather than relying on
+//! filesystem notifications, which are unreliable on the network mounts runs are usually
+//! written to.
+//!
+//! A subdirectory is only reported once its RunInfo.xml exists and parses, so a run folder that
+//! is still being created is picked up on a later scan rather than reported half-written.
+//!
+//! With the `async` feature, `RunDiscovery::into_stream` does the same from a tokio runtime, as
+//! a `Stream`.
+
+use std::collections::HashSet;
+#[cfg(feature = "async")]
+use std::collections::VecDeque;
+use std::fs::read_dir;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use crate::run_info::{read_run_info, RunInfo};
+#[cfg(feature = "async")]
+use crate::RUN_INFO_XML;
+use crate::{SeqDir, SeqDirError};
+
+/// How often [RunDiscovery::watch] scans the parent directory
+pub const DEFAULT_DISCOVERY_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+/// Watches a parent directory for newly-appearing sequencing runs.
+pub struct RunDiscovery {
+    parent: PathBuf,
+    interval: Duration,
+    seen: HashSet<PathBuf>,
+}
+
+impl RunDiscovery {
+    /// Start watching `parent`.
+    ///
+    /// Runs that are already complete enough to report (see [poll](RunDiscovery::poll())) are
+    /// considered known and are never reported. Returns SeqDirError::NotFound if `parent` is not
+    /// a directory.
+    pub fn new<P: AsRef<Path>>(parent: P) -> Result<Self, SeqDirError> {
+        if !parent.as_ref().is_dir() {
+            return Err(SeqDirError::NotFound(parent.as_ref().to_path_buf()));
+        }
+        let mut discovery = RunDiscovery {
+            parent: parent.as_ref().to_path_buf(),
+            interval: DEFAULT_DISCOVERY_INTERVAL,
+            seen: HashSet::new(),
+        };
+        discovery.poll()?;
+        Ok(discovery)
+    }
+
+    /// Set how often [run](RunDiscovery::run()) scans the parent directory
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns the watched parent directory
+    pub fn parent(&self) -> &Path {
+        &self.parent
+    }
+
+    /// Scan the parent directory once, returning runs that were not reported before.
+    ///
+    /// A subdirectory is reported once its RunInfo.xml exists and parses; until then it is
+    /// re-examined on every scan. Runs are returned in order of their paths.
+    pub fn poll(&mut self) -> Result<Vec<SeqDir>, SeqDirError> {
+        let mut new_runs = Vec::new();
+        for entry in read_dir(&self.parent)? {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            if self.seen.contains(&entry.path()) {
+                continue;
+            }
+            let Ok(seq_dir) = SeqDir::from_dir_entry(&entry) else {
+                continue;
+            };
+            if seq_dir.read_run_info().is_ok() {
+                self.seen.insert(seq_dir.root().to_path_buf());
+                new_runs.push(seq_dir);
+            }
+        }
+        new_runs.sort_by(|a, b| a.root().cmp(b.root()));
+        Ok(new_runs)
+    }
+
+    /// Scan the parent directory every interval, calling `on_new` for each new run.
+    ///
+    /// Blocks until the parent directory can no longer be read, and returns that error.
+    pub fn run<F: FnMut(SeqDir)>(mut self, mut on_new: F) -> Result<(), SeqDirError> {
+        loop {
+            std::thread::sleep(self.interval);
+            self.poll()?.into_iter().for_each(&mut on_new);
+        }
+    }
+
+    /// Watch `parent` with the default interval, calling `on_new` for each new run.
+    ///
+    /// Shorthand for `RunDiscovery::new(parent)?.run(on_new)`.
+    pub fn watch<P: AsRef<Path>, F: FnMut(SeqDir)>(
+        parent: P,
+        on_new: F,
+    ) -> Result<(), SeqDirError> {
+        Self::new(parent)?.run(on_new)
+    }
+}
+
+#[cfg(feature = "async")]
+impl RunDiscovery {
+    /// Watch `parent` from a tokio runtime, scanning it every `interval`.
+    ///
+    /// See [RunStream] for how runs are reported.
+    pub fn into_stream<P: AsRef<Path>>(parent: P, interval: Duration) -> RunStream {
+        RunStream {
+            parent: parent.as_ref().to_path_buf(),
+            interval,
+            seen_paths: HashSet::new(),
+            seen_keys: HashSet::new(),
+            pending: VecDeque::new(),
+            scanned: false,
+            report_existing: false,
+            scan: None,
+            error: None,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+/// The result of one scan: the paths seen so far, handed back, and the new runs found
+type ScanOutput = (
+    HashSet<PathBuf>,
+    Result<Vec<(PathBuf, RunInfo)>, SeqDirError>,
+);
+
+#[cfg(feature = "async")]
+/// Asynchronously reports newly-appearing sequencing runs, created by
+/// [RunDiscovery::into_stream].
+///
+/// RunStream is a [Stream](futures_core::Stream) of [SeqDirs](SeqDir), and also has an
+/// inherent [next](RunStream::next()) for use without a stream combinator library. Each scan
+/// lists the parent directory with `tokio::fs`, and, as with [RunDiscovery], reports a
+/// subdirectory once its RunInfo.xml exists and parses. Runs are identified by their
+/// [run_key](SeqDir::run_key()), so a run that appears in a second folder, e.g. a re-copy
+/// under a new name, is reported only once.
+///
+/// By default, runs present at the first scan are considered known and are never reported. A
+/// service that records which runs it has ingested should instead seed them with
+/// [with_known_keys](RunStream::with_known_keys()), so that runs which arrived while it was
+/// not running are still reported.
+pub struct RunStream {
+    parent: PathBuf,
+    interval: Duration,
+    seen_paths: HashSet<PathBuf>,
+    seen_keys: HashSet<String>,
+    pending: VecDeque<SeqDir>,
+    scanned: bool,
+    report_existing: bool,
+    scan: Option<Pin<Box<dyn Future<Output = ScanOutput> + Send>>>,
+    error: Option<SeqDirError>,
+}
+
+#[cfg(feature = "async")]
+impl std::fmt::Debug for RunStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunStream")
+            .field("parent", &self.parent)
+            .field("interval", &self.interval)
+            .field("pending", &self.pending)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "async")]
+impl RunStream {
+    /// Report runs present at the first scan, unless their [run_key](SeqDir::run_key()) is one
+    /// of `keys`.
+    ///
+    /// `keys` are also never reported later, e.g. if such a run is re-copied under a new name.
+    pub fn with_known_keys<I: IntoIterator<Item = String>>(mut self, keys: I) -> Self {
+        self.seen_keys.extend(keys);
+        self.report_existing = true;
+        self
+    }
+
+    /// Wait for the next new run.
+    ///
+    /// Equivalent to `StreamExt::next`. Runs found by the same scan are returned in order of
+    /// their paths. Returns None once the parent directory can no longer be read, after which
+    /// the error is available from [error](RunStream::error()). Cancelling the returned future
+    /// does not lose runs, since an interrupted scan resumes on the next call.
+    pub async fn next(&mut self) -> Option<SeqDir> {
+        std::future::poll_fn(|cx| futures_core::Stream::poll_next(Pin::new(&mut *self), cx)).await
+    }
+
+    /// Returns the error that ended the stream, if it has ended
+    pub fn error(&self) -> Option<&SeqDirError> {
+        self.error.as_ref()
+    }
+
+    /// Returns the watched parent directory
+    pub fn parent(&self) -> &Path {
+        &self.parent
+    }
+
+    /// Queue the runs found by a scan, unless they are duplicates or this was the first scan
+    fn queue(&mut self, found: Vec<(PathBuf, RunInfo)>) {
+        let report = self.scanned || self.report_existing;
+        for (path, run_info) in found {
+            let seq_dir = SeqDir::from_dir_unchecked(path);
+            let duplicate = seq_dir
+                .run_key_from(Ok(run_info))
+                .is_ok_and(|key| !self.seen_keys.insert(key));
+            if report && !duplicate {
+                self.pending.push_back(seq_dir);
+            }
+        }
+        self.pending
+            .make_contiguous()
+            .sort_by(|a, b| a.root().cmp(b.root()));
+        self.scanned = true;
+    }
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for RunStream {
+    type Item = SeqDir;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<SeqDir>> {
+        loop {
+            if let Some(run) = self.pending.pop_front() {
+                return Poll::Ready(Some(run));
+            }
+            if self.error.is_some() {
+                return Poll::Ready(None);
+            }
+            let this = &mut *self;
+            let scan = this.scan.get_or_insert_with(|| {
+                let delay = this.scanned.then_some(this.interval);
+                Box::pin(scan(
+                    this.parent.clone(),
+                    std::mem::take(&mut this.seen_paths),
+                    delay,
+                ))
+            });
+            let (seen_paths, found) = std::task::ready!(scan.as_mut().poll(cx));
+            this.scan = None;
+            this.seen_paths = seen_paths;
+            match found {
+                Ok(found) => this.queue(found),
+                Err(e) => this.error = Some(e),
+            }
+        }
+    }
+}
+
+/// Wait for `delay`, if any, then list `parent` for runs not in `seen_paths`.
+///
+/// Runs found are added to `seen_paths`, which is returned whether or not the scan succeeds.
+#[cfg(feature = "async")]
+async fn scan(
+    parent: PathBuf,
+    mut seen_paths: HashSet<PathBuf>,
+    delay: Option<Duration>,
+) -> ScanOutput {
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    let mut found = Vec::new();
+    let mut entries = match tokio::fs::read_dir(&parent).await {
+        Ok(entries) => entries,
+        Err(e) => return (seen_paths, Err(e.into())),
+    };
+    loop {
+        let entry = match entries.next_entry().await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => break,
+            Err(e) => return (seen_paths, Err(e.into())),
+        };
+        let path = entry.path();
+        if seen_paths.contains(&path)
+            || !tokio::fs::metadata(&path)
+                .await
+                .is_ok_and(|meta| meta.is_dir())
+        {
+            continue;
+        }
+        let Ok(raw) = tokio::fs::read(path.join(RUN_INFO_XML)).await else {
+            continue;
+        };
+        let Ok(run_info) = read_run_info(raw.as_slice()) else {
+            continue;
+        };
+        seen_paths.insert(path.clone());
+        found.push((path, run_info));
+    }
+    (seen_paths, Ok(found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunDiscovery;
+    use crate::test_dir::TestDir;
+    use crate::{SeqDirError, RUN_INFO_XML};
+
+    const RUN_INFO: &str = "test_data/seq_complete/RunInfo.xml";
+
+    #[test]
+    fn reports_new_runs_once() {
+        let parent = TestDir::new("discovery");
+        std::fs::create_dir_all(parent.join("existing")).unwrap();
+        std::fs::copy(RUN_INFO, parent.join("existing").join(RUN_INFO_XML)).unwrap();
+        let mut discovery = RunDiscovery::new(&parent).unwrap();
+        assert!(discovery.poll().unwrap().is_empty());
+
+        let run = parent.join("new_run");
+        std::fs::create_dir_all(&run).unwrap();
+        assert!(discovery.poll().unwrap().is_empty());
+        std::fs::write(run.join(RUN_INFO_XML), "<RunInfo").unwrap();
+        assert!(discovery.poll().unwrap().is_empty());
+        std::fs::copy(RUN_INFO, run.join(RUN_INFO_XML)).unwrap();
+        let found = discovery.poll().unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].root(), run);
+        assert!(discovery.poll().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn streams_new_runs_once() {
+        use std::time::Duration;
+
+        let parent = TestDir::new("discovery_stream");
+        std::fs::create_dir_all(parent.join("existing")).unwrap();
+        std::fs::copy(RUN_INFO, parent.join("existing").join(RUN_INFO_XML)).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut stream = RunDiscovery::into_stream(&parent, Duration::from_millis(5));
+        let quiet = Duration::from_millis(50);
+        runtime.block_on(async {
+            assert!(tokio::time::timeout(quiet, stream.next()).await.is_err());
+            for name in ["new_run", "new_run_copy"] {
+                std::fs::create_dir_all(parent.join(name)).unwrap();
+                std::fs::copy(
+                    "test_data/seq_nextseq2000/RunInfo.xml",
+                    parent.join(name).join(RUN_INFO_XML),
+                )
+                .unwrap();
+            }
+            let found = stream.next().await.unwrap();
+            assert_eq!(found.root(), parent.join("new_run"));
+            assert!(tokio::time::timeout(quiet, stream.next()).await.is_err());
+            // the stream ends once the parent disappears
+            std::fs::remove_dir_all(&parent).unwrap();
+            assert!(stream.next().await.is_none());
+        });
+        assert!(stream.error().is_some());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn streams_runs_unknown_at_start() {
+        use std::pin::Pin;
+        use std::time::Duration;
+
+        use futures_core::Stream;
+
+        use crate::SeqDir;
+
+        let parent = TestDir::new("discovery_known_keys");
+        for (name, run_info) in [
+            ("ingested", RUN_INFO),
+            ("missed", "test_data/seq_nextseq2000/RunInfo.xml"),
+        ] {
+            std::fs::create_dir_all(parent.join(name)).unwrap();
+            std::fs::copy(run_info, parent.join(name).join(RUN_INFO_XML)).unwrap();
+        }
+        let known = SeqDir::from_path(parent.join("ingested"))
+            .unwrap()
+            .run_key()
+            .unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut stream =
+            RunDiscovery::into_stream(&parent, Duration::from_millis(5)).with_known_keys([known]);
+        runtime.block_on(async {
+            let next = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx));
+            assert_eq!(next.await.unwrap().root(), parent.join("missed"));
+            let quiet = Duration::from_millis(50);
+            assert!(tokio::time::timeout(quiet, stream.next()).await.is_err());
+        });
+    }
+
+    #[test]
+    fn missing_parent() {
+        assert!(matches!(
+            RunDiscovery::new("test_data/does_not_exist"),
+            Err(SeqDirError::NotFound(..))
+        ));
+    }
+}
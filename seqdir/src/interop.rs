@@ -0,0 +1,211 @@
+//! Preflight checks for InterOp metrics
+//!
+//! RTA writes binary run metrics to the `InterOp/` directory. This module does not parse them,
+//! it only checks that the core metric files exist and that ExtractionMetricsOut.bin has a
+//! plausible size, so that a full InterOp parser is not launched against truncated metrics.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{RunInfo, SeqDir, SeqDirError};
+
+pub const INTEROP_DIR: &str = "InterOp";
+pub const EXTRACTION_METRICS_BIN: &str = "ExtractionMetricsOut.bin";
+pub const TILE_METRICS_BIN: &str = "TileMetricsOut.bin";
+pub const Q_METRICS_BIN: &str = "QMetricsOut.bin";
+pub const CORRECTED_INT_METRICS_BIN: &str = "CorrectedIntMetricsOut.bin";
+
+/// The metric files expected for every run.
+///
+/// ErrorMetricsOut.bin is deliberately excluded, since it is only written for runs with a PhiX
+/// control.
+pub const CORE_METRICS: [&str; 4] = [
+    TILE_METRICS_BIN,
+    EXTRACTION_METRICS_BIN,
+    Q_METRICS_BIN,
+    CORRECTED_INT_METRICS_BIN,
+];
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "status", content = "reason")]
+/// The outcome of checking a metric file
+pub enum MetricStatus {
+    Present,
+    Absent,
+    /// The file exists, but is probably truncated or does not belong to the run
+    Suspect(String),
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// The outcome of [interop_preflight](SeqDir::interop_preflight()) for each of the
+/// [core metrics](CORE_METRICS), in that order.
+pub struct InterOpPreflight {
+    pub metrics: Vec<(String, MetricStatus)>,
+}
+
+impl InterOpPreflight {
+    /// Returns true if every core metric file is present and none are suspect
+    pub fn ok(&self) -> bool {
+        self.metrics
+            .iter()
+            .all(|(_, status)| *status == MetricStatus::Present)
+    }
+
+    /// Returns the status of the metric file named `name`, if it was checked
+    pub fn status(&self, name: &str) -> Option<&MetricStatus> {
+        self.metrics
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, status)| status)
+    }
+}
+
+/// Check the size of ExtractionMetricsOut.bin of `len` bytes, whose first two bytes are `header`.
+///
+/// The layout relied upon is a one-byte version followed by a one-byte record size. Version 3
+/// adds a one-byte channel count; versions 2 and earlier have no further header. Every record
+/// describes one cycle of one tile. The file must hold a whole number of records, and no more
+/// than the cycles × tiles planned by RunInfo.xml. Once the run is complete, it must hold exactly
+/// that many.
+fn check_extraction_metrics(
+    len: u64,
+    header: [u8; 2],
+    run_info: Option<&RunInfo>,
+    is_complete: bool,
+) -> MetricStatus {
+    let [version, record_size] = header;
+    if record_size == 0 {
+        return MetricStatus::Suspect(String::from("record size is zero"));
+    }
+    let header_size = if version >= 3 { 3 } else { 2 };
+    let payload = len.saturating_sub(header_size);
+    if payload % u64::from(record_size) != 0 {
+        return MetricStatus::Suspect(format!(
+            "{payload} bytes of records is not a multiple of the {record_size} byte record size"
+        ));
+    }
+    let records = payload / u64::from(record_size);
+
+    let Some(run_info) = run_info else {
+        return MetricStatus::Present;
+    };
+    let Some(layout) = run_info.flowcell_layout() else {
+        return MetricStatus::Present;
+    };
+    let cycles = u64::from(run_info.total_cycles());
+    let tiles = layout.total_tiles();
+    let expected = cycles * tiles;
+    if records > expected || (is_complete && records < expected) {
+        MetricStatus::Suspect(format!(
+            "found {records} records, expected {expected} ({cycles} cycles × {tiles} tiles)"
+        ))
+    } else {
+        MetricStatus::Present
+    }
+}
+
+fn check_metric(path: &Path, run_info: Option<&RunInfo>, is_complete: bool) -> MetricStatus {
+    let Ok(metadata) = path.metadata() else {
+        return MetricStatus::Absent;
+    };
+    if !metadata.is_file() {
+        return MetricStatus::Suspect(String::from("not a regular file"));
+    }
+    if metadata.len() < 2 {
+        return MetricStatus::Suspect(String::from("too short to have a header"));
+    }
+    if path.file_name().and_then(|n| n.to_str()) != Some(EXTRACTION_METRICS_BIN) {
+        return MetricStatus::Present;
+    }
+    let mut header = [0u8; 2];
+    let read =
+        std::fs::File::open(path).and_then(|mut f| std::io::Read::read_exact(&mut f, &mut header));
+    match read {
+        Ok(()) => check_extraction_metrics(metadata.len(), header, run_info, is_complete),
+        Err(e) => MetricStatus::Suspect(e.to_string()),
+    }
+}
+
+pub(crate) fn interop_preflight(seq_dir: &SeqDir) -> Result<InterOpPreflight, SeqDirError> {
+    let interop = seq_dir.try_root()?.join(INTEROP_DIR);
+    let run_info = seq_dir.read_run_info().ok();
+    let is_complete = seq_dir.is_copy_complete();
+    let metrics = CORE_METRICS
+        .iter()
+        .map(|name| {
+            let status = check_metric(&interop.join(name), run_info.as_ref(), is_complete);
+            (name.to_string(), status)
+        })
+        .collect();
+    Ok(InterOpPreflight { metrics })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_extraction_metrics, MetricStatus, EXTRACTION_METRICS_BIN, INTEROP_DIR,
+        TILE_METRICS_BIN,
+    };
+    use crate::run_info::parse_run_info;
+    use crate::test_dir::TestDir;
+    use crate::SeqDir;
+
+    // 4 lanes, 2 surfaces, 3 swaths, 12 tiles, 3 sections and 91 cycles
+    const TRANSFERRING_RI: &str = "test_data/seq_transferring/RunInfo.xml";
+
+    #[test]
+    fn extraction_metrics_size() {
+        let run_info = parse_run_info(TRANSFERRING_RI).unwrap();
+        let expected = 91 * 864;
+        let check = |records: u64, is_complete| {
+            check_extraction_metrics(2 + records * 38, [2, 38], Some(&run_info), is_complete)
+        };
+        assert_eq!(check(10, false), MetricStatus::Present);
+        assert_eq!(check(expected, true), MetricStatus::Present);
+        assert!(matches!(check(10, true), MetricStatus::Suspect(..)));
+        assert!(matches!(
+            check(expected + 1, false),
+            MetricStatus::Suspect(..)
+        ));
+        // truncated mid-record
+        assert!(matches!(
+            check_extraction_metrics(2 + 38 * 10 + 5, [2, 38], None, false),
+            MetricStatus::Suspect(..)
+        ));
+        // version 3 has a longer header
+        assert_eq!(
+            check_extraction_metrics(3 + 38 * 10, [3, 38], None, false),
+            MetricStatus::Present
+        );
+    }
+
+    #[test]
+    fn preflight() {
+        let root = TestDir::new("interop_preflight");
+        let interop = root.join(INTEROP_DIR);
+        std::fs::create_dir_all(&interop).unwrap();
+        std::fs::write(interop.join(TILE_METRICS_BIN), [2, 10]).unwrap();
+        let mut extraction = vec![2u8, 38];
+        extraction.extend([0u8; 38 * 3 + 1]);
+        std::fs::write(interop.join(EXTRACTION_METRICS_BIN), extraction).unwrap();
+        let preflight = SeqDir::from_path(&root)
+            .unwrap()
+            .interop_preflight()
+            .unwrap();
+        assert!(!preflight.ok());
+        assert_eq!(preflight.metrics.len(), 4);
+        assert_eq!(
+            preflight.status(TILE_METRICS_BIN),
+            Some(&MetricStatus::Present)
+        );
+        assert!(matches!(
+            preflight.status(EXTRACTION_METRICS_BIN),
+            Some(MetricStatus::Suspect(..))
+        ));
+        assert_eq!(
+            preflight.status(super::Q_METRICS_BIN),
+            Some(&MetricStatus::Absent)
+        );
+    }
+}
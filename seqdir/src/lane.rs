@@ -1,21 +1,30 @@
 use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
 use std::ffi::OsStr;
-use std::fs::read_dir;
+use std::fs::{read_dir, File};
+use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use crate::SeqDirError;
+use crate::run_params::{parse_run_params, Platform};
+use crate::walk::walk_files;
+use crate::{SeqDirError, ANALYSIS_DIR, RUN_PARAMS_XML};
 
 // may as well future proof against S8 flowcells
 const LANES: [&str; 8] = [
     "L001", "L002", "L003", "L004", "L005", "L006", "L007", "L008",
 ];
 const BASECALLS: &str = "Data/Intensities/BaseCalls/";
+// relative to Analysis/N
+const ANALYSIS_BASECALLS: &str = "Data/BaseCalls/";
 const FILTER_EXT: &str = "filter";
+const LOCS_EXTS: [&str; 2] = ["locs", "clocs"];
 const CBCL: &str = "cbcl";
 const CBCL_GZ: &str = "cbcl.gz";
 const BCL: &str = "bcl";
 const BCL_GZ: &str = "bcl.gz";
 const CYCLE_PREFIX: &str = "C";
+const GZ: &str = "gz";
 
 /// A BCL or a CBCL
 #[derive(Clone, Debug, Serialize, PartialEq)]
@@ -39,16 +48,95 @@ impl Bcl {
             None
         }
     }
+
+    /// Returns a reference to the path of the (C)BCL
+    pub fn path(&self) -> &Path {
+        match self {
+            Bcl::Bcl(path) | Bcl::CBcl(path) => path,
+        }
+    }
+
+    /// Returns true if the whole (C)BCL file is gzipped, i.e. its path ends in '.gz'.
+    ///
+    /// The variant does not record this, so `s_1_1101.bcl` and `s_1_1101.bcl.gz` are both `Bcl`.
+    /// Note that the blocks within a CBCL are compressed regardless.
+    pub fn is_gzipped(&self) -> bool {
+        self.path().extension() == Some(OsStr::new(GZ))
+    }
+
+    /// Returns true if the (C)BCL is gzipped, see [is_gzipped](Bcl::is_gzipped())
+    #[deprecated(note = "use is_gzipped")]
+    pub fn is_compressed(&self) -> bool {
+        self.is_gzipped()
+    }
+
+    /// Read the number of tiles recorded in a CBCL's header.
+    ///
+    /// Only the header is read. The layout relied upon, with all integers little-endian, is:
+    ///
+    /// | offset         | size          | field                            |
+    /// |----------------|---------------|----------------------------------|
+    /// | 0              | 2             | version                          |
+    /// | 2              | 4             | header size                      |
+    /// | 6              | 1             | bits per basecall                |
+    /// | 7              | 1             | bits per quality score           |
+    /// | 8              | 4             | number of quality bins, `n`      |
+    /// | 12             | 8 × `n`       | quality bin (from, to) pairs     |
+    /// | 12 + 8 × `n`   | 4             | number of tiles                  |
+    ///
+    /// followed by the per-tile records, which are not read. Returns None for plain BCLs, which
+    /// have no such header, and an error of kind `UnexpectedEof` if the file is too short, e.g.
+    /// because it is still being written. Gzipped CBCLs are handled as by
+    /// [open_bcls](Cycle::open_bcls()).
+    pub fn cbcl_tile_count(&self) -> std::io::Result<Option<u32>> {
+        if matches!(self, Bcl::Bcl(..)) {
+            return Ok(None);
+        }
+        let mut reader = open_bcl(self)?;
+        let mut fixed = [0u8; 12];
+        reader.read_exact(&mut fixed)?;
+        let num_bins = u32::from_le_bytes([fixed[8], fixed[9], fixed[10], fixed[11]]);
+        std::io::copy(
+            &mut reader.by_ref().take(u64::from(num_bins) * 8),
+            &mut std::io::sink(),
+        )?;
+        let mut num_tiles = [0u8; 4];
+        reader.read_exact(&mut num_tiles)?;
+        Ok(Some(u32::from_le_bytes(num_tiles)))
+    }
+}
+
+/// The format of the (C)BCLs in a cycle or run
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+pub enum BclFormat {
+    Bcl,
+    CBcl,
+    Mixed,
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 /// A cycle consists of a cycle number and any number of (C)BCLs
+///
+/// Serializes with an additional `is_compressed` field, see [is_compressed](Cycle::is_compressed()).
 pub struct Cycle<P: AsRef<Path>> {
     pub cycle_num: u16,
     pub root: P,
     pub bcls: Vec<Bcl>,
 }
 
+impl<P: AsRef<Path> + Serialize> Serialize for Cycle<P> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut cycle = serializer.serialize_struct("Cycle", 4)?;
+        cycle.serialize_field("cycle_num", &self.cycle_num)?;
+        cycle.serialize_field("root", &self.root)?;
+        cycle.serialize_field("bcls", &self.bcls)?;
+        cycle.serialize_field("is_compressed", &self.is_compressed())?;
+        cycle.end()
+    }
+}
+
 impl<P: AsRef<Path>> Cycle<P> {
     /// Attempt to read the provided directory as a Cycle
     ///
@@ -58,15 +146,7 @@ impl<P: AsRef<Path>> Cycle<P> {
     /// 1. the directory does no start with 'C' or is not followed by a cycle number
     /// 2. the directory does not contain any (C)Bcls
     pub fn from_path(path: P) -> Result<Cycle<P>, SeqDirError> {
-        let cycle_num = path
-            .as_ref()
-            .file_stem()
-            .ok_or(SeqDirError::BadCycle(path.as_ref().to_owned()))?
-            .to_owned()
-            .to_string_lossy()
-            .strip_prefix(CYCLE_PREFIX)
-            .ok_or(SeqDirError::BadCycle(path.as_ref().to_owned()))?
-            .parse::<u16>()?;
+        let cycle_num = parse_cycle_num(path.as_ref())?;
 
         // collect any BCLs. Return None if no BCLs
         let bcls: Vec<Bcl> = read_dir(&path)?
@@ -84,14 +164,103 @@ impl<P: AsRef<Path>> Cycle<P> {
             bcls,
         })
     }
+
+    /// Returns the [BclFormat] of the cycle's (C)BCLs
+    ///
+    /// Returns `Mixed` if the cycle contains both BCLs and CBCLs.
+    pub fn bcl_format(&self) -> BclFormat {
+        if self.bcls.iter().all(|b| matches!(b, Bcl::Bcl(..))) {
+            BclFormat::Bcl
+        } else if self.bcls.iter().all(|b| matches!(b, Bcl::CBcl(..))) {
+            BclFormat::CBcl
+        } else {
+            BclFormat::Mixed
+        }
+    }
+
+    /// Open each of the cycle's (C)BCLs for reading.
+    ///
+    /// Gzipped files are transparently decompressed when the `gzip` feature is enabled, so
+    /// every reader yields the uncompressed contents. Without it, gzipped files yield an error of
+    /// kind `Unsupported`. Files that cannot be opened are yielded as errors rather than ending
+    /// iteration.
+    pub fn open_bcls(&self) -> impl Iterator<Item = std::io::Result<(Bcl, Box<dyn Read>)>> + '_ {
+        self.bcls
+            .iter()
+            .map(|bcl| Ok((bcl.clone(), open_bcl(bcl)?)))
+    }
+
+    /// Returns true if all of the cycle's (C)BCLs are gzipped.
+    ///
+    /// Returns false if the cycle contains a mix of compressed and uncompressed (C)BCLs, or
+    /// none at all.
+    pub fn is_compressed(&self) -> bool {
+        !self.bcls.is_empty() && self.bcls.iter().all(Bcl::is_gzipped)
+    }
+
+    /// Returns false if the cycle mixes BCLs with CBCLs, or compressed with uncompressed files.
+    ///
+    /// Instruments write a single format per cycle, so an inconsistent cycle usually means files
+    /// were partially compressed or copied in from another run.
+    pub fn is_consistent(&self) -> bool {
+        self.bcl_format() != BclFormat::Mixed
+            && (self.is_compressed() || !self.bcls.iter().any(Bcl::is_gzipped))
+    }
+}
+
+/// Open a (C)BCL, decompressing it if necessary
+fn open_bcl(bcl: &Bcl) -> std::io::Result<Box<dyn Read>> {
+    let reader = BufReader::new(File::open(bcl.path())?);
+    if !bcl.is_gzipped() {
+        return Ok(Box::new(reader));
+    }
+    #[cfg(feature = "gzip")]
+    {
+        Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)))
+    }
+    #[cfg(not(feature = "gzip"))]
+    {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "reading gzipped (C)BCLs requires the gzip feature",
+        ))
+    }
+}
+
+/// Parse the cycle number from a cycle directory name in the format of C###.#
+///
+/// Returns SeqDirError::NonUtf8Path if the directory name is not valid UTF-8, rather than
+/// mangling it into a misleading parse error.
+fn parse_cycle_num(path: &Path) -> Result<u16, SeqDirError> {
+    Ok(path
+        .file_stem()
+        .ok_or(SeqDirError::BadCycle(path.to_owned()))?
+        .to_str()
+        .ok_or(SeqDirError::NonUtf8Path(path.to_owned()))?
+        .strip_prefix(CYCLE_PREFIX)
+        .ok_or(SeqDirError::BadCycle(path.to_owned()))?
+        .parse::<u16>()?)
+}
+
+/// Returns true if `path` is a directory whose name starts with the cycle prefix.
+///
+/// Names that are not valid UTF-8 are checked lossily, so that such a cycle directory is reported
+/// by [parse_cycle_num] instead of being skipped.
+fn is_cycle_dir(path: &Path) -> bool {
+    path.is_dir()
+        && path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(CYCLE_PREFIX))
 }
 
 #[derive(Clone, Debug, Serialize, PartialEq)]
-/// A lane consists of any number of cycles and any number of filters
+/// A lane consists of any number of cycles, filters and cluster location (locs) files
 pub struct Lane<P: AsRef<Path>> {
     pub lane_num: u8,
+    pub root: P,
     cycles: Vec<Cycle<P>>,
     filters: Vec<P>,
+    locs: Vec<P>,
 }
 
 impl<P> Lane<P>
@@ -101,52 +270,109 @@ where
     /// Attempt to read the provided directory as a Lane
     ///
     /// This will try to construct valid [Cycle] objects from matching directories in the provided
-    /// directory. It will also attempt to find all filter files in the directory.
+    /// directory. It will also attempt to find all filter and `.locs`/`.clocs` files in the
+    /// directory.
     pub fn from_path(path: P) -> Result<Lane<PathBuf>, SeqDirError> {
-        let lane_num = path
-            .as_ref()
-            .file_stem()
-            .ok_or_else(|| SeqDirError::MissingLaneDirs)?
-            .to_str()
-            .ok_or_else(|| SeqDirError::MissingLaneDirs)?
-            .strip_prefix('L')
-            .ok_or_else(|| SeqDirError::MissingLaneDirs)?
-            .parse::<u8>()?;
+        let lane_num = parse_lane_num(path.as_ref())?;
 
         // collect any cycles we can find. Error if we don't find any, or any are malformed.
         let cycles = read_dir(&path)?
             .filter_map(|p| p.ok())
             .map(|p| p.path())
-            .filter(|p| {
-                p.is_dir()
-                    && p.file_name()
-                        .unwrap_or(OsStr::new(""))
-                        .to_str()
-                        .unwrap_or("")
-                        .starts_with(CYCLE_PREFIX)
-            })
+            .filter(|p| is_cycle_dir(p))
             .map(|p| Cycle::from_path(p.as_path().to_owned()))
             .collect::<Result<Vec<Cycle<PathBuf>>, SeqDirError>>()?;
         if cycles.is_empty() {
             return Err(SeqDirError::MissingCycles);
         }
 
-        // now collect any filters. It's okay to not find any.
-        let filters: Vec<PathBuf> = read_dir(&path)?
+        // now collect any filters and locs. It's okay to not find any.
+        let (filters, locs): (Vec<PathBuf>, Vec<PathBuf>) = read_dir(&path)?
             .filter_map(|p| p.ok())
             .map(|p| p.path())
             .filter(|p| {
-                p.is_file() && p.extension().unwrap_or_else(|| OsStr::new("")) == FILTER_EXT
+                let ext = p.extension().unwrap_or_else(|| OsStr::new(""));
+                p.is_file() && (ext == FILTER_EXT || LOCS_EXTS.iter().any(|l| ext == *l))
             })
-            .collect();
+            .partition(|p| p.extension().is_some_and(|ext| ext == FILTER_EXT));
 
         Ok(Lane {
             lane_num,
+            root: path.as_ref().to_path_buf(),
             cycles,
             filters,
+            locs,
+        })
+    }
+
+    /// Read the provided directory as a Lane without reading its contents.
+    ///
+    /// Only the lane number is parsed from the directory name, so the returned Lane has no
+    /// cycles, filters or locs, and the directory need not exist. Use [from_path](Lane::from_path())
+    /// on [root](Lane::root) to read them on demand.
+    pub fn from_path_shallow(path: P) -> Result<Lane<PathBuf>, SeqDirError> {
+        Ok(Lane {
+            lane_num: parse_lane_num(path.as_ref())?,
+            root: path.as_ref().to_path_buf(),
+            cycles: Vec::new(),
+            filters: Vec::new(),
+            locs: Vec::new(),
         })
     }
 
+    /// Returns the paths of the lane's cycle directories, sorted by cycle number
+    pub fn cycle_dirs(&self) -> Vec<&Path> {
+        self.sorted_cycles()
+            .into_iter()
+            .map(|c| c.root.as_ref())
+            .collect()
+    }
+
+    /// Returns the lane's cycles sorted by cycle number.
+    ///
+    /// [cycles](Lane::cycles()) is left in directory listing order.
+    pub fn sorted_cycles(&self) -> Vec<&Cycle<P>> {
+        let mut cycles: Vec<&Cycle<P>> = self.cycles.iter().collect();
+        cycles.sort_by_key(|c| c.cycle_num);
+        cycles
+    }
+
+    /// Returns the lowest and highest cycle numbers in the lane, or None if it has no cycles
+    pub fn cycle_range(&self) -> Option<(u16, u16)> {
+        let nums = self.cycles.iter().map(|c| c.cycle_num);
+        Some((nums.clone().min()?, nums.max()?))
+    }
+
+    /// Returns the cycle numbers absent between 1 and the highest cycle in the lane, in
+    /// ascending order.
+    ///
+    /// Interrupted transfers can leave holes, e.g. C1 through C50 and then C52. Returns an
+    /// empty list if the cycles are contiguous. Cycles after the highest cycle present are not
+    /// reported, see [validate_cycles](crate::SeqDir::validate_cycles()) to compare against the
+    /// planned cycles.
+    pub fn missing_cycles(&self) -> Vec<u16> {
+        missing_cycle_nums(self.cycles.iter().map(|c| c.cycle_num))
+    }
+
+    /// Returns the number of (C)BCLs across all of the lane's cycles.
+    ///
+    /// A gzipped and a plain copy of the same (C)BCL, e.g. `L001_1.cbcl` and `L001_1.cbcl.gz`,
+    /// are counted once.
+    pub fn bcl_count(&self) -> usize {
+        self.cycles
+            .iter()
+            .flat_map(|c| c.bcls.iter())
+            .map(|b| {
+                if b.is_gzipped() {
+                    b.path().with_extension("")
+                } else {
+                    b.path().to_path_buf()
+                }
+            })
+            .collect::<HashSet<PathBuf>>()
+            .len()
+    }
+
     /// Returns a reference to the vector of cycles
     pub fn cycles(&self) -> &Vec<Cycle<P>> {
         &self.cycles
@@ -166,6 +392,33 @@ where
     pub fn iter_filters(&self) -> std::slice::Iter<'_, P> {
         self.filters.iter()
     }
+
+    /// Returns a reference to the vector of cluster location (`.locs` and `.clocs`) files
+    pub fn locs(&self) -> &Vec<P> {
+        &self.locs
+    }
+
+    /// Returns an iterator over the associated cluster location files
+    pub fn iter_locs(&self) -> std::slice::Iter<'_, P> {
+        self.locs.iter()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// Whether a lane is ready to be demultiplexed, see
+/// [lane_readiness](crate::SeqDir::lane_readiness())
+pub struct LaneReadiness {
+    pub lane_num: u8,
+    /// The number of cycles present on disk
+    pub observed_cycles: u16,
+    /// The number of cycles planned for the run
+    pub expected_cycles: u16,
+    /// The number of samples the sample sheet assigns to this lane
+    pub sample_count: usize,
+    /// False if the lane is on disk but the sample sheet has no samples for it
+    pub in_samplesheet: bool,
+    /// True if every expected cycle is present and the lane has samples to demultiplex
+    pub ready: bool,
 }
 
 /// Find outputs per-lane for a sequencing directory and construct `Lane` objects.
@@ -175,22 +428,364 @@ where
 /// 2. any identified lane directory has no cycle directories
 /// 3. any identified cycle directory has no (C)BCLs
 pub fn detect_lanes<P: AsRef<Path>>(dir: P) -> Result<Vec<Lane<PathBuf>>, SeqDirError> {
+    existing_lane_dirs(dir)
+        .map(Lane::from_path)
+        .collect::<Result<Vec<Lane<PathBuf>>, SeqDirError>>()
+}
+
+/// Like [detect_lanes], but reads the lanes in parallel on the rayon thread pool.
+///
+/// Worthwhile on flowcells with many lanes and cycles. Lanes are returned in the same order as
+/// [detect_lanes], and if more than one lane cannot be read, the error for the lowest-numbered
+/// lane is returned.
+#[cfg(feature = "rayon")]
+pub fn detect_lanes_parallel<P: AsRef<Path>>(dir: P) -> Result<Vec<Lane<PathBuf>>, SeqDirError> {
+    use rayon::prelude::*;
+
+    existing_lane_dirs(dir)
+        .collect::<Vec<PathBuf>>()
+        .into_par_iter()
+        .map(Lane::from_path)
+        .collect::<Vec<Result<Lane<PathBuf>, SeqDirError>>>()
+        .into_iter()
+        .collect()
+}
+
+/// Like [detect_lanes], but only constructs the lanes numbered in `lanes`.
+///
+/// Lanes are returned in ascending order regardless of the order requested. Requested lanes that
+/// do not exist on disk are skipped. Returns SeqDirError::InvalidLane if any requested lane
+/// number is not in 1-8.
+pub fn detect_lanes_subset<P: AsRef<Path>>(
+    dir: P,
+    lanes: &[u8],
+) -> Result<Vec<Lane<PathBuf>>, SeqDirError> {
+    if let Some(lane) = lanes
+        .iter()
+        .find(|&&l| l == 0 || usize::from(l) > LANES.len())
+    {
+        return Err(SeqDirError::InvalidLane(*lane));
+    }
+    let basecalls = basecalls_dir(dir.as_ref());
     LANES
         .iter()
-        .map(|l| dir.as_ref().join(BASECALLS).join(l))
+        .enumerate()
+        .filter(|(i, _)| lanes.iter().any(|&l| usize::from(l) == i + 1))
+        .map(|(_, l)| basecalls.join(l))
         .filter(|l| l.exists())
         .map(Lane::from_path)
         .collect::<Result<Vec<Lane<PathBuf>>, SeqDirError>>()
 }
 
+/// Determine the [BclFormat] of a sequencing directory.
+///
+/// Only the lowest-numbered cycle of the first lane is read; the other cycle directories are
+/// only listed, so this is much cheaper than [detect_lanes].
+pub fn detect_bcl_format<P: AsRef<Path>>(dir: P) -> Result<BclFormat, SeqDirError> {
+    let lane = existing_lane_dirs(dir)
+        .next()
+        .ok_or(SeqDirError::MissingLaneDirs)?;
+
+    let (_, first_cycle) = numbered_cycle_dirs(&lane)?
+        .into_iter()
+        .min_by_key(|(cycle_num, _)| *cycle_num)
+        .ok_or(SeqDirError::MissingCycles)?;
+
+    Ok(Cycle::from_path(first_cycle)?.bcl_format())
+}
+
+/// Find the BaseCalls directory of a sequencing directory.
+///
+/// NextSeq 1000/2000 write basecalls to `Analysis/N/Data/BaseCalls/`, in which case the
+/// highest-numbered analysis containing basecalls is used. Every other platform, or a
+/// directory whose platform cannot be determined, uses `Data/Intensities/BaseCalls/`.
+pub fn basecalls_dir<P: AsRef<Path>>(dir: P) -> PathBuf {
+    let dir = dir.as_ref();
+    let classic = dir.join(BASECALLS);
+    let is_nextseq2000 = parse_run_params(dir.join(RUN_PARAMS_XML))
+        .is_ok_and(|params| params.instrument_type == Platform::NextSeq2000);
+    if !is_nextseq2000 {
+        return classic;
+    }
+    latest_analysis_dir(dir, ANALYSIS_BASECALLS).unwrap_or(classic)
+}
+
+/// Find `Analysis/N/<subdir>` for the highest-numbered analysis `N` in which it is a directory
+pub(crate) fn latest_analysis_dir<P: AsRef<Path>>(dir: P, subdir: &str) -> Option<PathBuf> {
+    read_dir(dir.as_ref().join(ANALYSIS_DIR))
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.ok())
+        .filter_map(|p| {
+            let n = p.file_name().to_str()?.parse::<u32>().ok()?;
+            Some((n, p.path().join(subdir)))
+        })
+        .filter(|(_, path)| path.is_dir())
+        .max_by_key(|(n, _)| *n)
+        .map(|(_, path)| path)
+}
+
+/// Parse the lane number from the name of a lane directory, e.g. 1 from `L001`
+fn parse_lane_num(path: &Path) -> Result<u8, SeqDirError> {
+    Ok(path
+        .file_stem()
+        .ok_or(SeqDirError::MissingLaneDirs)?
+        .to_str()
+        .ok_or(SeqDirError::MissingLaneDirs)?
+        .strip_prefix('L')
+        .ok_or(SeqDirError::MissingLaneDirs)?
+        .parse::<u8>()?)
+}
+
+/// Returns an iterator over the lane directories that exist in a sequencing directory
+pub(crate) fn existing_lane_dirs<P: AsRef<Path>>(dir: P) -> impl Iterator<Item = PathBuf> {
+    let basecalls = basecalls_dir(dir);
+    LANES
+        .iter()
+        .map(move |l| basecalls.join(l))
+        .filter(|l| l.exists())
+}
+
+/// List the entries of the BaseCalls directory that are not lane directories.
+///
+/// Lane directory names must match exactly, so e.g. `L001_old` and `l001` are both listed, as is
+/// a regular file named `L001`. Returned sorted by path.
+pub fn unexpected_basecalls_entries<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>, SeqDirError> {
+    let basecalls = basecalls_dir(dir);
+    let entries = read_dir(&basecalls).map_err(|_| SeqDirError::NotFound(basecalls.clone()))?;
+    let mut unexpected: Vec<PathBuf> = entries
+        .filter_map(|p| p.ok())
+        .filter(|p| {
+            let is_lane = p.file_name().to_str().is_some_and(|n| LANES.contains(&n));
+            !(is_lane && p.path().is_dir())
+        })
+        .map(|p| p.path())
+        .collect();
+    unexpected.sort_unstable();
+    Ok(unexpected)
+}
+
+/// Probe the accessibility of each lane directory listed in a sequencing directory.
+///
+/// A lane is listed if an entry for it exists at all (including e.g. a dangling symlink or a
+/// stub left behind by tiered storage), and accessible if its contents can be read.
+pub(crate) fn probe_lane_dirs<P: AsRef<Path>>(dir: P) -> Vec<(u8, bool)> {
+    let basecalls = basecalls_dir(dir);
+    (1u8..)
+        .zip(LANES.iter())
+        .map(|(n, l)| (n, basecalls.join(l)))
+        .filter(|(_, l)| l.symlink_metadata().is_ok())
+        .map(|(n, l)| (n, read_dir(l).is_ok()))
+        .collect()
+}
+
+/// List the cycle directories of a lane along with their cycle numbers, without reading them
+fn numbered_cycle_dirs(lane: &Path) -> Result<Vec<(u16, PathBuf)>, SeqDirError> {
+    read_dir(lane)?
+        .filter_map(|p| p.ok())
+        .map(|p| p.path())
+        .filter(|p| is_cycle_dir(p))
+        .map(|p| Ok((parse_cycle_num(&p)?, p)))
+        .collect()
+}
+
+/// Get the directory of one lane, checking that `lane` is in 1-8 and the directory exists.
+fn lane_dir<P: AsRef<Path>>(dir: P, lane: u8) -> Result<PathBuf, SeqDirError> {
+    let name = (lane > 0)
+        .then(|| LANES.get(usize::from(lane) - 1))
+        .flatten()
+        .ok_or(SeqDirError::InvalidLane(lane))?;
+    let lane_dir = basecalls_dir(dir).join(name);
+    if !lane_dir.is_dir() {
+        return Err(SeqDirError::NotFound(lane_dir));
+    }
+    Ok(lane_dir)
+}
+
+/// List the cycle directories of one lane of a sequencing directory, sorted by cycle number.
+///
+/// Only directory names are inspected; their contents are not read. Returns
+/// SeqDirError::InvalidLane if `lane` is not in 1-8, and SeqDirError::NotFound if the lane
+/// directory does not exist.
+pub fn cycle_dirs<P: AsRef<Path>>(dir: P, lane: u8) -> Result<Vec<PathBuf>, SeqDirError> {
+    let mut cycles = numbered_cycle_dirs(&lane_dir(dir, lane)?)?;
+    cycles.sort_unstable_by_key(|(n, _)| *n);
+    Ok(cycles.into_iter().map(|(_, p)| p).collect())
+}
+
+/// Find the cycle numbers present in any lane of a sequencing directory.
+///
+/// Only cycle directory names are inspected, their contents are not read. The returned cycle
+/// numbers are sorted and deduplicated across lanes.
+pub fn observed_cycles<P: AsRef<Path>>(dir: P) -> Result<Vec<u16>, SeqDirError> {
+    let mut cycles = Vec::new();
+    for lane in existing_lane_dirs(dir) {
+        cycles.extend(numbered_cycle_dirs(&lane)?.into_iter().map(|(n, _)| n));
+    }
+    cycles.sort_unstable();
+    cycles.dedup();
+    Ok(cycles)
+}
+
+/// Find when each cycle directory was last modified.
+///
+/// For cycles present in several lanes, the earliest modification time is used, since that is
+/// closest to when the cycle was first written. Cycles whose modification time cannot be read
+/// are skipped. Returned in ascending order of cycle number.
+pub fn cycle_mtimes<P: AsRef<Path>>(dir: P) -> Result<Vec<(u16, SystemTime)>, SeqDirError> {
+    let mut mtimes: BTreeMap<u16, SystemTime> = BTreeMap::new();
+    for lane in existing_lane_dirs(dir) {
+        for (n, path) in numbered_cycle_dirs(&lane)? {
+            let Ok(mtime) = path.metadata().and_then(|m| m.modified()) else {
+                continue;
+            };
+            mtimes
+                .entry(n)
+                .and_modify(|t| *t = (*t).min(mtime))
+                .or_insert(mtime);
+        }
+    }
+    Ok(mtimes.into_iter().collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The part of a sequencing directory to search, see [files_modified_since]
+pub enum WalkScope {
+    /// The whole directory
+    Run,
+    /// The BaseCalls directory
+    BaseCalls,
+    /// The directory of a single lane
+    Lane(u8),
+}
+
+/// List the files under `scope` of a sequencing directory that were modified after `since`.
+///
+/// Every directory under `scope` is listed, and every regular file in them is stat-ed, because
+/// writing to an existing file does not update its parent's modification time, so no subtree
+/// can be skipped. On a large flowcell the BaseCalls directory holds the vast majority of
+/// files, so narrowing the scope to a single lane is much cheaper than walking the whole run.
+/// The walk descends at most `max_depth` levels below the root of the scope and skips symlinks,
+/// see [walk_files](crate::walk::walk_files()).
+///
+/// Returned sorted by path. Errors if the root of the scope cannot be read, or if a lane is out
+/// of range or missing.
+pub fn files_modified_since<P: AsRef<Path>>(
+    dir: P,
+    since: SystemTime,
+    scope: WalkScope,
+    max_depth: usize,
+) -> Result<Vec<PathBuf>, SeqDirError> {
+    let start = match scope {
+        WalkScope::Run => dir.as_ref().to_path_buf(),
+        WalkScope::BaseCalls => basecalls_dir(dir),
+        WalkScope::Lane(lane) => lane_dir(dir, lane)?,
+    };
+    let mut modified: Vec<PathBuf> = walk_files(&start, max_depth)
+        .map_err(|_| SeqDirError::NotFound(start.clone()))?
+        .into_iter()
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|mtime| mtime > since)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    modified.sort_unstable();
+    Ok(modified)
+}
+
+/// Sum the sizes, in bytes, of the (C)BCLs of `cycle` across every lane.
+///
+/// Lanes without the cycle contribute nothing, as do (C)BCLs whose size cannot be read.
+pub fn cycle_size<P: AsRef<Path>>(dir: P, cycle: u16) -> Result<u64, SeqDirError> {
+    let mut size = 0;
+    for lane in existing_lane_dirs(dir) {
+        for (_, path) in numbered_cycle_dirs(&lane)?
+            .into_iter()
+            .filter(|(n, _)| *n == cycle)
+        {
+            size += read_dir(path)?
+                .filter_map(|p| p.ok())
+                .filter(|p| Bcl::from_path(p.path()).is_some())
+                .filter_map(|p| p.metadata().ok())
+                .map(|m| m.len())
+                .sum::<u64>();
+        }
+    }
+    Ok(size)
+}
+
+/// Find the lowest cycle in `1..=total_cycles` that is missing or contains no (C)BCLs.
+///
+/// Each lane is checked independently and the minimum across lanes is returned, since lanes may
+/// progress at slightly different rates. If no lane directories exist, cycle 1 is incomplete.
+/// Returns None if every cycle is present in every lane.
+pub fn first_incomplete_cycle<P: AsRef<Path>>(
+    dir: P,
+    total_cycles: u16,
+) -> Result<Option<u16>, SeqDirError> {
+    let mut first_incomplete: Option<u16> = None;
+    let mut found_lane = false;
+    for lane in existing_lane_dirs(dir) {
+        found_lane = true;
+        let populated: Vec<u16> = numbered_cycle_dirs(&lane)?
+            .into_iter()
+            .filter(|(_, p)| has_bcls(p))
+            .map(|(n, _)| n)
+            .collect();
+        if let Some(cycle) = (1..=total_cycles).find(|c| !populated.contains(c)) {
+            first_incomplete = Some(first_incomplete.map_or(cycle, |f| f.min(cycle)));
+        }
+    }
+    if !found_lane && total_cycles > 0 {
+        return Ok(Some(1));
+    }
+    Ok(first_incomplete)
+}
+
+/// Returns the numbers absent from `1..=max(cycle_nums)`, in ascending order
+pub(crate) fn missing_cycle_nums<I: IntoIterator<Item = u16>>(cycle_nums: I) -> Vec<u16> {
+    let mut nums: Vec<u16> = cycle_nums.into_iter().collect();
+    nums.sort_unstable();
+    nums.dedup();
+    let Some(&max) = nums.last() else {
+        return Vec::new();
+    };
+    (1..max)
+        .filter(|n| nums.binary_search(n).is_err())
+        .collect()
+}
+
+/// Returns true if a cycle directory contains at least one (C)BCL
+fn has_bcls(cycle: &Path) -> bool {
+    read_dir(cycle).is_ok_and(|entries| {
+        entries
+            .filter_map(|p| p.ok())
+            .any(|p| Bcl::from_path(p.path()).is_some())
+    })
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::lane::detect_lanes;
+    use std::io::Read;
+    use std::path::Path;
+
+    use crate::lane::{
+        basecalls_dir, cycle_dirs, detect_bcl_format, detect_lanes, detect_lanes_subset,
+        files_modified_since, first_incomplete_cycle, missing_cycle_nums, observed_cycles,
+        parse_cycle_num, Bcl, BclFormat, Cycle, Lane, WalkScope,
+    };
+    use crate::test_dir::TestDir;
+    use crate::SeqDirError;
 
     const COMPLETE: &str = "test_data/seq_complete/";
     const FAILED: &str = "test_data/seq_failed/";
     const TRANSFERRING: &str = "test_data/seq_transferring/";
+    const PARTIAL: &str = "test_data/seq_partial/";
+    const NEXTSEQ2000: &str = "test_data/seq_nextseq2000/";
 
     #[test]
     fn no_cycles_fails() {
@@ -206,4 +801,416 @@ mod tests {
     fn completed_dir_succeeds() {
         detect_lanes(COMPLETE).unwrap();
     }
+
+    #[test]
+    fn nextseq2000_basecalls() {
+        assert_eq!(
+            basecalls_dir(NEXTSEQ2000),
+            Path::new(NEXTSEQ2000).join("Analysis/1/Data/BaseCalls/")
+        );
+        assert_eq!(
+            basecalls_dir(COMPLETE),
+            Path::new(COMPLETE).join("Data/Intensities/BaseCalls/")
+        );
+        let lanes = detect_lanes(NEXTSEQ2000).unwrap();
+        assert_eq!(lanes.len(), 2);
+        assert_eq!(lanes[1].cycles().len(), 8);
+        assert_eq!(first_incomplete_cycle(NEXTSEQ2000, 8).unwrap(), None);
+    }
+
+    #[test]
+    fn lane_subset() {
+        let lanes = detect_lanes_subset(COMPLETE, &[3, 1, 7]).unwrap();
+        assert_eq!(
+            lanes.iter().map(|l| l.lane_num).collect::<Vec<u8>>(),
+            vec![1, 3]
+        );
+        assert!(detect_lanes_subset(COMPLETE, &[]).unwrap().is_empty());
+        assert!(matches!(
+            detect_lanes_subset(COMPLETE, &[2, 9]),
+            Err(SeqDirError::InvalidLane(9))
+        ));
+        assert!(matches!(
+            detect_lanes_subset(COMPLETE, &[0]),
+            Err(SeqDirError::InvalidLane(0))
+        ));
+    }
+
+    #[test]
+    fn shallow_lane() {
+        let path = basecalls_dir(COMPLETE).join("L002");
+        let lane = Lane::from_path_shallow(&path).unwrap();
+        assert_eq!(lane.lane_num, 2);
+        assert_eq!(lane.root, path);
+        assert!(lane.cycles().is_empty());
+        assert!(lane.filters().is_empty());
+        assert_eq!(Lane::from_path(&path).unwrap().root, path);
+        assert!(Lane::from_path_shallow(Path::new("Data/C1.1")).is_err());
+    }
+
+    #[test]
+    fn lane_locs() {
+        let root = TestDir::new("lane_locs");
+        let lane = basecalls_dir(&root).join("L001");
+        std::fs::create_dir_all(lane.join("C1.1")).unwrap();
+        std::fs::write(lane.join("C1.1/L001_1.cbcl"), "").unwrap();
+        for name in [
+            "s_1_1101.locs",
+            "s_1_1102.clocs",
+            "s_1_1101.filter",
+            "notes.txt",
+        ] {
+            std::fs::write(lane.join(name), "").unwrap();
+        }
+        std::fs::create_dir_all(lane.join("dir.locs")).unwrap();
+        let lane = Lane::from_path(lane).unwrap();
+
+        let mut locs: Vec<&str> = lane
+            .iter_locs()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        locs.sort_unstable();
+        assert_eq!(locs, ["s_1_1101.locs", "s_1_1102.clocs"]);
+        assert_eq!(lane.filters().len(), 1);
+        assert!(lane.filters()[0].ends_with("s_1_1101.filter"));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_lanes() {
+        use super::detect_lanes_parallel;
+
+        let lanes = detect_lanes_parallel(COMPLETE).unwrap();
+        assert_eq!(lanes, detect_lanes(COMPLETE).unwrap());
+        assert_eq!(
+            lanes.iter().map(|l| l.lane_num).collect::<Vec<u8>>(),
+            vec![1, 2, 3, 4]
+        );
+
+        let root = TestDir::new("parallel_lanes");
+        let basecalls = basecalls_dir(&root);
+        std::fs::create_dir_all(basecalls.join("L001/C1.1")).unwrap();
+        std::fs::write(basecalls.join("L001/C1.1/L001_1.cbcl"), "").unwrap();
+        std::fs::create_dir_all(basecalls.join("L002/C3.1")).unwrap();
+        std::fs::create_dir_all(basecalls.join("L003/C5.1")).unwrap();
+        assert!(matches!(
+            detect_lanes_parallel(&root),
+            Err(SeqDirError::MissingBcls(3))
+        ));
+    }
+
+    #[test]
+    fn lists_cycle_dirs() {
+        let dirs = cycle_dirs(COMPLETE, 2).unwrap();
+        assert_eq!(dirs.len(), 42);
+        assert!(dirs[0].ends_with("L002/C1.1"));
+        assert!(dirs[9].ends_with("L002/C10.1"));
+        assert!(dirs[41].ends_with("L002/C42.1"));
+        let lanes = detect_lanes(COMPLETE).unwrap();
+        assert_eq!(lanes[1].cycle_dirs(), dirs);
+        assert!(matches!(
+            cycle_dirs(COMPLETE, 9),
+            Err(SeqDirError::InvalidLane(9))
+        ));
+        assert!(matches!(
+            cycle_dirs(FAILED, 1),
+            Err(SeqDirError::NotFound(..))
+        ));
+    }
+
+    #[test]
+    fn lists_modified_files() {
+        use crate::walk::DEFAULT_MAX_DEPTH;
+        use std::fs::File;
+        use std::time::{Duration, SystemTime};
+
+        let root = TestDir::new("modified_since");
+        let cycle = basecalls_dir(&root).join("L001/C1.1");
+        std::fs::create_dir_all(&cycle).unwrap();
+        std::fs::create_dir_all(basecalls_dir(&root).join("L002")).unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [("L001_1.cbcl", 0), ("L001_2.cbcl", 3600)] {
+            let file = File::create(cycle.join(name)).unwrap();
+            file.set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+        let run_info = File::create(root.join("RunInfo.xml")).unwrap();
+        run_info.set_modified(now).unwrap();
+
+        let since = now - Duration::from_secs(60);
+        assert_eq!(
+            files_modified_since(&root, since, WalkScope::Run, DEFAULT_MAX_DEPTH).unwrap(),
+            vec![cycle.join("L001_1.cbcl"), root.join("RunInfo.xml")]
+        );
+        assert_eq!(
+            files_modified_since(&root, since, WalkScope::Lane(1), DEFAULT_MAX_DEPTH).unwrap(),
+            vec![cycle.join("L001_1.cbcl")]
+        );
+        assert!(
+            files_modified_since(&root, since, WalkScope::Lane(2), DEFAULT_MAX_DEPTH)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            files_modified_since(&root, since, WalkScope::Lane(3), DEFAULT_MAX_DEPTH),
+            Err(SeqDirError::NotFound(..))
+        ));
+    }
+
+    #[test]
+    fn sorted_cycles() {
+        let lane = Lane::from_path(basecalls_dir(COMPLETE).join("L002")).unwrap();
+        let sorted: Vec<u16> = lane.sorted_cycles().iter().map(|c| c.cycle_num).collect();
+        assert_eq!(sorted, (1..=42).collect::<Vec<u16>>());
+        assert_eq!(lane.cycles().len(), 42);
+    }
+
+    #[test]
+    fn counts_bcls() {
+        let lane = Lane::from_path(basecalls_dir(COMPLETE).join("L001")).unwrap();
+        assert_eq!(lane.bcl_count(), 84);
+
+        let root = TestDir::new("bcl_count");
+        let cycle = root.join("L001/C1.1");
+        std::fs::create_dir_all(&cycle).unwrap();
+        for bcl in [
+            "s_1_1101.bcl",
+            "s_1_1101.bcl.gz",
+            "s_1_1102.bcl.gz",
+            "L001_1.cbcl",
+        ] {
+            std::fs::write(cycle.join(bcl), "").unwrap();
+        }
+        let lane = Lane::from_path(root.join("L001")).unwrap();
+        assert_eq!(lane.bcl_count(), 3);
+    }
+
+    #[test]
+    fn missing_cycles() {
+        let root = TestDir::new("missing_cycles");
+        let lane = root.join("L001");
+        for cycle in [1, 2, 5, 3] {
+            let dir = lane.join(format!("C{cycle}.1"));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("L001_1.cbcl"), "").unwrap();
+        }
+        assert_eq!(Lane::from_path(&lane).unwrap().missing_cycles(), [4]);
+
+        let lane = Lane::from_path(basecalls_dir(COMPLETE).join("L001")).unwrap();
+        assert!(lane.missing_cycles().is_empty());
+        assert_eq!(lane.cycle_range(), Some((1, 42)));
+        assert_eq!(missing_cycle_nums([3, 1, 5, 1]), [2, 4]);
+        assert!(missing_cycle_nums([1]).is_empty());
+        assert!(missing_cycle_nums([]).is_empty());
+    }
+
+    #[test]
+    fn observed_cycles_dedupes() {
+        assert_eq!(
+            observed_cycles(COMPLETE).unwrap(),
+            (1..=42).collect::<Vec<u16>>()
+        );
+        assert!(observed_cycles(TRANSFERRING).unwrap().is_empty());
+    }
+
+    #[test]
+    fn finds_first_incomplete_cycle() {
+        assert_eq!(first_incomplete_cycle(COMPLETE, 42).unwrap(), None);
+        assert_eq!(first_incomplete_cycle(COMPLETE, 50).unwrap(), Some(43));
+        assert_eq!(first_incomplete_cycle(PARTIAL, 151).unwrap(), Some(76));
+        assert_eq!(first_incomplete_cycle(TRANSFERRING, 10).unwrap(), Some(1));
+        assert_eq!(first_incomplete_cycle(FAILED, 10).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn parses_cycle_nums() {
+        assert_eq!(parse_cycle_num(Path::new("L001/C12.1")).unwrap(), 12);
+        assert!(matches!(
+            parse_cycle_num(Path::new("L001/X12.1")),
+            Err(SeqDirError::BadCycle(..))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_cycle_fails() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new("L001").join(OsStr::from_bytes(b"C1\xff.1"));
+        assert!(matches!(
+            parse_cycle_num(&path),
+            Err(SeqDirError::NonUtf8Path(..))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_cycle_dir_fails_lane() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = TestDir::new("non_utf8_cycle");
+        let lane = root.join("L001");
+        std::fs::create_dir_all(lane.join("C1.1")).unwrap();
+        std::fs::write(lane.join("C1.1/L001_1.cbcl"), "").unwrap();
+        std::fs::create_dir_all(lane.join(OsStr::from_bytes(b"C2\xff.1"))).unwrap();
+        assert!(matches!(
+            Lane::from_path(&lane),
+            Err(SeqDirError::NonUtf8Path(..))
+        ));
+    }
+
+    #[test]
+    fn gzipped_bcls() {
+        for (path, gzipped) in [
+            ("s_1_1101.bcl", false),
+            ("s_1_1101.bcl.gz", true),
+            ("L001_1.cbcl", false),
+            ("L001_1.cbcl.gz", true),
+        ] {
+            let bcl = Bcl::from_path(path).unwrap();
+            assert_eq!(bcl.is_gzipped(), gzipped, "{path}");
+            assert_eq!(bcl.path(), Path::new(path));
+        }
+        assert!(matches!(
+            Bcl::from_path("s_1_1101.bcl.gz"),
+            Some(Bcl::Bcl(..))
+        ));
+        assert_eq!(
+            serde_json::to_string(&Bcl::from_path("L001_1.cbcl.gz").unwrap()).unwrap(),
+            r#"{"CBcl":"L001_1.cbcl.gz"}"#
+        );
+    }
+
+    #[test]
+    fn compressed_cycles() {
+        let lanes = detect_lanes(PARTIAL).unwrap();
+        let cycle = &lanes[0].cycles()[0];
+        assert!(cycle.is_compressed());
+        assert!(cycle.is_consistent());
+        assert!(serde_json::to_string(cycle)
+            .unwrap()
+            .contains("\"is_compressed\":true"));
+
+        let lanes = detect_lanes(COMPLETE).unwrap();
+        assert!(lanes[0].iter_cycles().all(|c| !c.is_compressed()));
+
+        let root = TestDir::new("mixed_gz");
+        let mixed = root.join("C1.1");
+        std::fs::create_dir_all(&mixed).unwrap();
+        std::fs::write(mixed.join("s_1_1101.bcl"), "").unwrap();
+        std::fs::write(mixed.join("s_1_1102.bcl.gz"), "").unwrap();
+        let cycle = Cycle::from_path(mixed).unwrap();
+        assert!(!cycle.is_compressed());
+        assert!(!cycle.is_consistent());
+
+        // computed from the current bcls
+        let mut cycle = cycle;
+        cycle.bcls.retain(Bcl::is_gzipped);
+        assert!(cycle.is_compressed());
+        assert!(cycle.is_consistent());
+        cycle.bcls.clear();
+        assert!(!cycle.is_compressed());
+    }
+
+    #[test]
+    fn open_uncompressed_bcls() {
+        let lanes = detect_lanes(COMPLETE).unwrap();
+        let readers = lanes[0].cycles()[0]
+            .open_bcls()
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(readers.len(), 2);
+        for (_, mut reader) in readers {
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).unwrap();
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn open_compressed_bcls_unsupported() {
+        let lanes = detect_lanes(PARTIAL).unwrap();
+        let err = lanes[0].cycles()[0]
+            .open_bcls()
+            .next()
+            .unwrap()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn open_compressed_bcls() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let root = TestDir::new("open_gz");
+        let cycle_dir = root.join("C1.1");
+        std::fs::create_dir_all(&cycle_dir).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"basecalls").unwrap();
+        std::fs::write(cycle_dir.join("s_1_1101.bcl.gz"), encoder.finish().unwrap()).unwrap();
+        let cycle = Cycle::from_path(cycle_dir).unwrap();
+        let mut contents = String::new();
+        cycle
+            .open_bcls()
+            .next()
+            .unwrap()
+            .and_then(|(_, mut reader)| reader.read_to_string(&mut contents))
+            .unwrap();
+        assert_eq!(contents, "basecalls");
+    }
+
+    #[test]
+    fn cbcl_tile_count() {
+        let dir = TestDir::new("cbcl_header");
+        let mut header = Vec::new();
+        header.extend(1u16.to_le_bytes());
+        header.extend(49u32.to_le_bytes());
+        header.extend([2, 2]);
+        header.extend(2u32.to_le_bytes());
+        header.extend([0u32, 0, 1, 1].iter().flat_map(|b| b.to_le_bytes()));
+        header.extend(6u32.to_le_bytes());
+        header.extend(1101u32.to_le_bytes());
+        std::fs::write(dir.join("L001_1.cbcl"), &header).unwrap();
+        std::fs::write(dir.join("L001_2.cbcl"), &header[..16]).unwrap();
+        std::fs::write(dir.join("s_1_1101.bcl"), "").unwrap();
+        assert_eq!(
+            Bcl::CBcl(dir.join("L001_1.cbcl"))
+                .cbcl_tile_count()
+                .unwrap(),
+            Some(6)
+        );
+        assert_eq!(
+            Bcl::CBcl(dir.join("L001_2.cbcl"))
+                .cbcl_tile_count()
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(
+            Bcl::Bcl(dir.join("s_1_1101.bcl"))
+                .cbcl_tile_count()
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn detects_cbcl_format() {
+        assert_eq!(detect_bcl_format(COMPLETE).unwrap(), BclFormat::CBcl);
+        assert!(detect_bcl_format(TRANSFERRING).is_err());
+        assert!(detect_bcl_format(FAILED).is_err());
+
+        // later cycles are not read, so an incomplete one does not matter
+        let root = TestDir::new("bcl_format_first_cycle");
+        let lane = basecalls_dir(&root).join("L001");
+        std::fs::create_dir_all(lane.join("C1.1")).unwrap();
+        std::fs::write(lane.join("C1.1/s_1_1101.bcl.gz"), "").unwrap();
+        std::fs::create_dir_all(lane.join("C2.1")).unwrap();
+        assert_eq!(detect_bcl_format(&root).unwrap(), BclFormat::Bcl);
+    }
 }
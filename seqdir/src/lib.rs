@@ -1,28 +1,105 @@
-use serde::Serialize;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use std::convert::AsRef;
+use std::fs::{read_dir, File};
 use std::num::ParseIntError;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod completion;
+pub mod demux;
+#[cfg(feature = "watch")]
+pub mod discovery;
+pub mod interop;
 pub mod lane;
+pub mod logs;
 pub mod manager;
+pub mod metadata;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+pub mod multi;
+pub mod recipe;
 pub mod run_completion;
+pub mod run_info;
+pub mod run_params;
+pub mod samplesheet;
+pub mod sentinel;
+pub mod snapshot;
+pub mod summary;
+#[cfg(test)]
+mod test_dir;
+pub mod validate;
+pub mod walk;
 
+pub use completion::CompletionCriteria;
+pub use completion::CompletionRule;
+pub use completion::StatusRequirement;
+pub use demux::DemuxStat;
+pub use interop::InterOpPreflight;
+pub use lane::BclFormat;
+pub use lane::LaneReadiness;
+pub use lane::WalkScope;
+pub use manager::AccessLevel;
+pub use manager::AvailabilityProbe;
+pub use manager::Clock;
 pub use manager::DirManager;
+pub use manager::RealClock;
 pub use manager::SeqDirState;
+pub use manager::SeqDirStateKind;
+pub use manager::StateWithAge;
+pub use manager::TransferPhase;
+pub use metadata::RunMetadata;
+pub use multi::MultiDirManager;
 pub use run_completion::CompletionStatus;
 pub use run_completion::Message;
+pub use run_info::Flowcell;
+pub use run_info::FlowcellLayout;
+pub use run_info::RunInfo;
+pub use run_params::Consumables;
+pub use run_params::Platform;
+pub use run_params::RunParameters;
+pub use run_params::SoftwareVersions;
+pub use samplesheet::DemuxTool;
+pub use samplesheet::Sample;
+pub use samplesheet::SampleSheet;
+pub use sentinel::CopyComplete;
+pub use sentinel::LockInfo;
+pub use snapshot::SeqDirSnapshot;
+pub use summary::RunSummary;
+pub use validate::CycleValidation;
+pub use validate::HealthIssue;
+pub use validate::HealthReport;
+pub use validate::LaneCycles;
+pub use validate::MetadataStatus;
 
-use crate::run_completion::parse_run_completion;
+use crate::lane::{detect_bcl_format, detect_lanes, first_incomplete_cycle, observed_cycles};
+use crate::run_completion::{parse_run_completion, parse_run_completion_reader};
+use crate::run_info::parse_run_info;
+use crate::run_params::{parse_run_params, platform_from_serial};
+use crate::samplesheet::{compare_reads, parse_samplesheet, ReadDiscrepancy};
+use crate::sentinel::{parse_copy_complete, parse_rta_version};
+use crate::snapshot::LaneSnapshot;
+use crate::validate::validate_xml;
 
 pub const COPY_COMPLETE_TXT: &str = "CopyComplete.txt";
 pub const RTA_COMPLETE_TXT: &str = "RTAComplete.txt";
 pub const SEQUENCE_COMPLETE_TXT: &str = "SequenceComplete.txt";
 pub const SAMPLESHEET_CSV: &str = "SampleSheet.csv";
+/// Known sample sheet names, in order of preference. Matched case-insensitively.
+pub const SAMPLESHEET_VARIANTS: [&str; 2] = [SAMPLESHEET_CSV, "SampleSheet_v2.csv"];
 pub const RUN_INFO_XML: &str = "RunInfo.xml";
 pub const RUN_COMPLETION_STATUS_XML: &str = "RunCompletionStatus.xml";
+/// Per-side completion status files written by some software for dual-flowcell instruments
+pub const SIDE_COMPLETION_STATUS_XML: [(char, &str); 2] = [
+    ('A', "RunCompletionStatus_A.xml"),
+    ('B', "RunCompletionStatus_B.xml"),
+];
 pub const RUN_PARAMS_XML: &str = "RunParameters.xml";
+pub const ANALYSIS_DIR: &str = "Analysis";
 
 #[derive(Debug, Error)]
 #[non_exhaustive]
@@ -43,11 +120,95 @@ pub enum SeqDirError {
     ParseIntError(#[from] ParseIntError),
     #[error("unexpected run completion status: {0}")]
     CompletionStatus(CompletionStatus),
+    #[error("{0} is empty")]
+    EmptyDir(PathBuf),
+    #[error("path is not valid UTF-8: {0}")]
+    NonUtf8Path(PathBuf),
+    #[error("{0} does not satisfy the completion rule")]
+    NotComplete(PathBuf),
+    #[error("lane {0} is out of range, expected 1-8")]
+    InvalidLane(u8),
+    #[error("malformed sample sheet: {0}")]
+    SampleSheet(String),
+    #[error("malformed demultiplexing report: {0}")]
+    DemuxReport(String),
+    #[error("RunInfo.xml does not describe the flowcell layout")]
+    MissingFlowcellLayout,
+    #[error("read {0} is not listed in RunInfo.xml")]
+    InvalidRead(u8),
+    #[error("timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("{0} is not a file name in the root of the directory")]
+    InvalidFileName(String),
+}
+
+impl From<SeqDirError> for std::io::Error {
+    /// Convert to an [io::Error](std::io::Error), preserving the display message.
+    ///
+    /// IoErrors are passed through unchanged. Missing files and directories map to
+    /// `ErrorKind::NotFound`, timeouts map to `ErrorKind::TimedOut`, and everything else maps to
+    /// `ErrorKind::InvalidData`.
+    fn from(value: SeqDirError) -> Self {
+        use std::io::{Error, ErrorKind};
+        let kind = match value {
+            SeqDirError::IoError(e) => return e,
+            SeqDirError::NotFound(..) | SeqDirError::EmptyDir(..) => ErrorKind::NotFound,
+            SeqDirError::Timeout(..) => ErrorKind::TimedOut,
+            SeqDirError::MissingLaneDirs
+            | SeqDirError::MissingCycles
+            | SeqDirError::MissingBcls(..)
+            | SeqDirError::BadCycle(..)
+            | SeqDirError::ParseIntError(..)
+            | SeqDirError::CompletionStatus(..)
+            | SeqDirError::NonUtf8Path(..)
+            | SeqDirError::NotComplete(..)
+            | SeqDirError::InvalidLane(..)
+            | SeqDirError::InvalidRead(..)
+            | SeqDirError::InvalidFileName(..)
+            | SeqDirError::SampleSheet(..)
+            | SeqDirError::DemuxReport(..)
+            | SeqDirError::MissingFlowcellLayout => ErrorKind::InvalidData,
+        };
+        Error::new(kind, value.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+/// Configuration for how a [SeqDir] determines its status.
+///
+/// Construct with `SeqDirConfig { trust_completion_status: false, ..Default::default() }`.
+pub struct SeqDirConfig {
+    /// Whether RunCompletionStatus.xml is consulted when determining failure (default: true).
+    ///
+    /// Some instruments write a spurious ExceptionEndedEarly status even for runs that completed
+    /// and transferred successfully. When false, [is_failed](SeqDir::is_failed()) always returns
+    /// `Ok(false)` and [from_completed](SeqDir::from_completed()) relies on CopyComplete.txt alone.
+    pub trust_completion_status: bool,
+    /// How availability of the directory is determined (default: [AvailabilityProbe::Exists]).
+    pub availability_probe: AvailabilityProbe,
+    /// Whether to look for a completion status in RunParameters.xml when RunCompletionStatus.xml
+    /// is absent (default: false).
+    ///
+    /// Some platforms that do not write RunCompletionStatus.xml record the status there instead.
+    /// See [get_completion_status](SeqDir::get_completion_status()).
+    pub completion_from_run_params: bool,
+}
+
+impl Default for SeqDirConfig {
+    fn default() -> Self {
+        SeqDirConfig {
+            trust_completion_status: true,
+            availability_probe: AvailabilityProbe::default(),
+            completion_from_run_params: false,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, PartialEq)]
 /// An Illumina sequencing directory
 pub struct SeqDir {
+    #[serde(serialize_with = "serialize_path_lossy")]
     root: PathBuf,
     #[serde(skip)]
     samplesheet: PathBuf,
@@ -57,6 +218,72 @@ pub struct SeqDir {
     run_params: PathBuf,
     #[serde(skip)]
     run_completion: PathBuf,
+    #[serde(skip)]
+    config: SeqDirConfig,
+    #[cfg(any(test, feature = "test-util"))]
+    #[serde(skip)]
+    mock: Option<mock::MockBuilder>,
+    #[cfg(feature = "archive")]
+    #[serde(skip)]
+    archive: Option<std::sync::Arc<archive::ArchiveIndex>>,
+}
+
+/// Only the root is serialized, the remaining paths are rebuilt from it as in
+/// [from_path](SeqDir::from_path()). The [SeqDirConfig] is not serialized, so a deserialized
+/// SeqDir uses the default configuration.
+impl<'de> Deserialize<'de> for SeqDir {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Root {
+            root: PathBuf,
+        }
+        Root::deserialize(deserializer).map(|Root { root }| SeqDir::from_dir_unchecked(root))
+    }
+}
+
+/// Serialize a path as a string on every platform, replacing invalid UTF-8 with U+FFFD
+fn serialize_path_lossy<S: serde::Serializer>(
+    path: &Path,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
+/// Trim a version string and strip any leading `v`, e.g. `v3.4.4` to `3.4.4`
+fn normalize_version(version: &str) -> String {
+    let version = version.trim();
+    version
+        .strip_prefix(['v', 'V'])
+        .unwrap_or(version)
+        .to_string()
+}
+
+/// Split a run folder name into its instrument, run number and bare flowcell id
+fn parse_run_folder_name(name: &str) -> Option<(String, u32, String)> {
+    let [_, instrument, run_number, flowcell] = name.split('_').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let run_number = run_number.parse().ok()?;
+    let has_sides = matches!(
+        platform_from_serial(instrument),
+        Some(Platform::NovaSeq | Platform::HiSeq)
+    );
+    let flowcell = match flowcell.strip_prefix(['A', 'B']) {
+        Some(id) if has_sides && !id.is_empty() => id,
+        _ => flowcell,
+    };
+    Some((instrument.to_string(), run_number, flowcell.to_string()))
+}
+
+/// Percent-encode everything but ASCII alphanumerics, `-` and `.`
+fn escape_key_component(component: &str) -> String {
+    component
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' => char::from(b).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
 }
 
 impl SeqDir {
@@ -67,45 +294,207 @@ impl SeqDir {
     /// `from_completed`.
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, SeqDirError> {
         if path.as_ref().is_dir() {
-            Ok(SeqDir {
-                root: path.as_ref().to_path_buf(),
-                samplesheet: path.as_ref().join(SAMPLESHEET_CSV),
-                run_info: path.as_ref().join(RUN_INFO_XML),
-                run_params: path.as_ref().join(RUN_PARAMS_XML),
-                run_completion: path.as_ref().join(RUN_COMPLETION_STATUS_XML),
-            })
+            Ok(Self::from_dir_unchecked(path.as_ref().to_path_buf()))
         } else {
             Err(SeqDirError::NotFound(path.as_ref().to_path_buf()))
         }
     }
 
+    /// Create a new SeqDir from an entry returned by [read_dir](std::fs::read_dir()).
+    ///
+    /// Like `from_path`, but uses the file type already known from listing the parent
+    /// directory, which typically saves a stat per entry. Symlinks are followed, which does
+    /// require a stat.
+    pub fn from_dir_entry(entry: &std::fs::DirEntry) -> Result<Self, SeqDirError> {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() || (file_type.is_symlink() && path.is_dir()) {
+            Ok(Self::from_dir_unchecked(path))
+        } else {
+            Err(SeqDirError::NotFound(path))
+        }
+    }
+
+    /// Construct a SeqDir rooted at `root` without checking that it is a directory
+    fn from_dir_unchecked(root: PathBuf) -> Self {
+        SeqDir {
+            samplesheet: root.join(SAMPLESHEET_CSV),
+            run_info: root.join(RUN_INFO_XML),
+            run_params: root.join(RUN_PARAMS_XML),
+            run_completion: root.join(RUN_COMPLETION_STATUS_XML),
+            root,
+            config: SeqDirConfig::default(),
+            #[cfg(any(test, feature = "test-util"))]
+            mock: None,
+            #[cfg(feature = "archive")]
+            archive: None,
+        }
+    }
+
+    /// Create a read-only SeqDir from a run archived as an uncompressed tarball.
+    ///
+    /// The archive is indexed once (see [ArchiveIndex](archive::ArchiveIndex)), after which
+    /// sentinel files, RunInfo.xml, the completion status, and
+    /// [lane_numbers](SeqDir::lane_numbers()) are served from the archive without extracting
+    /// it. `root` is the path to the archive, and the directory is available as long as the
+    /// archive exists. Other queries, in particular those that walk lanes and cycles, still
+    /// expect a directory and will fail.
+    ///
+    /// Returns [SeqDirError::NotFound] if `path` is not a file.
+    #[cfg(feature = "archive")]
+    pub fn from_archive<P: AsRef<Path>>(path: P) -> Result<Self, SeqDirError> {
+        if !path.as_ref().is_file() {
+            return Err(SeqDirError::NotFound(path.as_ref().to_path_buf()));
+        }
+        let index = archive::ArchiveIndex::open(&path)?;
+        Ok(SeqDir {
+            archive: Some(std::sync::Arc::new(index)),
+            ..Self::from_dir_unchecked(path.as_ref().to_path_buf())
+        })
+    }
+
+    /// Returns the index of the archive, if the SeqDir was created with
+    /// [from_archive](SeqDir::from_archive())
+    #[cfg(feature = "archive")]
+    pub fn archive_index(&self) -> Option<&archive::ArchiveIndex> {
+        self.archive.as_deref()
+    }
+
+    /// Create a new SeqDir from a directory that contains at least one entry.
+    ///
+    /// Like `from_path`, but returns SeqDirError::EmptyDir if the directory is empty. An empty
+    /// directory is most likely a run folder that has been created but not yet written to.
+    pub fn from_run_dir<P: AsRef<Path>>(path: P) -> Result<Self, SeqDirError> {
+        let seq_dir = Self::from_path(&path)?;
+        if seq_dir.is_empty()? {
+            return Err(SeqDirError::EmptyDir(seq_dir.root().to_owned()));
+        }
+        Ok(seq_dir)
+    }
+
     /// Create a new SeqDir from a completed sequencing directory.
     ///
     /// Errors if the sequencing directory is not complete.
     /// Completion is determined by the following:
     /// 1. CopyComplete.txt is present
     /// 2. RunCompletionStatus (if present) is CompletedAsPlanned
+    ///
+    /// The lane outputs are not inspected. See [from_completed_verified](SeqDir::from_completed_verified()).
     pub fn from_completed<P: AsRef<Path>>(path: P) -> Result<Self, SeqDirError> {
-        let seq_dir = Self::from_path(&path)?;
-        seq_dir
-            .is_copy_complete()
-            .then(|| Ok::<(), SeqDirError>(()))
-            .ok_or_else(|| SeqDirError::NotFound(seq_dir.root().join(COPY_COMPLETE_TXT)))??;
+        Self::from_completed_with(path, &CompletionCriteria::default())
+    }
 
+    /// Create a new SeqDir from a completed sequencing directory, using the provided config.
+    ///
+    /// Like `from_completed`, but RunCompletionStatus is only checked if
+    /// `config.trust_completion_status` is true.
+    pub fn from_completed_with_config<P: AsRef<Path>>(
+        path: P,
+        config: SeqDirConfig,
+    ) -> Result<Self, SeqDirError> {
+        let seq_dir = Self::from_path(&path)?.with_config(config);
         // If RunCompletionStatus exists, verify it, but cannot rely on this
         // since not all platforms output this file
-        match seq_dir.get_completion_status() {
-            None => {}
-            Some(Ok(status)) => match status {
-                CompletionStatus::CompletedAsPlanned(..) => {}
-                _ => return Err(SeqDirError::CompletionStatus(status)),
+        let criteria = CompletionCriteria {
+            status: if seq_dir.config.trust_completion_status {
+                StatusRequirement::IfPresent
+            } else {
+                StatusRequirement::Ignore
             },
-            Some(Err(e)) => return Err(e),
+            ..Default::default()
         };
+        criteria.check(&seq_dir)?;
+        Ok(seq_dir)
+    }
+
+    /// Create a new SeqDir from a sequencing directory that meets `criteria`.
+    ///
+    /// `from_completed` is equivalent to passing the default [CompletionCriteria]. Errors as
+    /// described by [check](CompletionCriteria::check()).
+    pub fn from_completed_with<P: AsRef<Path>>(
+        path: P,
+        criteria: &CompletionCriteria,
+    ) -> Result<Self, SeqDirError> {
+        let seq_dir = Self::from_path(&path)?;
+        criteria.check(&seq_dir)?;
+        Ok(seq_dir)
+    }
 
+    /// Create a new SeqDir from a completed sequencing directory whose outputs are present.
+    ///
+    /// Stricter than `from_completed`: in addition to the sentinel checks, at least one lane
+    /// must exist and every lane must contain cycles with (C)BCLs. Some transfer configurations
+    /// write CopyComplete.txt before BaseCalls has finished syncing, which `from_completed` will
+    /// not catch. Returns SeqDirError::MissingLaneDirs if no lanes are found.
+    ///
+    /// Reads every lane and cycle directory, so this is considerably more expensive.
+    pub fn from_completed_verified<P: AsRef<Path>>(path: P) -> Result<Self, SeqDirError> {
+        let seq_dir = Self::from_completed(path)?;
+        if detect_lanes(seq_dir.root())?.is_empty() {
+            return Err(SeqDirError::MissingLaneDirs);
+        }
         Ok(seq_dir)
     }
 
+    /// Create a new SeqDir from a directory that satisfies `rule`.
+    ///
+    /// Returns SeqDirError::NotComplete if the rule is not satisfied. See [CompletionRule].
+    pub fn from_completed_with_rule<P: AsRef<Path>>(
+        path: P,
+        rule: &CompletionRule,
+    ) -> Result<Self, SeqDirError> {
+        let seq_dir = Self::from_path(&path)?;
+        if rule.evaluate(&seq_dir)? {
+            Ok(seq_dir)
+        } else {
+            Err(SeqDirError::NotComplete(seq_dir.root().to_owned()))
+        }
+    }
+
+    /// Replace the root with its canonical, absolute form, re-deriving the paths of metadata files.
+    ///
+    /// After canonicalizing, handles to the same run compare equal and serialize identically,
+    /// regardless of how the path was originally spelled. If the root cannot be canonicalized
+    /// (e.g. it no longer exists), an error is returned and self is left unchanged.
+    ///
+    /// The [SeqDirConfig] is kept, as is the index of a SeqDir created with `from_archive`.
+    pub fn canonicalize(&mut self) -> std::io::Result<()> {
+        let root = std::fs::canonicalize(&self.root)?;
+        self.derive_paths(root);
+        Ok(())
+    }
+
+    /// Move the SeqDir to `new_root`, re-deriving the paths of metadata files.
+    ///
+    /// `new_root` is not required to exist. Moving a SeqDir created with `from_archive` drops its
+    /// archive index, so that files are read from `new_root` rather than from the old archive.
+    pub fn set_root(&mut self, new_root: PathBuf) {
+        #[cfg(feature = "archive")]
+        {
+            self.archive = None;
+        }
+        self.derive_paths(new_root);
+    }
+
+    /// Set the root to `root` and rebuild the paths of metadata files from it
+    fn derive_paths(&mut self, root: PathBuf) {
+        self.samplesheet = root.join(SAMPLESHEET_CSV);
+        self.run_info = root.join(RUN_INFO_XML);
+        self.run_params = root.join(RUN_PARAMS_XML);
+        self.run_completion = root.join(RUN_COMPLETION_STATUS_XML);
+        self.root = root;
+    }
+
+    /// Replace the [SeqDirConfig] of this SeqDir
+    pub fn with_config(self, config: SeqDirConfig) -> Self {
+        SeqDir { config, ..self }
+    }
+
+    /// Returns reference to the [SeqDirConfig] of this SeqDir
+    pub fn config(&self) -> &SeqDirConfig {
+        &self.config
+    }
+
     /// Try to get the root of the sequencing directory.
     ///
     /// Returns SeqDirError::NotFound if directory is inaccessible.
@@ -116,19 +505,95 @@ impl SeqDir {
             .ok_or_else(|| SeqDirError::NotFound(self.root().to_owned()))
     }
 
+    /// Returns true if the root directory contains no entries.
+    ///
+    /// Errors if the root directory cannot be read.
+    pub fn is_empty(&self) -> std::io::Result<bool> {
+        Ok(read_dir(self.root())?.next().is_none())
+    }
+
+    /// Returns true if `name` exists, relative to the root of the sequencing directory.
+    pub(crate) fn has_file(&self, name: &str) -> bool {
+        #[cfg(any(test, feature = "test-util"))]
+        if let Some(mock) = &self.mock {
+            return mock.has_file(name);
+        }
+        #[cfg(feature = "archive")]
+        if let Some(archive) = &self.archive {
+            return archive.contains_file(name);
+        }
+        self.root().join(name).exists()
+    }
+
     /// Returns true if CopyComplete.txt exists.
     pub fn is_copy_complete(&self) -> bool {
-        self.root().join(COPY_COMPLETE_TXT).exists()
+        self.has_file(COPY_COMPLETE_TXT)
+    }
+
+    /// Attempt to read the contents of CopyComplete.txt
+    ///
+    /// Returns None if CopyComplete.txt does not exist. See [CopyComplete] for how the completion
+    /// time is determined.
+    pub fn copy_complete_info(&self) -> Result<Option<CopyComplete>, SeqDirError> {
+        if !self.is_copy_complete() {
+            return Ok(None);
+        }
+        #[cfg(any(test, feature = "test-util"))]
+        if self.mock.is_some() {
+            return Ok(Some(CopyComplete {
+                completed_at: None,
+                raw: String::new(),
+            }));
+        }
+        #[cfg(feature = "archive")]
+        if let Some(archive) = &self.archive {
+            let mut raw = String::new();
+            if let Some(reader) = archive.open_file(COPY_COMPLETE_TXT) {
+                std::io::Read::read_to_string(&mut reader?, &mut raw)?;
+            }
+            let completed_at = sentinel::parse_timestamp(&raw).or_else(|| {
+                let mtime = archive.mtime(COPY_COMPLETE_TXT)?;
+                chrono::DateTime::from_timestamp(i64::try_from(mtime).ok()?, 0)
+            });
+            return Ok(Some(CopyComplete { completed_at, raw }));
+        }
+        parse_copy_complete(self.root().join(COPY_COMPLETE_TXT)).map(Some)
+    }
+
+    /// Look for a lock file named `name` in the root of the sequencing directory.
+    ///
+    /// Returns None if no such lock exists. The lock's age is measured from its modification
+    /// time; locks are otherwise opaque to this crate.
+    ///
+    /// Errors with [InvalidFileName](SeqDirError::InvalidFileName) if `name` is not a single path
+    /// component, so that a lock outside the sequencing directory can never be read.
+    pub fn processing_lock(&self, name: &str) -> Result<Option<LockInfo>, SeqDirError> {
+        let mut components = Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(std::path::Component::Normal(_)), None) => {}
+            _ => return Err(SeqDirError::InvalidFileName(name.to_string())),
+        }
+        sentinel::read_lock(self.root().join(name))
+    }
+
+    /// Returns true if a lock file named `name` exists and is older than `max_age`.
+    ///
+    /// A missing lock is not stale. Errors under the same conditions as
+    /// [processing_lock](SeqDir::processing_lock()).
+    pub fn is_lock_stale(&self, name: &str, max_age: Duration) -> Result<bool, SeqDirError> {
+        Ok(self
+            .processing_lock(name)?
+            .is_some_and(|lock| lock.age > max_age))
     }
 
     /// Returns true if RTAComplete.txt exists.
     pub fn is_rta_complete(&self) -> bool {
-        self.root().join(RTA_COMPLETE_TXT).exists()
+        self.has_file(RTA_COMPLETE_TXT)
     }
 
     /// Returns true if SequenceComplete.txt exists.
     pub fn is_sequence_complete(&self) -> bool {
-        self.root().join(SEQUENCE_COMPLETE_TXT).exists()
+        self.has_file(SEQUENCE_COMPLETE_TXT)
     }
 
     /// Get an arbitrary file rooted at the base of the sequencing directory.
@@ -143,34 +608,152 @@ impl SeqDir {
     }
 
     /// Returns true if the root directory is readable.
+    ///
+    /// Also requires the configured [AvailabilityProbe] to succeed.
     pub fn is_available(&self) -> bool {
-        self.try_root().is_ok()
+        #[cfg(any(test, feature = "test-util"))]
+        if let Some(mock) = &self.mock {
+            return mock.is_available();
+        }
+        #[cfg(feature = "archive")]
+        if self.archive.is_some() {
+            return self.root().is_file();
+        }
+        self.try_root().is_ok() && self.config.availability_probe.probe(self.root())
+    }
+
+    /// Probe how much of the directory the current process may access.
+    ///
+    /// A directory that can be seen but not read (e.g. on a locked-down mount) will fail most
+    /// other methods with permission errors; checking this once up front gives a single clear
+    /// signal instead. See [AccessLevel::of] for what is probed.
+    pub fn access_level(&self) -> AccessLevel {
+        #[cfg(any(test, feature = "test-util"))]
+        if let Some(mock) = &self.mock {
+            return match mock.is_available() {
+                true => AccessLevel::Full,
+                false => AccessLevel::Denied,
+            };
+        }
+        #[cfg(feature = "archive")]
+        if self.archive.is_some() {
+            return match File::open(self.root()) {
+                Ok(..) => AccessLevel::Full,
+                Err(..) => AccessLevel::Denied,
+            };
+        }
+        AccessLevel::of(self.root())
     }
 
     /// Returns true if the root directory cannot be read
+    ///
+    /// Convenience method, inverts `is_available`
     pub fn is_unavailable(&self) -> bool {
-        self.try_root().is_err()
+        !self.is_available()
     }
 
     /// Attempt to parse RunCompletionStatus.xml and return a
     /// Option<Result<[CompletionStatus]>>
+    ///
+    /// The file is opened exactly once, and returns None if it cannot be opened or is not a
+    /// regular file. This avoids racing an instrument that replaces the file between an
+    /// existence check and the read.
+    ///
+    /// If RunCompletionStatus.xml is absent and `config.completion_from_run_params` is set, the
+    /// status recorded in RunParameters.xml (if any) is used instead. See
+    /// [RunParameters::completion_status].
     pub fn get_completion_status(&self) -> Option<Result<CompletionStatus, SeqDirError>> {
-        Some(parse_run_completion(self.run_completion_status()?).map_err(SeqDirError::from))
+        #[cfg(any(test, feature = "test-util"))]
+        if let Some(mock) = &self.mock {
+            return mock.get_completion_status().map(Ok);
+        }
+        #[cfg(feature = "archive")]
+        if let Some(archive) = &self.archive {
+            return archive
+                .open_file(RUN_COMPLETION_STATUS_XML)
+                .map(|reader| Ok(parse_run_completion_reader(reader?)?));
+        }
+        match File::open(&self.run_completion) {
+            Ok(handle) if handle.metadata().is_ok_and(|m| m.is_file()) => {
+                Some(parse_run_completion_reader(handle).map_err(SeqDirError::from))
+            }
+            _ if self.config.completion_from_run_params => {
+                self.read_run_params().ok()?.completion_status().map(Ok)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the completion status of each side of a dual-flowcell run.
+    ///
+    /// Each side may complete independently, which some software records in
+    /// [per-side files](SIDE_COMPLETION_STATUS_XML) in the format of RunCompletionStatus.xml.
+    /// Sides without such a file are omitted. If neither file exists, falls back to
+    /// [get_completion_status](SeqDir::get_completion_status()), labelled with the side of the
+    /// [flowcell](SeqDir::flowcell()), or `'A'` if the side is unknown (e.g. single-flowcell
+    /// instruments). Returns an empty Vec if there is no completion status at all.
+    ///
+    /// Errors if a completion status file cannot be parsed.
+    pub fn side_completion(&self) -> Result<Vec<(char, CompletionStatus)>, SeqDirError> {
+        let sides = SIDE_COMPLETION_STATUS_XML
+            .iter()
+            .map(|(side, name)| (*side, self.root().join(name)))
+            .filter(|(_, path)| path.is_file())
+            .map(|(side, path)| Ok((side, parse_run_completion(path)?)))
+            .collect::<Result<Vec<_>, SeqDirError>>()?;
+        if !sides.is_empty() {
+            return Ok(sides);
+        }
+        let Some(status) = self.get_completion_status().transpose()? else {
+            return Ok(Vec::new());
+        };
+        let side = self.flowcell().ok().and_then(|f| f.side).unwrap_or('A');
+        Ok(vec![(side, status)])
     }
 
     /// Attempt to determine if a run has failed sequencing.
     ///
-    /// Uses RunCompletionStatus.xml. If RunCompletionStatus is not available, returns false.
+    /// Uses RunCompletionStatus.xml. If RunCompletionStatus is not available, returns false, see
+    /// [CompletionStatus::is_failed] for which statuses count as failure.
     /// unlike other `is_` library methods, this is fallible because it must parse a file.
+    /// Always returns `Ok(false)` if the [SeqDirConfig] does not trust the completion status.
     pub fn is_failed(&self) -> Result<bool, SeqDirError> {
+        if !self.config.trust_completion_status {
+            return Ok(false);
+        }
         match self.get_completion_status() {
             None => Ok(false),
             Some(Err(e)) => Err(e),
-            Some(Ok(res)) => match res {
-                CompletionStatus::CompletedAsPlanned(..) => Ok(false),
-                _ => Ok(true),
-            },
+            Some(Ok(status)) => Ok(status.is_failed()),
+        }
+    }
+
+    /// Summarize why a run failed, for alerting.
+    ///
+    /// Combines the ErrorDescription of RunCompletionStatus.xml with the last
+    /// [ERROR_LOG_TAIL_LINES](logs::ERROR_LOG_TAIL_LINES) lines of the most recent
+    /// [error log](logs::find_error_log()), when either exists. If neither does, the completion
+    /// status itself is described. Returns None if the run has not [failed](SeqDir::is_failed()).
+    pub fn failure_context(&self) -> Result<Option<String>, SeqDirError> {
+        if !self.is_failed()? {
+            return Ok(None);
+        }
+        let mut parts = Vec::new();
+        let status = self.get_completion_status().transpose()?;
+        if let Some(message) = status.as_ref().and_then(|s| s.message().message.as_ref()) {
+            parts.push(format!("ErrorDescription: {message}"));
         }
+        if let Some(log) = logs::find_error_log(self.root()) {
+            let tail = logs::tail(&log, logs::ERROR_LOG_TAIL_LINES)?;
+            let name = log.strip_prefix(self.root()).unwrap_or(&log);
+            parts.push(format!("{}:\n{tail}", name.display()));
+        }
+        if parts.is_empty() {
+            if let Some(status) = status {
+                parts.push(status.to_string());
+            }
+        }
+        Ok(Some(parts.join("\n\n")))
     }
 
     /// Returns true if SequenceComplete.txt is not present
@@ -180,6 +763,612 @@ impl SeqDir {
         !self.is_sequence_complete()
     }
 
+    /// Determine whether the run's basecalls are BCLs or CBCLs.
+    ///
+    /// Only inspects the first cycle of the first lane. See [BclFormat].
+    pub fn bcl_format(&self) -> Result<BclFormat, SeqDirError> {
+        detect_bcl_format(self.root())
+    }
+
+    /// Check that each expected XML metadata file parses and has the correct root element.
+    ///
+    /// Checks RunInfo.xml, RunParameters.xml, and RunCompletionStatus.xml, in that order. Every
+    /// file's [MetadataStatus] is reported, rather than stopping at the first problem.
+    pub fn validate_metadata(&self) -> Vec<(PathBuf, MetadataStatus)> {
+        [
+            (&self.run_info, "RunInfo"),
+            (&self.run_params, "RunParameters"),
+            (&self.run_completion, "RunCompletionStatus"),
+        ]
+        .into_iter()
+        .map(|(path, root_tag)| (path.clone(), validate_xml(path, root_tag)))
+        .collect()
+    }
+
+    /// Run every available check against the directory, reporting all problems found.
+    ///
+    /// Covers availability, metadata validity, the completion status, lane and cycle structure,
+    /// filter files, and flowcell consistency. Missing cycles are only reported once
+    /// CopyComplete.txt exists, since they are expected while a run is in progress. Never fails:
+    /// every error is reported as a [HealthIssue].
+    pub fn health_check(&self) -> HealthReport {
+        validate::health_check(self)
+    }
+
+    /// Compare the cycle directories of every lane to the [planned_cycles](SeqDir::planned_cycles()).
+    ///
+    /// Catches runs whose transfer died partway but that still wrote CopyComplete.txt. A lane
+    /// is [short](LaneCycles::is_short()) if its highest cycle is below the planned count, and
+    /// has [gaps](LaneCycles::has_gaps()) if cycles are missing below its highest cycle; a lane
+    /// can be both. Only cycle directories are considered, their (C)BCLs are not checked.
+    ///
+    /// Errors if RunInfo.xml cannot be read or lanes cannot be detected.
+    pub fn validate_cycles(&self) -> Result<CycleValidation, SeqDirError> {
+        validate::validate_cycles(self)
+    }
+
+    /// Count the (C)BCLs across every lane, as by [Lane::bcl_count](lane::Lane::bcl_count()).
+    ///
+    /// Errors if lanes cannot be detected. Returns 0 if there are no lanes.
+    pub fn total_bcls(&self) -> Result<usize, SeqDirError> {
+        Ok(detect_lanes(self.try_root()?)?
+            .iter()
+            .map(lane::Lane::bcl_count)
+            .sum())
+    }
+
+    /// Summarize the directory in a human-readable, multi-line report.
+    ///
+    /// Covers the state the directory would be in if managed, the run id, the platform, cycle
+    /// completeness, missing metadata and sentinel files, the completion status, and any issues
+    /// found by [health_check](SeqDir::health_check()). Intended for display, e.g. by a
+    /// `seqdir validate` command; the format is not stable and should not be parsed. Never fails:
+    /// anything that cannot be determined is reported as such.
+    pub fn validate_report(&self) -> String {
+        validate::validate_report(self)
+    }
+
+    /// Check that the core InterOp metric files exist and look complete.
+    ///
+    /// See [InterOpPreflight] and [CORE_METRICS](interop::CORE_METRICS). Only file sizes and
+    /// headers are read. ExtractionMetricsOut.bin is checked against the cycles and tiles planned
+    /// by RunInfo.xml if it is available, and must be complete once CopyComplete.txt exists.
+    /// Errors if the root is not available.
+    pub fn interop_preflight(&self) -> Result<InterOpPreflight, SeqDirError> {
+        interop::interop_preflight(self)
+    }
+
+    /// Capture the current structure of the directory into a [SeqDirSnapshot].
+    ///
+    /// The snapshot owns all of its data and does not access the filesystem after it is taken.
+    /// Errors if lanes cannot be detected or RunCompletionStatus.xml cannot be parsed.
+    pub fn snapshot(&self) -> Result<SeqDirSnapshot, SeqDirError> {
+        let lanes = detect_lanes(self.root())?
+            .iter()
+            .map(LaneSnapshot::from)
+            .collect();
+        Ok(SeqDirSnapshot {
+            root: self.root.clone(),
+            taken_at: Utc::now(),
+            copy_complete: self.is_copy_complete(),
+            rta_complete: self.is_rta_complete(),
+            sequence_complete: self.is_sequence_complete(),
+            completion_status: self.get_completion_status().transpose()?,
+            lanes,
+        })
+    }
+
+    /// Load RunInfo.xml, RunParameters.xml and the completion status in one call.
+    ///
+    /// Files that are missing or malformed are left as None, see [RunMetadata].
+    pub fn metadata(&self) -> RunMetadata {
+        RunMetadata::from(self)
+    }
+
+    /// Attempt to parse RunInfo.xml
+    ///
+    /// Returns SeqDirError::NotFound if RunInfo.xml does not exist or is inaccessible.
+    pub fn read_run_info(&self) -> Result<RunInfo, SeqDirError> {
+        #[cfg(feature = "archive")]
+        if let Some(archive) = &self.archive {
+            let reader = archive
+                .open_file(RUN_INFO_XML)
+                .ok_or_else(|| SeqDirError::NotFound(self.run_info.clone()))?;
+            return Ok(run_info::read_run_info(reader?)?);
+        }
+        Ok(parse_run_info(self.run_info()?)?)
+    }
+
+    /// Returns true if the run is paired-end, according to RunInfo.xml.
+    ///
+    /// See [RunInfo::is_paired_end].
+    pub fn is_paired_end(&self) -> Result<bool, SeqDirError> {
+        Ok(self.read_run_info()?.is_paired_end())
+    }
+
+    /// Get the instrument serial number the run was sequenced on, according to RunInfo.xml.
+    pub fn instrument(&self) -> Result<String, SeqDirError> {
+        Ok(self.read_run_info()?.instrument)
+    }
+
+    /// Returns true if the run's [instrument](SeqDir::instrument()) is in `allowed`.
+    ///
+    /// The whole serial number must match, but the comparison ignores ASCII case.
+    pub fn instrument_in(&self, allowed: &[&str]) -> Result<bool, SeqDirError> {
+        let instrument = self.instrument()?;
+        Ok(allowed.iter().any(|a| a.eq_ignore_ascii_case(&instrument)))
+    }
+
+    /// Get the identifiers of the consumables loaded for the run, from RunParameters.xml.
+    ///
+    /// See [Consumables]. Identifiers that are not recorded are None.
+    pub fn consumables(&self) -> Result<Consumables, SeqDirError> {
+        Ok(self.read_run_params()?.consumables)
+    }
+
+    /// Determine the [Platform] of the run.
+    ///
+    /// RunParameters.xml is consulted first. If it is missing or does not identify the platform,
+    /// the instrument serial number is matched against
+    /// [INSTRUMENT_PREFIXES](run_params::INSTRUMENT_PREFIXES), taking the serial from
+    /// RunInfo.xml or, failing that, the second component of the run folder name. Errors only if
+    /// RunParameters.xml cannot be read and no serial matches.
+    pub fn platform(&self) -> Result<Platform, SeqDirError> {
+        let from_params = self.read_run_params().map(|params| params.instrument_type);
+        if let Ok(platform) = from_params {
+            if platform != Platform::Unknown {
+                return Ok(platform);
+            }
+        }
+        let from_serial = self
+            .instrument()
+            .ok()
+            .and_then(|serial| platform_from_serial(&serial))
+            .or_else(|| {
+                let folder = self.root().file_name()?.to_str()?;
+                platform_from_serial(folder.split('_').nth(1)?)
+            });
+        match from_serial {
+            Some(platform) => Ok(platform),
+            None => from_params,
+        }
+    }
+
+    /// Attempt to parse the sample sheet, found by [samplesheet_path](SeqDir::samplesheet_path()).
+    pub fn read_samplesheet(&self) -> Result<SampleSheet, SeqDirError> {
+        parse_samplesheet(self.samplesheet_path()?)
+    }
+
+    /// Returns true if the read lengths in the sample sheet match RunInfo.xml.
+    ///
+    /// Errors if either file is missing or malformed, or the sample sheet has no `[Reads]`
+    /// section. See [read_discrepancies](SeqDir::read_discrepancies()) for the differences.
+    pub fn reads_consistent(&self) -> Result<bool, SeqDirError> {
+        Ok(self.read_discrepancies()?.is_empty())
+    }
+
+    /// List every read whose length differs between the sample sheet and RunInfo.xml.
+    ///
+    /// v1 sample sheets do not list index reads, so only non-index reads are compared for them.
+    pub fn read_discrepancies(&self) -> Result<Vec<ReadDiscrepancy>, SeqDirError> {
+        let run_info = self.read_run_info()?;
+        let lengths = self.read_samplesheet()?.read_lengths()?;
+        Ok(compare_reads(&run_info, &lengths))
+    }
+
+    /// Get the [Flowcell] of the run.
+    ///
+    /// The id is read from RunInfo.xml, and the side is parsed from the run folder name.
+    pub fn flowcell(&self) -> Result<Flowcell, SeqDirError> {
+        let run_info = self.read_run_info()?;
+        let folder_name = self
+            .root()
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("");
+        Ok(Flowcell::from_run_folder(&run_info.flowcell, folder_name))
+    }
+
+    /// Compute a key that identifies the physical run, independent of where it is stored.
+    ///
+    /// The key is `{instrument}_{run_number}_{flowcell}`, e.g. `A00123_0042_HABCDEFXY`:
+    /// - the values come from RunInfo.xml, or, if it cannot be read or has no run number, from a
+    ///   run folder name of the form `{date}_{instrument}_{run_number}_{side}{flowcell}`.
+    /// - the run number is zero-padded to at least four digits, as in run folder names.
+    /// - the flowcell is the bare id, without the instrument side (see [Flowcell]).
+    /// - within each component, every byte other than an ASCII letter, digit, `-` or `.` is
+    ///   percent-encoded as `%XX` (uppercase hex), so `_` only ever separates components.
+    ///
+    /// Case is preserved. If neither source is usable, returns the error from RunInfo.xml.
+    pub fn run_key(&self) -> Result<String, SeqDirError> {
+        self.run_key_from(self.read_run_info())
+    }
+
+    /// Like [run_key](SeqDir::run_key()), with RunInfo.xml already read
+    fn run_key_from(&self, run_info: Result<RunInfo, SeqDirError>) -> Result<String, SeqDirError> {
+        let parts = match run_info {
+            Ok(RunInfo {
+                instrument,
+                run_number: Some(run_number),
+                flowcell,
+                ..
+            }) => Ok((instrument, run_number, flowcell)),
+            Ok(..) => Err(SeqDirError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "RunInfo.xml has no run number",
+            ))),
+            Err(e) => Err(e),
+        };
+        let (instrument, run_number, flowcell) = match parts {
+            Ok(parts) => parts,
+            Err(e) => self
+                .root()
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parse_run_folder_name)
+                .ok_or(e)?,
+        };
+        Ok(format!(
+            "{}_{:04}_{}",
+            escape_key_component(&instrument),
+            run_number,
+            escape_key_component(&flowcell)
+        ))
+    }
+
+    /// Attempt to parse RunParameters.xml
+    ///
+    /// Returns SeqDirError::NotFound if RunParameters.xml does not exist or is inaccessible.
+    pub fn read_run_params(&self) -> Result<RunParameters, SeqDirError> {
+        Ok(parse_run_params(self.run_params()?)?)
+    }
+
+    /// Get the version of RTA used for the run.
+    ///
+    /// Tries the RtaVersion element of RunParameters.xml first, then falls back to the contents
+    /// of RTAComplete.txt. Either way the version is returned without a leading `v`, e.g.
+    /// `3.4.4` rather than `v3.4.4`. Returns None if neither source yields a version.
+    pub fn rta_version(&self) -> Option<String> {
+        self.rta_version_from(self.read_run_params().ok())
+    }
+
+    /// Get the versions of the control software and RTA that produced the run.
+    ///
+    /// Both are read from RunParameters.xml. As with [rta_version](SeqDir::rta_version()), the
+    /// RTA version falls back to the contents of RTAComplete.txt and has any leading `v`
+    /// stripped. Use [satisfies](SoftwareVersions::satisfies()) to check a minimum RTA version.
+    ///
+    /// Returns SeqDirError::NotFound if RunParameters.xml does not exist or is inaccessible.
+    pub fn software_versions(&self) -> Result<SoftwareVersions, SeqDirError> {
+        let params = self.read_run_params()?;
+        Ok(SoftwareVersions {
+            control_software: params.application_version.clone(),
+            rta: self.rta_version_from(Some(params)),
+        })
+    }
+
+    /// The normalized RTA version from `params`, falling back to RTAComplete.txt
+    fn rta_version_from(&self, params: Option<RunParameters>) -> Option<String> {
+        params
+            .and_then(|params| params.rta_version)
+            .or_else(|| {
+                std::fs::read_to_string(self.root().join(RTA_COMPLETE_TXT))
+                    .ok()
+                    .and_then(|raw| parse_rta_version(&raw))
+            })
+            .map(|version| normalize_version(&version))
+    }
+
+    /// Returns true if the instrument ran secondary analysis (e.g. DRAGEN) on board.
+    ///
+    /// Signals are checked in order of precedence:
+    /// 1. The SecondaryAnalysisWorkflow (or SecondaryAnalysisPlatform) element of
+    ///    RunParameters.xml. If present, it is authoritative: any value other than `None` or
+    ///    `false` means onboard analysis was run.
+    /// 2. Otherwise, the presence of an Analysis/ directory.
+    pub fn has_onboard_analysis(&self) -> bool {
+        match self
+            .read_run_params()
+            .ok()
+            .and_then(|params| params.secondary_analysis)
+        {
+            Some(workflow) => {
+                !(workflow.eq_ignore_ascii_case("none") || workflow.eq_ignore_ascii_case("false"))
+            }
+            None => self.root().join(ANALYSIS_DIR).is_dir(),
+        }
+    }
+
+    /// Read the planned cycle count from the run's recipe or configuration files.
+    ///
+    /// Returns None if there are no such files, which is the case for most platforms other than
+    /// HiSeq. See [recipe] for the files consulted.
+    pub fn recipe_cycles(&self) -> Result<Option<u16>, SeqDirError> {
+        recipe::find_recipe_cycles(self.try_root()?)
+    }
+
+    /// The total number of cycles planned for the run.
+    ///
+    /// On HiSeq, whose runs may be customized after RunInfo.xml is written, the
+    /// [recipe_cycles](SeqDir::recipe_cycles()) take precedence. Otherwise, or if no recipe is
+    /// found, this is the sum of the reads in RunInfo.xml. Errors if RunInfo.xml is needed but
+    /// unavailable.
+    pub fn planned_cycles(&self) -> Result<u16, SeqDirError> {
+        if self.platform().is_ok_and(|p| p == Platform::HiSeq) {
+            if let Ok(Some(cycles)) = self.recipe_cycles() {
+                return Ok(cycles);
+            }
+        }
+        Ok(self.read_run_info()?.total_cycles())
+    }
+
+    /// Estimate how far along an active run is, as a percentage from 0 to 100.
+    ///
+    /// Computed as the highest cycle number observed in any lane divided by the
+    /// [planned_cycles](SeqDir::planned_cycles()). Errors if RunInfo.xml is unavailable or plans
+    /// no cycles.
+    pub fn percent_complete(&self) -> Result<f32, SeqDirError> {
+        let total_cycles = self.planned_cycles()?;
+        if total_cycles == 0 {
+            return Err(SeqDirError::MissingCycles);
+        }
+        let max_cycle = observed_cycles(self.root())?.last().copied().unwrap_or(0);
+        Ok((f32::from(max_cycle) / f32::from(total_cycles) * 100.0).clamp(0.0, 100.0))
+    }
+
+    /// Estimate how far along an active run is by bytes written, as a percentage from 0 to 100.
+    ///
+    /// Computed as the total size of the (C)BCLs on disk divided by the
+    /// [estimate_final_size](SeqDir::estimate_final_size()), so unlike
+    /// [percent_complete](SeqDir::percent_complete()) it reflects partially copied cycles, e.g.
+    /// during transfer. Returns 0 if the final size is estimated to be zero bytes, and errors
+    /// under the same conditions as estimate_final_size.
+    pub fn percent_complete_by_size(&self) -> Result<f32, SeqDirError> {
+        let final_size = self.estimate_final_size()?;
+        if final_size == 0 {
+            return Ok(0.0);
+        }
+        let mut copied = 0;
+        for cycle in observed_cycles(self.root())? {
+            copied += lane::cycle_size(self.root(), cycle)?;
+        }
+        Ok((copied as f64 / final_size as f64 * 100.0).clamp(0.0, 100.0) as f32)
+    }
+
+    /// Estimate the final size, in bytes, of the run's basecalls.
+    ///
+    /// This is an estimate, made under the following assumptions:
+    /// 1. Every [planned](SeqDir::planned_cycles()) cycle is about the same size.
+    /// 2. Once a later cycle exists, the lowest observed cycle has been fully written.
+    ///
+    /// If (2) holds for the current state of the directory, the size of the lowest observed cycle
+    /// is extrapolated to the total number of cycles. Otherwise (e.g. the run has just started),
+    /// the flowcell geometry from RunInfo.xml is used: cycles × lanes × tiles per lane ×
+    /// [bytes_per_tile_cycle](Platform::bytes_per_tile_cycle()) for the run's platform. Those
+    /// byte counts are placeholders, so this fallback is only a rough order of magnitude. Only (C)BCLs are counted; InterOp, logs and thumbnails are not.
+    ///
+    /// Errors if RunInfo.xml is unavailable or plans no cycles, or if the geometry is needed but
+    /// RunInfo.xml has no complete FlowcellLayout.
+    pub fn estimate_final_size(&self) -> Result<u64, SeqDirError> {
+        let total_cycles = u64::from(self.planned_cycles()?);
+        if total_cycles == 0 {
+            return Err(SeqDirError::MissingCycles);
+        }
+        if let [first, _, ..] = observed_cycles(self.root())?.as_slice() {
+            return Ok(lane::cycle_size(self.root(), *first)? * total_cycles);
+        }
+        let layout = self
+            .read_run_info()?
+            .flowcell_layout()
+            .ok_or(SeqDirError::MissingFlowcellLayout)?;
+        let tiles = layout.total_tiles();
+        let platform = self.platform().unwrap_or_default();
+        Ok(total_cycles * tiles * platform.bytes_per_tile_cycle())
+    }
+
+    /// Get the first cycle number of read `read_number`, according to RunInfo.xml.
+    ///
+    /// Cycles are numbered from 1 across all reads, in order of read number, so index reads
+    /// sequenced earlier shift the offset of later reads. Together with the read's length this
+    /// bounds the cycle directories belonging to the read, see [RunInfo::cycle_ranges].
+    /// Errors with SeqDirError::InvalidRead if RunInfo.xml has no such read.
+    pub fn cycle_offset_for_read(&self, read_number: u8) -> Result<u16, SeqDirError> {
+        self.read_run_info()?
+            .cycle_ranges()
+            .into_iter()
+            .find(|(number, _)| *number == read_number)
+            .map(|(_, cycles)| *cycles.start())
+            .ok_or(SeqDirError::InvalidRead(read_number))
+    }
+
+    /// Find the first planned cycle that is missing or empty on disk.
+    ///
+    /// Walks the [planned_cycles](SeqDir::planned_cycles()) and returns the lowest one that is not
+    /// fully present in every lane, or None if all cycles are present. Useful for "stuck at cycle
+    /// N" diagnostics. Errors if RunInfo.xml is unavailable.
+    pub fn first_incomplete_cycle(&self) -> Result<Option<u16>, SeqDirError> {
+        first_incomplete_cycle(self.root(), self.planned_cycles()?)
+    }
+
+    /// Find cycles that are present now but were not in `previous`.
+    ///
+    /// Cycle numbers are aggregated across lanes, deduplicated, and returned in ascending order.
+    /// Only cycle directory names are inspected.
+    pub fn cycles_since(&self, previous: &[u16]) -> Result<Vec<u16>, SeqDirError> {
+        Ok(observed_cycles(self.root())?
+            .into_iter()
+            .filter(|c| !previous.contains(c))
+            .collect())
+    }
+
+    /// Find cycles that were written more than `min_gap` after the previous cycle.
+    ///
+    /// Returns `(cycle number, gap)` for each such cycle. A long gap usually means the run was
+    /// paused and resumed. This assumes cycle directories are not modified after they are
+    /// written, and uses their modification times (see [cycle_mtimes](lane::cycle_mtimes)).
+    /// Filesystems with coarse timestamps may record consecutive cycles at the same time, or
+    /// slightly out of order; such gaps are treated as zero rather than as errors.
+    pub fn detect_resume_gaps(
+        &self,
+        min_gap: Duration,
+    ) -> Result<Vec<(u16, Duration)>, SeqDirError> {
+        let mtimes = lane::cycle_mtimes(self.root())?;
+        Ok(mtimes
+            .windows(2)
+            .map(|pair| {
+                let gap = pair[1].1.duration_since(pair[0].1).unwrap_or_default();
+                (pair[1].0, gap)
+            })
+            .filter(|(_, gap)| *gap > min_gap)
+            .collect())
+    }
+
+    /// Join the lanes on disk with the samples the sample sheet assigns to them.
+    ///
+    /// Lanes are [detected](lane::detect_lanes()) on disk, and their cycles are compared to the
+    /// [planned_cycles](SeqDir::planned_cycles()). Samples without a lane apply to every lane.
+    /// Lanes that the sample sheet assigns samples to but that are not on disk are included with
+    /// no observed cycles. Returned in order of lane number.
+    ///
+    /// Errors if lanes cannot be detected, or if RunInfo.xml or the sample sheet cannot be read.
+    pub fn lane_readiness(&self) -> Result<Vec<LaneReadiness>, SeqDirError> {
+        let lanes = detect_lanes(self.try_root()?)?;
+        let expected_cycles = self.planned_cycles()?;
+        let samples = self.read_samplesheet()?.samples()?;
+        let mut lane_nums: Vec<u8> = lanes
+            .iter()
+            .map(|l| l.lane_num)
+            .chain(samples.iter().filter_map(|s| s.lane))
+            .collect();
+        lane_nums.sort_unstable();
+        lane_nums.dedup();
+        Ok(lane_nums
+            .into_iter()
+            .map(|lane_num| {
+                let observed_cycles = lanes
+                    .iter()
+                    .find(|l| l.lane_num == lane_num)
+                    .map_or(0, |l| u16::try_from(l.cycles().len()).unwrap_or(u16::MAX));
+                let sample_count = samples
+                    .iter()
+                    .filter(|s| s.lane.map_or(true, |l| l == lane_num))
+                    .count();
+                LaneReadiness {
+                    lane_num,
+                    observed_cycles,
+                    expected_cycles,
+                    sample_count,
+                    in_samplesheet: sample_count > 0,
+                    ready: sample_count > 0 && observed_cycles >= expected_cycles,
+                }
+            })
+            .collect())
+    }
+
+    /// List the files in the run whose names end with the extension `ext`, sorted by path.
+    ///
+    /// `ext` may be given with or without its leading dot, may span several dots (e.g.
+    /// `cbcl.gz`), and is matched ignoring case. Only the root is searched unless `recursive` is
+    /// true, in which case subdirectories are searched as by
+    /// [walk_files](walk::walk_files()), up to [DEFAULT_MAX_DEPTH](walk::DEFAULT_MAX_DEPTH)
+    /// levels deep and skipping symlinks.
+    pub fn find_by_extension(
+        &self,
+        ext: &str,
+        recursive: bool,
+    ) -> Result<Vec<PathBuf>, SeqDirError> {
+        let suffix = format!(".{}", ext.trim_start_matches('.')).to_ascii_lowercase();
+        let max_depth = if recursive {
+            walk::DEFAULT_MAX_DEPTH
+        } else {
+            1
+        };
+        let mut found: Vec<PathBuf> = walk::walk_files(self.try_root()?, max_depth)?
+            .into_iter()
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .to_ascii_lowercase()
+                    .ends_with(&suffix)
+            })
+            .map(|entry| entry.path())
+            .collect();
+        found.sort_unstable();
+        Ok(found)
+    }
+
+    /// List the files in the run that were modified after `since`, sorted by path.
+    ///
+    /// This walks the whole directory tree, up to [DEFAULT_MAX_DEPTH](walk::DEFAULT_MAX_DEPTH)
+    /// levels deep; see [files_modified_since_in](SeqDir::files_modified_since_in()) to narrow
+    /// it.
+    pub fn files_modified_since(&self, since: SystemTime) -> Result<Vec<PathBuf>, SeqDirError> {
+        self.files_modified_since_in(since, WalkScope::Run, walk::DEFAULT_MAX_DEPTH)
+    }
+
+    /// List the files under `scope`, at most `max_depth` levels deep, that were modified after
+    /// `since`, sorted by path.
+    ///
+    /// See [files_modified_since](lane::files_modified_since()) for the cost of the traversal.
+    pub fn files_modified_since_in(
+        &self,
+        since: SystemTime,
+        scope: WalkScope,
+        max_depth: usize,
+    ) -> Result<Vec<PathBuf>, SeqDirError> {
+        lane::files_modified_since(self.try_root()?, since, scope, max_depth)
+    }
+
+    /// List the cycle directories of `lane`, sorted by cycle number, without reading them.
+    ///
+    /// Much cheaper than [detect_lanes](lane::detect_lanes()) when only paths are needed. See
+    /// [cycle_dirs](lane::cycle_dirs()).
+    pub fn cycle_dirs_for_lane(&self, lane: u8) -> Result<Vec<PathBuf>, SeqDirError> {
+        lane::cycle_dirs(self.root(), lane)
+    }
+
+    /// Iterate over the paths of the lane directories present, in ascending order of lane.
+    ///
+    /// Cycles are not read. See [Lane::from_path_shallow](lane::Lane::from_path_shallow()) to
+    /// cheaply turn the paths into lanes.
+    pub fn lane_dirs(&self) -> Result<impl Iterator<Item = PathBuf>, SeqDirError> {
+        Ok(lane::existing_lane_dirs(self.try_root()?.to_owned()))
+    }
+
+    /// List the numbers of the lanes present, in ascending order.
+    ///
+    /// Only lane directory names are inspected, not their contents.
+    pub fn lane_numbers(&self) -> Result<Vec<u8>, SeqDirError> {
+        #[cfg(feature = "archive")]
+        if let Some(archive) = &self.archive {
+            return Ok(archive.lanes());
+        }
+        Ok(lane::probe_lane_dirs(self.try_root()?)
+            .into_iter()
+            .map(|(lane, _)| lane)
+            .collect())
+    }
+
+    /// List entries of the BaseCalls directory that are not recognized lane directories.
+    ///
+    /// Useful for spotting artifacts of a partial or repeated copy, e.g. `L001_old`. This is
+    /// purely informational; [detect_lanes](lane::detect_lanes()) ignores such entries.
+    /// Returns [SeqDirError::NotFound] if the BaseCalls directory cannot be read.
+    pub fn unexpected_basecalls_entries(&self) -> Result<Vec<PathBuf>, SeqDirError> {
+        lane::unexpected_basecalls_entries(self.root())
+    }
+
+    /// Find the BCLConvert `Reports/` directory of the run, if it has been demultiplexed.
+    ///
+    /// Onboard analyses (e.g. NextSeq 1000/2000) write reports to `Analysis/N/Data/Reports/`, in
+    /// which case the highest-numbered analysis is used. Otherwise, a top-level `Reports/`
+    /// directory is used. Returns Ok(None) if neither exists, and
+    /// [SeqDirError::NotFound] if the root is not available.
+    pub fn demux_reports(&self) -> Result<Option<PathBuf>, SeqDirError> {
+        Ok(demux::find_reports_dir(self.try_root()?))
+    }
+
     /// Returns reference to seqdir root
     pub fn root(&self) -> &Path {
         &self.root
@@ -195,6 +1384,33 @@ impl SeqDir {
             .ok_or_else(|| SeqDirError::NotFound(self.samplesheet.clone()))
     }
 
+    /// Find the sample sheet, allowing for non-standard names.
+    ///
+    /// Tries each of [SAMPLESHEET_VARIANTS] in order, ignoring case, and returns the first match.
+    /// Use [samplesheet](SeqDir::samplesheet()) to only accept the canonical name.
+    /// Returns SeqDirError::NotFound if no variant exists.
+    pub fn samplesheet_path(&self) -> Result<PathBuf, SeqDirError> {
+        if self.samplesheet.is_file() {
+            return Ok(self.samplesheet.clone());
+        }
+        let files: Vec<PathBuf> = read_dir(self.root())?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .collect();
+        SAMPLESHEET_VARIANTS
+            .iter()
+            .find_map(|variant| {
+                files.iter().find(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.eq_ignore_ascii_case(variant))
+                })
+            })
+            .cloned()
+            .ok_or_else(|| SeqDirError::NotFound(self.samplesheet.clone()))
+    }
+
     /// Get the path to RunInfo.xml
     ///
     /// Returns SeqDirError::NotFound if path does not exist or is inaccessible.
@@ -231,12 +1447,41 @@ impl SeqDir {
 #[cfg(test)]
 mod tests {
 
-    use crate::{SeqDir, SeqDirError};
+    use crate::test_dir::TestDir;
+    use crate::{
+        BclFormat, CompletionCriteria, CompletionRule, CompletionStatus, MetadataStatus, Platform,
+        SeqDir, SeqDirConfig, SeqDirError,
+    };
 
     const COMPLETE: &str = "test_data/seq_complete/";
     const FAILED: &str = "test_data/seq_failed/";
     const TRANSFERRING: &str = "test_data/seq_transferring/";
     const SEQUENCING: &str = "test_data/seq_sequencing/";
+    const PARTIAL: &str = "test_data/seq_partial/";
+    const NEXTSEQ2000: &str = "test_data/seq_nextseq2000/";
+    const NOVASEQ_A: &str = "test_data/20240110_A00123_0042_AHABCDEFXY/";
+    const RUNPARAMS_STATUS: &str = "test_data/seq_runparams_status/";
+
+    #[test]
+    fn lock_names_stay_in_root() {
+        let seq_dir = SeqDir::from_path(COMPLETE).unwrap();
+        assert!(seq_dir
+            .processing_lock(".processing.lock")
+            .unwrap()
+            .is_none());
+        for name in ["../RunInfo.xml", "/etc/passwd", "Data/lock", "..", ".", ""] {
+            assert!(
+                matches!(
+                    seq_dir.processing_lock(name),
+                    Err(SeqDirError::InvalidFileName(..))
+                ),
+                "{name}"
+            );
+        }
+        assert!(seq_dir
+            .is_lock_stale("../lock", std::time::Duration::ZERO)
+            .is_err());
+    }
 
     #[test]
     fn complete_seqdir() {
@@ -249,6 +1494,610 @@ mod tests {
         assert!(seq_dir.is_copy_complete());
         assert!(seq_dir.is_rta_complete());
         assert!(!seq_dir.is_sequencing());
+        assert!(!seq_dir.is_empty().unwrap());
+        assert_eq!(seq_dir.bcl_format().unwrap(), BclFormat::CBcl);
+        assert!(seq_dir
+            .validate_metadata()
+            .iter()
+            .all(|(_, status)| status.is_ok()));
+    }
+
+    #[test]
+    fn completed_with_criteria() {
+        assert!(SeqDir::from_completed(TRANSFERRING).is_err());
+        let seq_dir =
+            SeqDir::from_completed_with(TRANSFERRING, &CompletionCriteria::miseq()).unwrap();
+        assert!(!seq_dir.is_copy_complete());
+        assert!(matches!(
+            SeqDir::from_completed_with(FAILED, &CompletionCriteria::miseq()),
+            Err(SeqDirError::CompletionStatus(..))
+        ));
+    }
+
+    #[test]
+    fn empty_seqdir() {
+        let empty = TestDir::new("empty_seqdir");
+        let seq_dir = SeqDir::from_path(&empty).unwrap();
+        assert!(seq_dir.is_empty().unwrap());
+        assert!(matches!(
+            SeqDir::from_run_dir(&empty),
+            Err(SeqDirError::EmptyDir(..))
+        ));
+        SeqDir::from_run_dir(COMPLETE).unwrap();
+    }
+
+    #[test]
+    fn percent_complete() {
+        let seq_dir = SeqDir::from_path(PARTIAL).unwrap();
+        let percent = seq_dir.percent_complete().unwrap();
+        assert!((percent - 49.668).abs() < 0.01);
+        let seq_dir = SeqDir::from_path(COMPLETE).unwrap();
+        assert_eq!(seq_dir.percent_complete().unwrap(), 100.0);
+        let seq_dir = SeqDir::from_path(SEQUENCING).unwrap();
+        assert!(seq_dir.percent_complete().is_err());
+    }
+
+    #[test]
+    fn samplesheet_variants() {
+        let seq_dir = SeqDir::from_path(PARTIAL).unwrap();
+        assert!(seq_dir.samplesheet().is_err());
+        assert!(seq_dir
+            .samplesheet_path()
+            .unwrap()
+            .ends_with("samplesheet_v2.csv"));
+        let seq_dir = SeqDir::from_path(COMPLETE).unwrap();
+        assert!(seq_dir
+            .samplesheet_path()
+            .unwrap()
+            .ends_with("SampleSheet.csv"));
+    }
+
+    #[test]
+    fn first_incomplete_cycle() {
+        let seq_dir = SeqDir::from_path(PARTIAL).unwrap();
+        assert_eq!(seq_dir.first_incomplete_cycle().unwrap(), Some(76));
+        let seq_dir = SeqDir::from_path(COMPLETE).unwrap();
+        assert_eq!(seq_dir.first_incomplete_cycle().unwrap(), None);
+    }
+
+    #[test]
+    fn rta_version() {
+        let seq_dir = SeqDir::from_path(COMPLETE).unwrap();
+        assert_eq!(seq_dir.rta_version().as_deref(), Some("3.4.4"));
+        let seq_dir = SeqDir::from_path(TRANSFERRING).unwrap();
+        assert_eq!(seq_dir.rta_version().as_deref(), Some("1.18.54"));
+        let seq_dir = SeqDir::from_path(SEQUENCING).unwrap();
+        assert_eq!(seq_dir.rta_version(), None);
+    }
+
+    #[test]
+    fn software_versions() {
+        let versions = SeqDir::from_path(PARTIAL)
+            .unwrap()
+            .software_versions()
+            .unwrap();
+        assert_eq!(versions.control_software.as_deref(), Some("2.6.2.1"));
+        assert_eq!(versions.rta.as_deref(), Some("1.18.54"));
+        assert!(versions.satisfies(&semver::Version::new(1, 18, 0)));
+        assert!(!versions.satisfies(&semver::Version::new(2, 0, 0)));
+        assert!(SeqDir::from_path(SEQUENCING)
+            .unwrap()
+            .software_versions()
+            .is_err());
+    }
+
+    #[test]
+    fn paired_end() {
+        assert!(SeqDir::from_path(NOVASEQ_A)
+            .unwrap()
+            .is_paired_end()
+            .unwrap());
+        assert!(!SeqDir::from_path(TRANSFERRING)
+            .unwrap()
+            .is_paired_end()
+            .unwrap());
+        assert!(SeqDir::from_path(SEQUENCING)
+            .unwrap()
+            .is_paired_end()
+            .is_err());
+    }
+
+    #[test]
+    fn instrument_allowlist() {
+        let seq_dir = SeqDir::from_path(NOVASEQ_A).unwrap();
+        assert_eq!(seq_dir.instrument().unwrap(), "A00123");
+        assert!(seq_dir.instrument_in(&["M00001", "a00123"]).unwrap());
+        assert!(!seq_dir.instrument_in(&["A0012", "A001234"]).unwrap());
+        assert!(!seq_dir.instrument_in(&[]).unwrap());
+        assert!(SeqDir::from_path(SEQUENCING)
+            .unwrap()
+            .instrument_in(&["A00123"])
+            .is_err());
+    }
+
+    #[test]
+    fn consumables() {
+        let consumables = SeqDir::from_path(PARTIAL).unwrap().consumables().unwrap();
+        assert_eq!(consumables.reagent.part_number.as_deref(), Some("15043962"));
+        assert!(SeqDir::from_path(SEQUENCING)
+            .unwrap()
+            .consumables()
+            .is_err());
+    }
+
+    #[test]
+    fn reads_consistent() {
+        assert!(SeqDir::from_path(COMPLETE)
+            .unwrap()
+            .reads_consistent()
+            .unwrap());
+        let seq_dir = SeqDir::from_path(NOVASEQ_A).unwrap();
+        assert!(!seq_dir.reads_consistent().unwrap());
+        assert_eq!(seq_dir.read_discrepancies().unwrap().len(), 2);
+        // sample sheet is missing
+        assert!(matches!(
+            SeqDir::from_path(NEXTSEQ2000).unwrap().reads_consistent(),
+            Err(SeqDirError::NotFound(..))
+        ));
+        // RunInfo.xml is empty
+        assert!(SeqDir::from_path(SEQUENCING)
+            .unwrap()
+            .reads_consistent()
+            .is_err());
+    }
+
+    #[test]
+    fn planned_cycles() {
+        let hiseq = SeqDir::from_path("test_data/seq_hiseq").unwrap();
+        assert_eq!(hiseq.recipe_cycles().unwrap(), Some(6));
+        assert_eq!(hiseq.planned_cycles().unwrap(), 6);
+        let complete = SeqDir::from_path(COMPLETE).unwrap();
+        assert_eq!(complete.recipe_cycles().unwrap(), None);
+        assert_eq!(complete.planned_cycles().unwrap(), 42);
+    }
+
+    #[test]
+    fn platform() {
+        assert_eq!(
+            SeqDir::from_path(NEXTSEQ2000).unwrap().platform().unwrap(),
+            Platform::NextSeq2000
+        );
+        assert_eq!(
+            SeqDir::from_path(COMPLETE).unwrap().platform().unwrap(),
+            Platform::NovaSeq
+        );
+        assert!(SeqDir::from_path(SEQUENCING).unwrap().platform().is_err());
+        // no RunParameters.xml, falls back to the instrument serial
+        assert_eq!(
+            SeqDir::from_path("test_data/20240110_A00123_0042_AHABCDEFXY")
+                .unwrap()
+                .platform()
+                .unwrap(),
+            Platform::NovaSeq
+        );
+    }
+
+    #[test]
+    fn flowcell() {
+        let flowcell = SeqDir::from_path(NOVASEQ_A).unwrap().flowcell().unwrap();
+        assert_eq!(flowcell.id, "HABCDEFXY");
+        assert_eq!(flowcell.side, Some('A'));
+        let flowcell = SeqDir::from_path(COMPLETE).unwrap().flowcell().unwrap();
+        assert_eq!(flowcell.side, None);
+    }
+
+    #[test]
+    fn onboard_analysis() {
+        assert!(SeqDir::from_path(NEXTSEQ2000)
+            .unwrap()
+            .has_onboard_analysis());
+        assert!(!SeqDir::from_path(COMPLETE).unwrap().has_onboard_analysis());
+    }
+
+    #[test]
+    fn cycles_since() {
+        let seq_dir = SeqDir::from_path(PARTIAL).unwrap();
+        let previous: Vec<u16> = (1..=70).collect();
+        assert_eq!(
+            seq_dir.cycles_since(&previous).unwrap(),
+            vec![71, 72, 73, 74, 75]
+        );
+        assert_eq!(seq_dir.cycles_since(&[]).unwrap().len(), 75);
+    }
+
+    #[test]
+    fn into_io_error() {
+        use std::io::ErrorKind;
+
+        let err: std::io::Error = SeqDirError::NotFound("foo".into()).into();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "cannot find foo or it is not readable");
+        let err: std::io::Error = SeqDirError::MissingBcls(3).into();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err: std::io::Error =
+            SeqDirError::IoError(std::io::Error::from(ErrorKind::TimedOut)).into();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn verified_completion() {
+        SeqDir::from_completed_verified(COMPLETE).unwrap();
+        SeqDir::from_completed_verified(NEXTSEQ2000).unwrap();
+        // CopyComplete.txt is present, but there are no lanes
+        let early = TestDir::new("early_sentinel");
+        std::fs::write(early.join(super::COPY_COMPLETE_TXT), "").unwrap();
+        SeqDir::from_completed(&early).unwrap();
+        assert!(matches!(
+            SeqDir::from_completed_verified(&early),
+            Err(SeqDirError::MissingLaneDirs)
+        ));
+        assert!(matches!(
+            SeqDir::from_completed_verified(FAILED),
+            Err(SeqDirError::CompletionStatus(..))
+        ));
+    }
+
+    #[test]
+    fn resume_gaps() {
+        use std::time::{Duration, SystemTime};
+
+        let root = TestDir::new("resume_gaps");
+        let lane = root.join("Data/Intensities/BaseCalls/L001");
+        let start = SystemTime::now() - Duration::from_secs(3600);
+        // cycle 3 has a coarse timestamp identical to cycle 2
+        for (cycle, offset) in [(1, 0), (2, 60), (3, 60), (4, 1860), (5, 1920)] {
+            let dir = lane.join(format!("C{cycle}.1"));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::File::open(&dir)
+                .unwrap()
+                .set_modified(start + Duration::from_secs(offset))
+                .unwrap();
+        }
+        let gaps = SeqDir::from_path(&root)
+            .unwrap()
+            .detect_resume_gaps(Duration::from_secs(300));
+        assert_eq!(gaps.unwrap(), vec![(4, Duration::from_secs(1800))]);
+        assert!(SeqDir::from_path(FAILED)
+            .unwrap()
+            .detect_resume_gaps(Duration::ZERO)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn unexpected_basecalls_entries() {
+        let root = TestDir::new("unexpected_basecalls");
+        let basecalls = root.join("Data/Intensities/BaseCalls");
+        for lane in ["L001", "L001_old", "l002"] {
+            std::fs::create_dir_all(basecalls.join(lane)).unwrap();
+        }
+        std::fs::write(basecalls.join("L003"), "").unwrap();
+        let unexpected = SeqDir::from_path(&root)
+            .unwrap()
+            .unexpected_basecalls_entries();
+        assert_eq!(
+            unexpected.unwrap(),
+            vec![
+                basecalls.join("L001_old"),
+                basecalls.join("L003"),
+                basecalls.join("l002")
+            ]
+        );
+        assert!(SeqDir::from_path(COMPLETE)
+            .unwrap()
+            .unexpected_basecalls_entries()
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            SeqDir::from_path(FAILED)
+                .unwrap()
+                .unexpected_basecalls_entries(),
+            Err(SeqDirError::NotFound(..))
+        ));
+    }
+
+    #[test]
+    fn demux_reports() {
+        let reports = SeqDir::from_path(NEXTSEQ2000)
+            .unwrap()
+            .demux_reports()
+            .unwrap();
+        assert!(reports
+            .unwrap()
+            .join(crate::demux::DEMUX_STATS_CSV)
+            .is_file());
+        assert_eq!(
+            SeqDir::from_path(COMPLETE)
+                .unwrap()
+                .demux_reports()
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn estimate_final_size() {
+        // no cycles yet, so the geometry of the flowcell is used: 4 lanes, 2 surfaces, 3 swaths,
+        // 12 tiles and 3 sections
+        let estimate = SeqDir::from_path(TRANSFERRING)
+            .unwrap()
+            .estimate_final_size()
+            .unwrap();
+        assert_eq!(
+            estimate,
+            91 * 864 * Platform::NextSeq.bytes_per_tile_cycle()
+        );
+
+        let root = TestDir::new("estimate_size");
+        let lane = root.join("Data/Intensities/BaseCalls/L001");
+        for cycle in ["C1.1", "C2.1"] {
+            std::fs::create_dir_all(lane.join(cycle)).unwrap();
+        }
+        std::fs::write(lane.join("C1.1/L001_1.cbcl"), [0u8; 100]).unwrap();
+        std::fs::write(lane.join("C1.1/L001_1.cbcl.filter"), [0u8; 50]).unwrap();
+        std::fs::copy(
+            format!("{TRANSFERRING}/RunInfo.xml"),
+            root.join("RunInfo.xml"),
+        )
+        .unwrap();
+        let seq_dir = SeqDir::from_path(&root).unwrap();
+        assert_eq!(seq_dir.estimate_final_size().unwrap(), 100 * 91);
+        // only C1.1 has been written
+        let by_size = seq_dir.percent_complete_by_size().unwrap();
+        assert!((by_size - 100.0 / 91.0).abs() < 0.001);
+
+        assert!(SeqDir::from_path(SEQUENCING)
+            .unwrap()
+            .estimate_final_size()
+            .is_err());
+    }
+
+    #[test]
+    fn side_completion() {
+        let root = TestDir::new("side_completion");
+        let status = |dir: &str| format!("{dir}/{}", super::RUN_COMPLETION_STATUS_XML);
+        std::fs::copy(status(COMPLETE), root.join("RunCompletionStatus_A.xml")).unwrap();
+        std::fs::copy(status(FAILED), root.join("RunCompletionStatus_B.xml")).unwrap();
+        let sides = SeqDir::from_path(&root).unwrap().side_completion().unwrap();
+        assert_eq!(sides.len(), 2);
+        assert!(matches!(
+            sides[0],
+            ('A', CompletionStatus::CompletedAsPlanned(..))
+        ));
+        assert!(matches!(
+            sides[1],
+            ('B', CompletionStatus::ExceptionEndedEarly(..))
+        ));
+
+        // whole-run status
+        let sides = SeqDir::from_path(COMPLETE)
+            .unwrap()
+            .side_completion()
+            .unwrap();
+        assert!(matches!(
+            sides.as_slice(),
+            [('A', CompletionStatus::CompletedAsPlanned(..))]
+        ));
+        assert!(SeqDir::from_path(NOVASEQ_A)
+            .unwrap()
+            .side_completion()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn run_key() {
+        let key = SeqDir::from_path(NOVASEQ_A).unwrap().run_key().unwrap();
+        assert_eq!(key, "A00123_0042_HABCDEFXY");
+        assert_eq!(
+            SeqDir::from_path(COMPLETE).unwrap().run_key().unwrap(),
+            "foo_0001_ABCXYZ"
+        );
+        // RunInfo.xml is unusable, so the folder name is parsed
+        let seq_dir = SeqDir::mock(
+            std::path::PathBuf::from("/seq/20240110_A00123_0042_BHABCDEFXY"),
+            crate::mock::MockBuilder::new(),
+        );
+        assert_eq!(seq_dir.run_key().unwrap(), "A00123_0042_HABCDEFXY");
+        assert!(SeqDir::from_path(SEQUENCING).unwrap().run_key().is_err());
+        assert_eq!(super::escape_key_component("a_b c%"), "a%5Fb%20c%25");
+    }
+
+    #[test]
+    fn failure_context() {
+        let context = SeqDir::from_path(FAILED)
+            .unwrap()
+            .failure_context()
+            .unwrap()
+            .unwrap();
+        assert!(context.starts_with("ErrorDescription: Turboencabulator failed"));
+        assert!(context.contains("Logs/20231231_bar_ABCXYZ_Error.log:\n"));
+        assert_eq!(
+            context.lines().count(),
+            2 + 1 + crate::logs::ERROR_LOG_TAIL_LINES
+        );
+        assert!(SeqDir::from_path(COMPLETE)
+            .unwrap()
+            .failure_context()
+            .unwrap()
+            .is_none());
+        // failed, but nothing more to say than the status
+        let mock = crate::mock::MockBuilder::new().completion_status(
+            CompletionStatus::UserEndedEarly(crate::Message {
+                run_id: String::from("mock"),
+                message: None,
+            }),
+        );
+        let seq_dir = SeqDir::mock(std::path::PathBuf::from("/does/not/exist"), mock);
+        assert_eq!(
+            seq_dir.failure_context().unwrap().unwrap(),
+            "UserEndedEarly : mock : None"
+        );
+    }
+
+    #[test]
+    fn find_by_extension() {
+        let seq_dir = SeqDir::from_path(NEXTSEQ2000).unwrap();
+        let xml = seq_dir.find_by_extension("XML", false).unwrap();
+        assert!(xml.iter().all(|p| p.parent() == Some(seq_dir.root())));
+        assert!(xml.iter().any(|p| p.ends_with("RunInfo.xml")));
+        assert!(seq_dir.find_by_extension("csv", false).unwrap().is_empty());
+        let csv = seq_dir.find_by_extension(".csv", true).unwrap();
+        assert!(csv
+            .iter()
+            .any(|p| p.ends_with("Reports/Quality_Metrics.csv")));
+        let cbcls = SeqDir::from_path(COMPLETE)
+            .unwrap()
+            .find_by_extension("cbcl", true)
+            .unwrap();
+        assert!(!cbcls.is_empty());
+        assert!(seq_dir.find_by_extension("nope", true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn cycle_offset_for_read() {
+        let seq_dir = SeqDir::from_path(NOVASEQ_A).unwrap();
+        assert_eq!(seq_dir.cycle_offset_for_read(1).unwrap(), 1);
+        assert_eq!(seq_dir.cycle_offset_for_read(2).unwrap(), 152);
+        assert_eq!(seq_dir.cycle_offset_for_read(3).unwrap(), 160);
+        assert!(matches!(
+            seq_dir.cycle_offset_for_read(4),
+            Err(SeqDirError::InvalidRead(4))
+        ));
+    }
+
+    #[test]
+    fn total_bcls() {
+        assert_eq!(
+            SeqDir::from_path(COMPLETE).unwrap().total_bcls().unwrap(),
+            336
+        );
+        assert_eq!(
+            SeqDir::from_path(SEQUENCING).unwrap().total_bcls().unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn lane_readiness() {
+        let readiness = SeqDir::from_path(COMPLETE)
+            .unwrap()
+            .lane_readiness()
+            .unwrap();
+        assert_eq!(readiness.len(), 4);
+        assert_eq!(readiness[0].observed_cycles, 42);
+        assert_eq!(readiness[0].expected_cycles, 42);
+        assert_eq!(readiness[0].sample_count, 2);
+        assert!(readiness[0].ready);
+        // only lane 1 is in the sample sheet
+        assert!(!readiness[1].in_samplesheet);
+        assert!(!readiness[1].ready);
+    }
+
+    #[test]
+    fn serializes_root_as_string() {
+        let root = |path: std::path::PathBuf| {
+            let seq_dir = SeqDir::mock(path, crate::mock::MockBuilder::new());
+            serde_json::to_value(seq_dir).unwrap()["root"].clone()
+        };
+        assert_eq!(root(std::path::PathBuf::from("/seq/run_x")), "/seq/run_x");
+        assert_eq!(
+            root(std::path::PathBuf::from(r"C:\seq\run_x")),
+            r"C:\seq\run_x"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let path = std::path::PathBuf::from(std::ffi::OsStr::from_bytes(b"/seq/run_\xff"));
+            assert_eq!(root(path), "/seq/run_\u{fffd}");
+        }
+    }
+
+    #[test]
+    fn from_dir_entry() {
+        let entries: Vec<std::fs::DirEntry> = std::fs::read_dir("test_data")
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let dir = entries
+            .iter()
+            .find(|e| e.file_name() == "seq_complete")
+            .unwrap();
+        assert_eq!(
+            SeqDir::from_dir_entry(dir).unwrap(),
+            SeqDir::from_path("test_data/seq_complete").unwrap()
+        );
+        let file = entries
+            .iter()
+            .find(|e| e.file_name() == "SequenceComplete.txt")
+            .unwrap();
+        assert!(matches!(
+            SeqDir::from_dir_entry(file),
+            Err(SeqDirError::NotFound(..))
+        ));
+    }
+
+    #[test]
+    fn set_root() {
+        let mut seq_dir = SeqDir::from_path(SEQUENCING).unwrap();
+        assert!(seq_dir.read_run_info().is_err());
+        seq_dir.set_root(std::path::PathBuf::from(COMPLETE));
+        assert_eq!(seq_dir, SeqDir::from_path(COMPLETE).unwrap());
+        seq_dir.read_run_info().unwrap();
+    }
+
+    #[test]
+    fn canonicalize() {
+        let mut relative = SeqDir::from_path(COMPLETE).unwrap();
+        let mut dotted = SeqDir::from_path("test_data/../test_data/seq_complete").unwrap();
+        assert_ne!(relative, dotted);
+        relative.canonicalize().unwrap();
+        dotted.canonicalize().unwrap();
+        assert!(relative.root().is_absolute());
+        assert_eq!(relative, dotted);
+        assert_eq!(
+            serde_json::to_string(&relative).unwrap(),
+            serde_json::to_string(&dotted).unwrap()
+        );
+        assert!(relative.run_info().unwrap().starts_with(relative.root()));
+
+        let mut missing = SeqDir::from_path(COMPLETE).unwrap();
+        missing.set_root(std::path::PathBuf::from("/does/not/exist"));
+        let before = missing.clone();
+        assert!(missing.canonicalize().is_err());
+        assert_eq!(missing, before);
+    }
+
+    #[test]
+    fn absent_completion_status() {
+        let seq_dir = SeqDir::from_path(TRANSFERRING).unwrap();
+        assert!(seq_dir.get_completion_status().is_none());
+        let root = TestDir::new("status_is_dir");
+        std::fs::create_dir_all(root.join(super::RUN_COMPLETION_STATUS_XML)).unwrap();
+        assert!(SeqDir::from_path(&root)
+            .unwrap()
+            .get_completion_status()
+            .is_none());
+    }
+
+    #[test]
+    fn completion_from_run_params() {
+        let seq_dir = SeqDir::from_path(RUNPARAMS_STATUS).unwrap();
+        assert!(seq_dir.get_completion_status().is_none());
+        assert!(!seq_dir.is_failed().unwrap());
+        let seq_dir = seq_dir.with_config(SeqDirConfig {
+            completion_from_run_params: true,
+            ..Default::default()
+        });
+        assert!(seq_dir.is_failed().unwrap());
+        // RunCompletionStatus.xml takes precedence
+        let seq_dir = SeqDir::from_path(COMPLETE)
+            .unwrap()
+            .with_config(SeqDirConfig {
+                completion_from_run_params: true,
+                ..Default::default()
+            });
+        assert!(!seq_dir.is_failed().unwrap());
     }
 
     #[test]
@@ -261,6 +2110,62 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn lane_dirs() {
+        let seq_dir = SeqDir::from_path(COMPLETE).unwrap();
+        let dirs: Vec<std::path::PathBuf> = seq_dir.lane_dirs().unwrap().collect();
+        assert_eq!(dirs.len(), 4);
+        assert!(dirs[0].ends_with("Data/Intensities/BaseCalls/L001"));
+        assert!(dirs[3].ends_with("Data/Intensities/BaseCalls/L004"));
+        let lanes: Vec<u8> = dirs
+            .into_iter()
+            .map(|d| crate::lane::Lane::from_path_shallow(d).unwrap().lane_num)
+            .collect();
+        assert_eq!(lanes, seq_dir.lane_numbers().unwrap());
+    }
+
+    #[test]
+    fn running_is_not_failed() {
+        let mock = crate::mock::MockBuilder::new().completion_status(CompletionStatus::Running(
+            crate::Message {
+                run_id: String::from("mock"),
+                message: None,
+            },
+        ));
+        let seq_dir = SeqDir::mock(std::path::PathBuf::from("/does/not/exist"), mock);
+        assert!(!seq_dir.is_failed().unwrap());
+        assert!(seq_dir.failure_context().unwrap().is_none());
+    }
+
+    #[test]
+    fn copy_complete_info() {
+        let seq_dir = SeqDir::from_path(FAILED).unwrap();
+        let info = seq_dir.copy_complete_info().unwrap().unwrap();
+        assert!(info.completed_at.is_some());
+        let seq_dir = SeqDir::from_path(TRANSFERRING).unwrap();
+        assert!(seq_dir.copy_complete_info().unwrap().is_none());
+    }
+
+    #[test]
+    fn completed_with_rule() {
+        let rule = CompletionRule::illumina_default();
+        SeqDir::from_completed_with_rule(COMPLETE, &rule).unwrap();
+        assert!(matches!(
+            SeqDir::from_completed_with_rule(FAILED, &rule),
+            Err(SeqDirError::NotComplete(..))
+        ));
+    }
+
+    #[test]
+    fn untrusted_completion_status() {
+        let config = SeqDirConfig {
+            trust_completion_status: false,
+            ..Default::default()
+        };
+        let seq_dir = SeqDir::from_completed_with_config(FAILED, config).unwrap();
+        assert!(!seq_dir.is_failed().unwrap());
+    }
+
     #[test]
     fn transferring_seqdir() {
         let seq_dir = SeqDir::from_path(TRANSFERRING).unwrap();
@@ -281,5 +2186,9 @@ mod tests {
         assert!(!seq_dir.is_failed().unwrap());
         assert!(!seq_dir.is_copy_complete());
         assert!(seq_dir.is_rta_complete());
+        let metadata = seq_dir.validate_metadata();
+        assert_eq!(metadata.len(), 3);
+        assert!(matches!(metadata[0].1, MetadataStatus::Malformed(..)));
+        assert_eq!(metadata[2].1, MetadataStatus::Missing);
     }
 }
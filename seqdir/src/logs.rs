@@ -0,0 +1,88 @@
+//! Find and read instrument error logs
+//!
+//! When a run fails, the control software usually writes its own account of the failure to an
+//! error log, e.g. `Logs/<run id>_Error.log`. Error logs are identified by name: any `.log` file
+//! whose name contains `error` (ignoring case), either in `Logs/` or in the root of the run.
+
+use std::fs::{read_dir, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub const LOGS_DIR: &str = "Logs";
+
+/// How many lines of an error log [failure_context](crate::SeqDir::failure_context()) includes
+pub const ERROR_LOG_TAIL_LINES: usize = 20;
+
+/// At most this many bytes are read from the end of a log by [tail]
+const TAIL_BYTES: u64 = 64 * 1024;
+
+/// Find the most recently modified error log of a sequencing directory.
+///
+/// Returns None if there is none.
+pub fn find_error_log<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+    let dir = dir.as_ref();
+    [dir.join(LOGS_DIR), dir.to_path_buf()]
+        .iter()
+        .filter_map(|d| read_dir(d).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_ascii_lowercase();
+            name.ends_with(".log") && name.contains("error")
+        })
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some((
+                meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                entry.path(),
+            ))
+        })
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// Read the last `lines` lines of a text file.
+///
+/// Only the final 64 KiB are read, so very long lines may be truncated. Invalid UTF-8 is
+/// replaced with U+FFFD. Trailing whitespace is removed.
+pub fn tail<P: AsRef<Path>>(path: P, lines: usize) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut raw = Vec::new();
+    file.read_to_end(&mut raw)?;
+    let text = String::from_utf8_lossy(&raw);
+    let text = text.trim_end();
+    let start = text
+        .rmatch_indices('\n')
+        .nth(lines.saturating_sub(1))
+        .map_or(0, |(i, _)| i + 1);
+    Ok(match lines {
+        0 => String::new(),
+        _ => text[start..].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_error_log, tail};
+
+    const FAILED: &str = "test_data/seq_failed";
+
+    #[test]
+    fn finds_error_log() {
+        let log = find_error_log(FAILED).unwrap();
+        assert!(log.ends_with("Logs/20231231_bar_ABCXYZ_Error.log"));
+        assert!(find_error_log("test_data/seq_complete").is_none());
+    }
+
+    #[test]
+    fn tails_log() {
+        let log = find_error_log(FAILED).unwrap();
+        let last = tail(&log, 2).unwrap();
+        assert_eq!(last.lines().count(), 2);
+        assert!(last.ends_with("turboencabulator failed to frobnicate splines"));
+        assert_eq!(tail(&log, 100).unwrap().lines().count(), 31);
+        assert_eq!(tail(&log, 0).unwrap(), "");
+    }
+}
@@ -51,18 +51,30 @@
 //!
 //! All states are serializable so that they may be treated as emitted events.
 
+use std::cmp::Ordering;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+#[cfg(feature = "notify")]
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Sender, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{SeqDir, SeqDirError};
+use crate::lane::probe_lane_dirs;
+use crate::{
+    RunSummary, SeqDir, SeqDirConfig, SeqDirError, COPY_COMPLETE_TXT, RTA_COMPLETE_TXT,
+    RUN_COMPLETION_STATUS_XML, RUN_INFO_XML, RUN_PARAMS_XML, SEQUENCE_COMPLETE_TXT,
+};
 
 pub(crate) mod sealed {
     pub trait Sealed {}
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "state")]
 /// The current state of the SeqDir.
 ///
@@ -74,7 +86,16 @@ pub enum SeqDirState {
     Failed(FailedSeqDir),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+/// The variant of a [SeqDirState], without the wrapped directory
+pub enum SeqDirStateKind {
+    Complete,
+    Transferring,
+    Sequencing,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 /// The availability of a directory.
 ///
 /// Determined by whether it can be read or not.
@@ -84,23 +105,126 @@ pub enum Availability {
     Unavailable(DateTime<Utc>),
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+/// How to determine whether a directory is available.
+pub enum AvailabilityProbe {
+    /// The directory exists.
+    #[default]
+    Exists,
+    /// The directory exists and its contents can be listed.
+    Readable,
+    /// The directory contains a RunInfo.xml file.
+    ///
+    /// Useful for treating a mounted-but-empty (e.g. stale NFS) directory as unavailable.
+    RequiresRunInfo,
+    /// The directory's contents can be listed and read, see [AccessLevel].
+    FullAccess,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+/// How much of a directory the current process may access
+pub enum AccessLevel {
+    /// The directory can be listed and its files opened.
+    Full,
+    /// The directory can be listed, but its files cannot be inspected or opened.
+    ///
+    /// Typical of a directory with read but not execute permission, or whose files are
+    /// unreadable to the current user.
+    ListOnly,
+    /// The directory cannot be listed.
+    Denied,
+}
+
+impl AccessLevel {
+    /// Probe the access level of the directory at `path`.
+    ///
+    /// Lists the directory, then tries to open RunInfo.xml or, if that does not exist, the
+    /// first regular file listed. Only permission errors downgrade the level to ListOnly; an
+    /// empty directory is Full.
+    pub fn of<P: AsRef<Path>>(path: P) -> AccessLevel {
+        use std::io::ErrorKind;
+        let Ok(entries) = std::fs::read_dir(&path) else {
+            return AccessLevel::Denied;
+        };
+        let opened =
+            match std::fs::File::open(path.as_ref().join(RUN_INFO_XML)) {
+                Err(e) if e.kind() == ErrorKind::NotFound => entries
+                    .filter_map(Result::ok)
+                    .find_map(|entry| match entry.metadata() {
+                        Ok(meta) if !meta.is_file() => None,
+                        Ok(..) => Some(std::fs::File::open(entry.path()).map(drop)),
+                        Err(e) => Some(Err(e)),
+                    }),
+                result => Some(result.map(drop)),
+            };
+        match opened {
+            Some(Err(e)) if e.kind() == ErrorKind::PermissionDenied => AccessLevel::ListOnly,
+            _ => AccessLevel::Full,
+        }
+    }
+}
+
+impl AvailabilityProbe {
+    /// Returns true if `path` is available according to this probe
+    pub fn probe<P: AsRef<Path>>(&self, path: P) -> bool {
+        match self {
+            AvailabilityProbe::Exists => path.as_ref().exists(),
+            AvailabilityProbe::Readable => std::fs::read_dir(path).is_ok(),
+            AvailabilityProbe::RequiresRunInfo => path.as_ref().join(RUN_INFO_XML).is_file(),
+            AvailabilityProbe::FullAccess => AccessLevel::of(path) == AccessLevel::Full,
+        }
+    }
+}
+
 impl Availability {
     /// Compares self to updated availability. If it differs, emit
     /// the correct variant with updated timestamp. Otherwise,
     /// return self with original timestamp.
+    ///
+    /// Availability is determined by [AvailabilityProbe::Exists].
     pub fn check<P: AsRef<Path>>(self, path: P) -> Availability {
-        let exists = path.as_ref().exists();
+        self.check_with(path, AvailabilityProbe::Exists)
+    }
+
+    /// Like [check](Availability::check()), but uses [SeqDir::is_available], which respects the
+    /// [AvailabilityProbe] configured for `seq_dir`.
+    pub fn check_dir(self, seq_dir: &SeqDir) -> Availability {
+        self.check_dir_at(seq_dir, Utc::now())
+    }
+
+    /// Like [check_dir](Availability::check_dir()), but timestamps any change with `now`.
+    pub fn check_dir_at(self, seq_dir: &SeqDir, now: DateTime<Utc>) -> Availability {
+        self.update(seq_dir.is_available(), now)
+    }
+
+    /// Like [check](Availability::check()), but determines availability with `probe`.
+    pub fn check_with<P: AsRef<Path>>(self, path: P, probe: AvailabilityProbe) -> Availability {
+        self.check_with_at(path, probe, Utc::now())
+    }
+
+    /// Like [check_with](Availability::check_with()), but timestamps any change with `now`.
+    pub fn check_with_at<P: AsRef<Path>>(
+        self,
+        path: P,
+        probe: AvailabilityProbe,
+        now: DateTime<Utc>,
+    ) -> Availability {
+        self.update(probe.probe(path), now)
+    }
+
+    /// Update to the observed availability, keeping the timestamp if it has not changed
+    fn update(self, exists: bool, now: DateTime<Utc>) -> Availability {
         match self {
             Availability::Available(..) => {
                 if exists {
                     self
                 } else {
-                    Availability::Unavailable(Utc::now())
+                    Availability::Unavailable(now)
                 }
             }
             Availability::Unavailable(..) => {
                 if exists {
-                    Availability::Available(Utc::now())
+                    Availability::Available(now)
                 } else {
                     self
                 }
@@ -110,15 +234,23 @@ impl Availability {
 }
 
 /// Implemented for structs that can transition to another state.
-pub trait Transition: sealed::Sealed {
+pub trait Transition: sealed::Sealed + Sized {
     /// Attempt to perform a state transition.
     ///
     /// On transition, struct is consumed and wrapped by the appropriate [SeqDirState]
-    fn transition(self) -> SeqDirState;
+    fn transition(self) -> SeqDirState {
+        self.transition_at(Utc::now())
+    }
+
+    /// Like [transition](Transition::transition()), but timestamps any change with `now`.
+    fn transition_at(self, now: DateTime<Utc>) -> SeqDirState;
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 /// A directory whose run has completed sequencing.
+///
+/// When possible, `since` is the completion time recorded by CopyComplete.txt rather than the
+/// time the transition was observed.
 pub struct CompleteSeqDir {
     #[serde(flatten)]
     seq_dir: SeqDir,
@@ -127,7 +259,7 @@ pub struct CompleteSeqDir {
 }
 
 /// A directory whose run is actively sequencing
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SequencingSeqDir {
     #[serde(flatten)]
     seq_dir: SeqDir,
@@ -136,7 +268,7 @@ pub struct SequencingSeqDir {
 }
 
 /// A directory whose run has failed sequencing.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FailedSeqDir {
     #[serde(flatten)]
     seq_dir: SeqDir,
@@ -145,7 +277,7 @@ pub struct FailedSeqDir {
 }
 
 /// A directory whose run is transferring.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TransferringSeqDir {
     #[serde(flatten)]
     seq_dir: SeqDir,
@@ -153,6 +285,29 @@ pub struct TransferringSeqDir {
     availability: Availability,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+/// What a [Transferring](SeqDirState::Transferring) directory is waiting on
+pub enum TransferPhase {
+    /// RTA is still basecalling, RTAComplete.txt is absent
+    Basecalling,
+    /// Basecalling is done and the run is being copied, RTAComplete.txt is present
+    Copying,
+}
+
+impl TransferringSeqDir {
+    /// Determine the [TransferPhase] of the directory.
+    ///
+    /// Re-evaluated on every call. This is informational only; both phases are part of the same
+    /// state.
+    pub fn phase(&self) -> TransferPhase {
+        if self.seq_dir.is_rta_complete() {
+            TransferPhase::Copying
+        } else {
+            TransferPhase::Basecalling
+        }
+    }
+}
+
 impl sealed::Sealed for CompleteSeqDir {}
 impl sealed::Sealed for TransferringSeqDir {}
 impl sealed::Sealed for FailedSeqDir {}
@@ -160,9 +315,9 @@ impl sealed::Sealed for SequencingSeqDir {}
 
 /// Completed must only transition to itself, possibly updating its [Availability]
 impl Transition for CompleteSeqDir {
-    fn transition(self) -> SeqDirState {
+    fn transition_at(self, now: DateTime<Utc>) -> SeqDirState {
         SeqDirState::Complete(CompleteSeqDir {
-            availability: self.availability.check(self.seq_dir.root()),
+            availability: self.availability.check_dir_at(&self.seq_dir, now),
             ..self
         })
     }
@@ -175,20 +330,21 @@ impl Transition for CompleteSeqDir {
 /// If [is_failed](SeqDir::is_failed()) returns true, transitions to Failed.
 /// Otherwise, availability is updated and returns self.
 impl Transition for TransferringSeqDir {
-    fn transition(self) -> SeqDirState {
+    fn transition_at(self, now: DateTime<Utc>) -> SeqDirState {
         if self.seq_dir.is_unavailable() {
             return SeqDirState::Transferring(TransferringSeqDir {
-                availability: self.availability.check(self.seq_dir.root()),
+                availability: self.availability.check_dir_at(&self.seq_dir, now),
                 ..self
             });
         }
+        let availability = self.availability.check_dir_at(&self.seq_dir, now);
         if self.seq_dir.is_copy_complete() {
-            SeqDirState::Complete(CompleteSeqDir::from(self))
+            SeqDirState::Complete(CompleteSeqDir::entered(self.seq_dir, availability, now))
         } else if self.seq_dir.is_failed().unwrap_or(false) {
-            SeqDirState::Failed(FailedSeqDir::from(self))
+            SeqDirState::Failed(FailedSeqDir::entered(self.seq_dir, availability, now))
         } else {
             SeqDirState::Transferring(TransferringSeqDir {
-                availability: self.availability.check(self.seq_dir.root()),
+                availability,
                 ..self
             })
         }
@@ -203,87 +359,114 @@ impl Transition for TransferringSeqDir {
 /// If CopyComplete.txt is found, transitions to Completed.
 /// Otherwise, is assumed to be Transferring (as SequenceComplete is present but not CopyComplete).
 impl Transition for SequencingSeqDir {
-    fn transition(self) -> SeqDirState {
+    fn transition_at(self, now: DateTime<Utc>) -> SeqDirState {
         if self.seq_dir.is_unavailable() {
             return SeqDirState::Sequencing(SequencingSeqDir {
-                availability: self.availability.check(self.seq_dir.root()),
+                availability: self.availability.check_dir_at(&self.seq_dir, now),
                 ..self
             });
         }
+        let availability = self.availability.check_dir_at(&self.seq_dir, now);
         if self.seq_dir.is_failed().unwrap_or(false) {
-            SeqDirState::Failed(FailedSeqDir::from(self))
+            SeqDirState::Failed(FailedSeqDir::entered(self.seq_dir, availability, now))
         } else if self.seq_dir.is_sequencing() {
-            return SeqDirState::Sequencing(self);
+            SeqDirState::Sequencing(SequencingSeqDir {
+                availability,
+                ..self
+            })
         } else if self.seq_dir.is_copy_complete() {
-            SeqDirState::Complete(CompleteSeqDir::from(self))
+            SeqDirState::Complete(CompleteSeqDir::entered(self.seq_dir, availability, now))
         } else {
-            SeqDirState::Transferring(TransferringSeqDir::from(self))
+            SeqDirState::Transferring(TransferringSeqDir::entered(self.seq_dir, availability, now))
         }
     }
 }
 
 /// Failed must only transition to itself, possibly updating its [Availability].
 impl Transition for FailedSeqDir {
-    fn transition(self) -> SeqDirState {
+    fn transition_at(self, now: DateTime<Utc>) -> SeqDirState {
         SeqDirState::Failed(FailedSeqDir {
-            availability: self.availability.check(self.seq_dir.root()),
+            availability: self.availability.check_dir_at(&self.seq_dir, now),
             ..self
         })
     }
 }
 
+impl CompleteSeqDir {
+    /// Enter the Complete state at `now`, or when CopyComplete.txt says the run completed
+    fn entered(seq_dir: SeqDir, availability: Availability, now: DateTime<Utc>) -> Self {
+        CompleteSeqDir {
+            availability: availability.check_dir_at(&seq_dir, now),
+            since: completed_since(&seq_dir, now),
+            seq_dir,
+        }
+    }
+}
+
+impl FailedSeqDir {
+    /// Enter the Failed state at `now`
+    fn entered(seq_dir: SeqDir, availability: Availability, now: DateTime<Utc>) -> Self {
+        FailedSeqDir {
+            availability: availability.check_dir_at(&seq_dir, now),
+            seq_dir,
+            since: now,
+        }
+    }
+}
+
+impl TransferringSeqDir {
+    /// Enter the Transferring state at `now`
+    fn entered(seq_dir: SeqDir, availability: Availability, now: DateTime<Utc>) -> Self {
+        TransferringSeqDir {
+            availability: availability.check_dir_at(&seq_dir, now),
+            seq_dir,
+            since: now,
+        }
+    }
+}
+
 impl From<SequencingSeqDir> for CompleteSeqDir {
     /// Sequencing -> Available
     fn from(value: SequencingSeqDir) -> Self {
-        CompleteSeqDir {
-            availability: value.availability.check(value.seq_dir.root()),
-            seq_dir: value.seq_dir,
-            since: Utc::now(),
-        }
+        CompleteSeqDir::entered(value.seq_dir, value.availability, Utc::now())
     }
 }
 
 impl From<SequencingSeqDir> for FailedSeqDir {
     /// Sequencing -> Failed
     fn from(value: SequencingSeqDir) -> Self {
-        FailedSeqDir {
-            availability: value.availability.check(value.seq_dir.root()),
-            seq_dir: value.seq_dir,
-            since: Utc::now(),
-        }
+        FailedSeqDir::entered(value.seq_dir, value.availability, Utc::now())
     }
 }
 
 impl From<SequencingSeqDir> for TransferringSeqDir {
     /// Sequencing -> Transferring
     fn from(value: SequencingSeqDir) -> Self {
-        TransferringSeqDir {
-            availability: value.availability.check(value.seq_dir.root()),
-            seq_dir: value.seq_dir,
-            since: Utc::now(),
-        }
+        TransferringSeqDir::entered(value.seq_dir, value.availability, Utc::now())
     }
 }
 
 impl From<TransferringSeqDir> for CompleteSeqDir {
     /// Transferring -> Available
     fn from(value: TransferringSeqDir) -> Self {
-        CompleteSeqDir {
-            availability: value.availability.check(value.seq_dir.root()),
-            seq_dir: value.seq_dir,
-            since: Utc::now(),
-        }
+        CompleteSeqDir::entered(value.seq_dir, value.availability, Utc::now())
+    }
+}
+
+/// When a directory entered the Complete state.
+///
+/// Uses the completion time recorded by CopyComplete.txt if available, otherwise `now`.
+fn completed_since(seq_dir: &SeqDir, now: DateTime<Utc>) -> DateTime<Utc> {
+    match seq_dir.copy_complete_info() {
+        Ok(Some(info)) => info.completed_at.unwrap_or(now),
+        _ => now,
     }
 }
 
 impl From<TransferringSeqDir> for FailedSeqDir {
     /// Transferring -> Failed
     fn from(value: TransferringSeqDir) -> Self {
-        FailedSeqDir {
-            availability: value.availability.check(value.seq_dir.root()),
-            seq_dir: value.seq_dir,
-            since: Utc::now(),
-        }
+        FailedSeqDir::entered(value.seq_dir, value.availability, Utc::now())
     }
 }
 
@@ -308,9 +491,51 @@ impl SeqDirState {
         }
     }
 
+    /// Returns the [SeqDirStateKind] of the state
+    pub fn kind(&self) -> SeqDirStateKind {
+        match self {
+            SeqDirState::Complete(..) => SeqDirStateKind::Complete,
+            SeqDirState::Transferring(..) => SeqDirStateKind::Transferring,
+            SeqDirState::Sequencing(..) => SeqDirStateKind::Sequencing,
+            SeqDirState::Failed(..) => SeqDirStateKind::Failed,
+        }
+    }
+
+    /// How long the directory has been in this state, as of now.
+    ///
+    /// Never negative, even if `since` is in the future, e.g. because of clock skew with the
+    /// instrument that wrote CopyComplete.txt.
+    pub fn elapsed(&self) -> chrono::Duration {
+        self.elapsed_at(Utc::now())
+    }
+
+    fn elapsed_at(&self, now: DateTime<Utc>) -> chrono::Duration {
+        (now - *self.since()).max(chrono::Duration::zero())
+    }
+
+    /// Compare two states by when they were entered, earliest first.
+    ///
+    /// Only `since` is compared, so states of different variants or directories may be equal.
+    pub fn cmp_by_since(&self, other: &Self) -> Ordering {
+        self.since().cmp(other.since())
+    }
+
+    /// Returns true if the state is Complete or Failed, which never transition to another state
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, SeqDirState::Complete(..) | SeqDirState::Failed(..))
+    }
+
+    /// Returns the [TransferPhase] if the state is Transferring, otherwise None.
+    pub fn transfer_phase(&self) -> Option<TransferPhase> {
+        match self {
+            SeqDirState::Transferring(dir) => Some(dir.phase()),
+            _ => None,
+        }
+    }
+
     /// Mutable reference to inner SeqDir
     #[cfg(test)]
-    fn dir_mut(&mut self) -> &mut SeqDir {
+    pub(crate) fn dir_mut(&mut self) -> &mut SeqDir {
         match self {
             SeqDirState::Failed(dir) => &mut dir.seq_dir,
             SeqDirState::Complete(dir) => &mut dir.seq_dir,
@@ -319,12 +544,12 @@ impl SeqDirState {
         }
     }
 
-    fn transition(self) -> Self {
+    fn transition_at(self, now: DateTime<Utc>) -> Self {
         match self {
-            SeqDirState::Complete(dir) => dir.transition(),
-            SeqDirState::Failed(dir) => dir.transition(),
-            SeqDirState::Sequencing(dir) => dir.transition(),
-            SeqDirState::Transferring(dir) => dir.transition(),
+            SeqDirState::Complete(dir) => dir.transition_at(now),
+            SeqDirState::Failed(dir) => dir.transition_at(now),
+            SeqDirState::Sequencing(dir) => dir.transition_at(now),
+            SeqDirState::Transferring(dir) => dir.transition_at(now),
         }
     }
 
@@ -358,41 +583,234 @@ impl SeqDirState {
         matches!(self.availablity(), Availability::Available(..))
     }
 
+    /// Returns when the directory became unavailable, or None if it is available.
+    ///
+    /// The timestamp is that of the most recent availability check to find the directory
+    /// unavailable after it had been available, so it marks the start of the current outage.
+    /// Compare with [since](SeqDirState::since()) to tell a stalled run from a dropped mount.
+    pub fn unavailable_since(&self) -> Option<DateTime<Utc>> {
+        match self.availablity() {
+            Availability::Unavailable(since) => Some(*since),
+            Availability::Available(..) => None,
+        }
+    }
+
+    /// Probe the accessibility of each lane directory, returning `(lane number, accessible)`.
+    ///
+    /// Lanes are listed in ascending order. On tiered storage, individual lanes can be evicted
+    /// while the root remains available, which [available](SeqDirState::available()) cannot
+    /// detect. This is purely informational and does not affect state transitions.
+    pub fn lane_availability(&self) -> Vec<(u8, bool)> {
+        probe_lane_dirs(self.dir().root())
+    }
+
+    /// Wrap the state so that it serializes with its age, see [StateWithAge]
+    pub fn with_age(&self) -> StateWithAge<'_> {
+        StateWithAge::new(self)
+    }
+
     /// Check the current availablity, possibly updating it, and return true if available
     ///
     /// See [available](SeqDirState::available()) for an immutable alternative.
     pub fn check_available(&mut self) -> bool {
-        *self.availability_mut() = self.availability_mut().check(self.dir().root());
+        self.check_available_at(Utc::now())
+    }
+
+    /// Like [check_available](SeqDirState::check_available()), but timestamps any change with
+    /// `now`.
+    pub fn check_available_at(&mut self, now: DateTime<Utc>) -> bool {
+        *self.availability_mut() = self.availability_mut().check_dir_at(self.dir(), now);
         self.available()
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+/// Serializes a [SeqDirState] along with `age_seconds`, the number of whole seconds since the
+/// state was entered.
+///
+/// Age is computed when the wrapper is serialized, not when it is created, unless a reference
+/// time is given with [at](StateWithAge::at()). Ages are never negative.
+pub struct StateWithAge<'a> {
+    state: &'a SeqDirState,
+    now: Option<DateTime<Utc>>,
+}
+
+impl<'a> StateWithAge<'a> {
+    pub fn new(state: &'a SeqDirState) -> Self {
+        StateWithAge { state, now: None }
+    }
+
+    /// Compute the age relative to `now` instead of the time of serialization
+    pub fn at(self, now: DateTime<Utc>) -> Self {
+        StateWithAge {
+            now: Some(now),
+            ..self
+        }
+    }
+
+    /// Returns the age of the state in whole seconds, relative to the reference time or now.
+    ///
+    /// Without a reference time, now is [Utc::now]; use [DirManager::with_age] to measure age
+    /// with a manager's [Clock] instead.
+    pub fn age_seconds(&self) -> i64 {
+        let now = self.now.unwrap_or_else(Utc::now);
+        self.state.elapsed_at(now).num_seconds()
+    }
+}
+
+impl Serialize for StateWithAge<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct WithAge<'a> {
+            #[serde(flatten)]
+            state: &'a SeqDirState,
+            age_seconds: i64,
+        }
+        WithAge {
+            state: self.state,
+            age_seconds: self.age_seconds(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// A source of the current time.
+///
+/// [DirManager] uses a Clock to timestamp state and availability changes. [RealClock] is used
+/// unless another is provided with [with_clock](DirManager::with_clock()), which is mostly
+/// useful to make timestamps deterministic in tests.
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The system clock, see [Utc::now]
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
 #[derive(Clone)]
 /// Implements a state machine for managing the state of a [SeqDir].
 ///
 /// Once a directory has gone to either [Complete](SeqDirState::Complete) or
 /// [Failed](SeqDirState::Failed), it cannot transition back to another state.
 /// However, the [Availability] of the dir may still update on every call to [poll](DirManager::poll()).
+///
+/// A directory that has been removed for good can be [marked deleted](DirManager::mark_deleted()),
+/// after which polling has no effect.
 pub struct DirManager {
     seq_dir: SeqDirState,
+    clock: Arc<dyn Clock>,
+    deleted: Option<DateTime<Utc>>,
+    on_transition: Vec<TransitionCallback>,
+    history: Option<Vec<(DateTime<Utc>, SeqDirStateKind)>>,
 }
 
+/// A callback registered with [on_transition](DirManager::on_transition())
+type TransitionCallback = Arc<Mutex<dyn FnMut(&SeqDirState, &SeqDirState) + Send>>;
+
 impl DirManager {
     /// Construct a new DirManager from a path.
     ///
     /// The initial state will always be Sequencing', but `poll` is called
     /// automatically before returning, so the state will be accurate.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, SeqDirError> {
-        let seq_dir = SeqDir::from_path(&path)?;
+        Self::new_boxed(path.as_ref())
+    }
+
+    /// Construct a new DirManager from a borrowed path.
+    ///
+    /// Non-generic equivalent of [new](DirManager::new()), useful when paths come from
+    /// heterogeneous sources (e.g. `&dyn AsRef<Path>`). The path is always copied into an owned
+    /// `PathBuf`, so DirManagers constructed either way are the same type.
+    pub fn new_boxed(path: &Path) -> Result<Self, SeqDirError> {
+        Ok(Self::from_seq_dir(SeqDir::from_path(path)?))
+    }
+
+    /// Returns a [DirManagerBuilder] for configuring a new DirManager
+    pub fn builder<P: AsRef<Path>>(path: P) -> DirManagerBuilder {
+        DirManagerBuilder {
+            path: path.as_ref().to_owned(),
+            config: SeqDirConfig::default(),
+            clock: Arc::new(RealClock),
+        }
+    }
+
+    /// Construct a new DirManager from a path, using the provided [SeqDirConfig].
+    pub fn with_config<P: AsRef<Path>>(path: P, config: SeqDirConfig) -> Result<Self, SeqDirError> {
+        Ok(Self::from_seq_dir(
+            SeqDir::from_path(path)?.with_config(config),
+        ))
+    }
+
+    /// Construct a new DirManager from a path, using `clock` for every timestamp.
+    ///
+    /// Timestamps read from the directory itself, such as the completion time recorded in
+    /// CopyComplete.txt, are unaffected.
+    pub fn with_clock<P: AsRef<Path>, C: Clock + 'static>(
+        path: P,
+        clock: C,
+    ) -> Result<Self, SeqDirError> {
+        Ok(Self::from_seq_dir_with_clock(
+            SeqDir::from_path(path)?,
+            Arc::new(clock),
+        ))
+    }
+
+    /// Construct a new DirManager from a path, recording a [history](DirManager::history()) of
+    /// its state changes.
+    ///
+    /// The history starts with the state found by the initial poll.
+    pub fn with_history<P: AsRef<Path>>(path: P) -> Result<Self, SeqDirError> {
+        let mut dir_manager = Self::new(path)?;
+        dir_manager.history = Some(vec![(
+            *dir_manager.seq_dir.since(),
+            dir_manager.seq_dir.kind(),
+        )]);
+        Ok(dir_manager)
+    }
+
+    /// Start managing an existing [SeqDir], polling once before returning.
+    pub fn from_seq_dir(seq_dir: SeqDir) -> Self {
+        Self::from_seq_dir_with_clock(seq_dir, Arc::new(RealClock))
+    }
+
+    /// Resume managing a directory in a previously recorded state, e.g. one deserialized after a
+    /// restart.
+    ///
+    /// Unlike the other constructors this does not poll, so the state, including its `since`
+    /// timestamp, is exactly as given until the next [poll](DirManager::poll()). The directory
+    /// uses the default [SeqDirConfig], which is not serialized, and is not
+    /// [marked deleted](DirManager::mark_deleted()) since the state does not record that either.
+    pub fn from_state(state: SeqDirState) -> Self {
+        DirManager {
+            seq_dir: state,
+            clock: Arc::new(RealClock),
+            deleted: None,
+            on_transition: Vec::new(),
+            history: None,
+        }
+    }
+
+    fn from_seq_dir_with_clock(seq_dir: SeqDir, clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
         let mut dir_manager = DirManager {
             seq_dir: SeqDirState::Sequencing(SequencingSeqDir {
                 seq_dir,
-                since: Utc::now(),
-                availability: Availability::Available(Utc::now()),
+                since: now,
+                availability: Availability::Available(now),
             }),
+            clock,
+            deleted: None,
+            on_transition: Vec::new(),
+            history: None,
         };
         dir_manager.poll();
-        Ok(dir_manager)
+        dir_manager
     }
 
     /// Consume the DirManager, returning contained SeqDir, regardless of state.
@@ -407,6 +825,13 @@ impl DirManager {
         }
     }
 
+    /// Consume the DirManager, returning a [RunSummary] of its final state.
+    ///
+    /// Like [into_inner](DirManager::into_inner()), but returns a richer report.
+    pub fn into_summary(self) -> RunSummary {
+        RunSummary::from(self.seq_dir)
+    }
+
     /// Returns reference to the inner SeqDir being managed.
     pub fn inner(&self) -> &SeqDir {
         self.seq_dir.dir()
@@ -430,10 +855,10 @@ impl DirManager {
 
     /// Attempt to perform a transition, possibly updating the state.
     ///
+    /// Does nothing once the manager is [marked deleted](DirManager::mark_deleted()).
     /// Returns reference to current state.
     pub fn poll(&mut self) -> &SeqDirState {
-        let state = std::mem::replace(&mut self.seq_dir, _default());
-        self.seq_dir = state.transition();
+        self.transition();
         self.state()
     }
 
@@ -442,15 +867,424 @@ impl DirManager {
     /// Returns mutable reference to current state.
     /// CAUTION: poll_mut should be used judiciously.
     pub fn poll_mut(&mut self) -> &mut SeqDirState {
-        let state = std::mem::replace(&mut self.seq_dir, _default());
-        self.seq_dir = state.transition();
+        self.transition();
         self.state_mut()
     }
 
+    fn transition(&mut self) {
+        if self.is_deleted() {
+            return;
+        }
+        let previous_kind = self.seq_dir.kind();
+        let previous = (!self.on_transition.is_empty()).then(|| self.seq_dir.clone());
+        let state = std::mem::replace(&mut self.seq_dir, _default());
+        self.seq_dir = state.transition_at(self.clock.now());
+        let kind = self.seq_dir.kind();
+        if kind == previous_kind {
+            return;
+        }
+        if let Some(history) = &mut self.history {
+            history.push((*self.seq_dir.since(), kind));
+        }
+        if let Some(previous) = previous {
+            for callback in &self.on_transition {
+                let mut callback = callback.lock().unwrap_or_else(PoisonError::into_inner);
+                callback(&previous, &self.seq_dir);
+            }
+        }
+    }
+
+    /// Every state the directory has been in, oldest first, with when it was entered as given by
+    /// [since](SeqDirState::since()).
+    ///
+    /// Empty unless the DirManager was constructed with [with_history](DirManager::with_history()).
+    pub fn history(&self) -> &[(DateTime<Utc>, SeqDirStateKind)] {
+        self.history.as_deref().unwrap_or_default()
+    }
+
+    /// Register `f` to be called with the previous and new state whenever polling changes the
+    /// state variant, e.g. from Sequencing to Transferring.
+    ///
+    /// Changes in availability alone, and polls that leave the variant as-is, do not call `f`.
+    /// Callbacks run in the order they were registered, on the thread that polls. They are
+    /// shared with clones of the manager, so a transition polled on either calls them.
+    pub fn on_transition<F>(&mut self, f: F)
+    where
+        F: FnMut(&SeqDirState, &SeqDirState) + Send + 'static,
+    {
+        self.on_transition.push(Arc::new(Mutex::new(f)));
+    }
+
+    /// Record that the directory was permanently removed.
+    ///
+    /// Unlike [Unavailable](Availability::Unavailable), which may be temporary (e.g. an
+    /// unmounted share), this is terminal: every later poll is a no-op and the last state is
+    /// kept as-is. Loops that wait for a terminal state, such as
+    /// [into_event_iter](DirManager::into_event_iter()) and
+    /// [poll_until_terminal](DirManager::poll_until_terminal()), stop once the manager is deleted.
+    /// Marking an already deleted manager keeps the original timestamp.
+    pub fn mark_deleted(&mut self) {
+        self.deleted.get_or_insert_with(|| self.clock.now());
+    }
+
+    /// Returns true if the directory has been [marked deleted](DirManager::mark_deleted())
+    pub fn is_deleted(&self) -> bool {
+        self.deleted.is_some()
+    }
+
+    /// Timestamp of when the directory was [marked deleted](DirManager::mark_deleted()), if it was
+    pub fn deleted_at(&self) -> Option<&DateTime<Utc>> {
+        self.deleted.as_ref()
+    }
+
+    /// Returns true if polling can no longer change the state, because it is terminal or the
+    /// directory was deleted
+    fn is_finished(&self) -> bool {
+        self.is_deleted() || self.state().is_terminal()
+    }
+
+    /// Attempt to perform a transition, retrying on transient IO errors.
+    ///
+    /// Before transitioning, the root directory and the sentinel and metadata files read by a
+    /// transition (CopyComplete.txt, RTAComplete.txt, SequenceComplete.txt,
+    /// RunCompletionStatus.xml, RunInfo.xml and RunParameters.xml) are stat'ed up to `retries`
+    /// additional times, sleeping for `backoff` between attempts, as long as any of them fails
+    /// with a transient error (EIO, ETIMEDOUT, stale file handle). These are common on network
+    /// mounts and usually resolve on their own. Hard errors or exhausted retries fall through to a
+    /// regular [poll](DirManager::poll()), which will mark the directory as Unavailable if the
+    /// root cannot be reached.
+    ///
+    /// Only the probes are retried: a transient error while the transition itself reads those
+    /// files is handled as in a regular poll.
+    ///
+    /// Returns reference to current state.
+    pub fn poll_with_retry(&mut self, retries: u32, backoff: Duration) -> &SeqDirState {
+        if self.is_deleted() {
+            return self.state();
+        }
+        let root = self.inner().root().to_owned();
+        // poll reports an unreachable root itself, so the outcome of the probes is not needed
+        let _ = retry_transient(retries, backoff, || probe_run_files(&root));
+        self.poll()
+    }
+
     /// Timestamp of when the DirManager's SeqDir entered its current state
     pub fn since(&self) -> &DateTime<Utc> {
         self.seq_dir.since()
     }
+
+    /// How long the DirManager's SeqDir has been in its current state, as of now.
+    ///
+    /// Now is given by the DirManager's [Clock], which is [Utc::now] unless another was provided.
+    /// See [SeqDirState::elapsed].
+    pub fn elapsed(&self) -> chrono::Duration {
+        self.seq_dir.elapsed_at(self.clock.now())
+    }
+
+    /// Wrap the state so that it serializes with its age as of now, see [StateWithAge].
+    ///
+    /// Now is given by the DirManager's [Clock], see [SeqDirState::with_age].
+    pub fn with_age(&self) -> StateWithAge<'_> {
+        self.seq_dir.with_age().at(self.clock.now())
+    }
+
+    /// Check the current availability, possibly updating it, and return true if available.
+    ///
+    /// Any change is timestamped by the DirManager's [Clock], see
+    /// [SeqDirState::check_available].
+    pub fn check_available(&mut self) -> bool {
+        let now = self.clock.now();
+        self.seq_dir.check_available_at(now)
+    }
+
+    /// Sort managers by how recently their directories changed state, most recent first.
+    ///
+    /// The sort is stable, so managers whose states were entered at the same time keep their
+    /// relative order. See [cmp_by_since](SeqDirState::cmp_by_since()).
+    pub fn sort_by_recency(managers: &mut [DirManager]) {
+        managers.sort_by(|a, b| b.state().cmp_by_since(a.state()));
+    }
+
+    /// Consume the DirManager, returning an iterator that yields each new state as it is entered.
+    ///
+    /// Each call to `next` blocks, polling every `interval`, until the state changes variant.
+    /// Changes in availability alone are not yielded. The iterator ends after yielding a
+    /// terminal state; if the directory is already Complete or Failed, that state is yielded
+    /// once and the iterator ends. If the manager is [marked deleted](DirManager::mark_deleted()),
+    /// the iterator is empty.
+    pub fn into_event_iter(self, interval: Duration) -> impl Iterator<Item = SeqDirState> {
+        let mut manager = (!self.is_deleted()).then_some(self);
+        std::iter::from_fn(move || {
+            let current = manager.as_mut()?;
+            if current.state().is_terminal() {
+                return manager.take().map(|m| m.seq_dir);
+            }
+            let before = std::mem::discriminant(current.state());
+            loop {
+                std::thread::sleep(interval);
+                if std::mem::discriminant(current.poll()) != before {
+                    break;
+                }
+            }
+            if current.state().is_terminal() {
+                manager.take().map(|m| m.seq_dir)
+            } else {
+                Some(current.state().clone())
+            }
+        })
+    }
+
+    /// Poll every `interval` until the directory is Complete or Failed, and return that state.
+    ///
+    /// Returns immediately if the directory is already in a terminal state. If `timeout` is
+    /// given and elapses first, returns SeqDirError::Timeout. The final sleep is shortened so
+    /// the timeout is not overshot by more than one poll. A directory that is
+    /// [marked deleted](DirManager::mark_deleted()) never changes state, so its last state is
+    /// returned immediately, whether or not it is terminal.
+    pub fn poll_until_terminal(
+        &mut self,
+        interval: Duration,
+        timeout: Option<Duration>,
+    ) -> Result<&SeqDirState, SeqDirError> {
+        let start = std::time::Instant::now();
+        self.poll();
+        while !self.is_finished() {
+            let Some(timeout) = timeout else {
+                std::thread::sleep(interval);
+                continue;
+            };
+            let remaining = timeout.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                return Err(SeqDirError::Timeout(timeout));
+            }
+            std::thread::sleep(interval.min(remaining));
+            self.poll();
+        }
+        Ok(self.state())
+    }
+
+    /// Consume the DirManager, spawning a thread that sends each new state over `tx`.
+    ///
+    /// The thread yields the same states as [into_event_iter](DirManager::into_event_iter()),
+    /// polling every `interval`, and exits after sending a terminal state or when a send fails
+    /// because the receiving end of `tx` was dropped. A dropped receiver is only noticed when the
+    /// next state is sent, so the thread keeps polling a directory that never changes state.
+    /// `Sender` is unbounded, but at most one state is sent per transition, so at most two are
+    /// ever sent. See [spawn_watcher_bounded](DirManager::spawn_watcher_bounded()) to apply
+    /// backpressure.
+    pub fn spawn_watcher(self, tx: Sender<SeqDirState>, interval: Duration) -> JoinHandle<()> {
+        self.spawn_sender(interval, move |state| tx.send(state).is_ok())
+    }
+
+    /// Like [spawn_watcher](DirManager::spawn_watcher()), but sends over a bounded channel.
+    ///
+    /// If the channel is full, the watcher blocks until there is room, and does not poll in the
+    /// meantime. Because only changes of state are sent, no transition is lost, but its `since`
+    /// timestamp may be later than when the change actually occurred.
+    pub fn spawn_watcher_bounded(
+        self,
+        tx: SyncSender<SeqDirState>,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        self.spawn_sender(interval, move |state| tx.send(state).is_ok())
+    }
+
+    /// Spawn a thread that passes each new state to `send`, stopping once it returns false
+    fn spawn_sender<F>(self, interval: Duration, mut send: F) -> JoinHandle<()>
+    where
+        F: FnMut(SeqDirState) -> bool + Send + 'static,
+    {
+        std::thread::spawn(move || {
+            for state in self.into_event_iter(interval) {
+                if !send(state) {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Consume the DirManager, spawning a thread that polls whenever the root directory changes.
+    ///
+    /// Enabled by the `notify` feature. The thread sends the same states as
+    /// [spawn_watcher](DirManager::spawn_watcher()) over the returned channel, but rather than
+    /// polling every interval it waits for filesystem notifications. Notifications are
+    /// debounced: after one arrives, the thread waits until none has arrived for `debounce`, so
+    /// a burst of writes causes a single poll. The directory is also polled once when the
+    /// thread starts, to catch changes that happened before the watch was in place.
+    ///
+    /// Only the root is watched, not its subdirectories, since the sentinel files that drive
+    /// every transition are written there. Most network filesystems do not deliver
+    /// notifications for changes made by other hosts; prefer `spawn_watcher` for those.
+    ///
+    /// If the manager is [marked deleted](DirManager::mark_deleted()), nothing is sent and the
+    /// channel is closed straight away.
+    ///
+    /// Errors if the root cannot be watched.
+    #[cfg(feature = "notify")]
+    pub fn watch(mut self, debounce: Duration) -> Result<Receiver<SeqDirState>, SeqDirError> {
+        use notify::Watcher;
+
+        if self.is_deleted() {
+            return Ok(std::sync::mpsc::channel().1);
+        }
+        let (events_tx, events) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(events_tx).map_err(std::io::Error::other)?;
+        watcher
+            .watch(self.inner().root(), notify::RecursiveMode::NonRecursive)
+            .map_err(std::io::Error::other)?;
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let _watcher = watcher;
+            // None if the state after the first poll should be sent regardless
+            let mut previous =
+                (!self.state().is_terminal()).then(|| std::mem::discriminant(self.state()));
+            loop {
+                let state = self.poll();
+                if previous != Some(std::mem::discriminant(state)) {
+                    let terminal = state.is_terminal();
+                    if tx.send(state.clone()).is_err() || terminal {
+                        return;
+                    }
+                    previous = Some(std::mem::discriminant(state));
+                }
+                if events.recv().is_err() {
+                    return;
+                }
+                while events.recv_timeout(debounce).is_ok() {}
+            }
+        });
+        Ok(rx)
+    }
+
+    /// A stable hash of the current state, for cheap change detection across restarts.
+    ///
+    /// The fingerprint covers exactly:
+    /// 1. which [SeqDirState] variant the directory is in
+    /// 2. the `since` timestamp of that state, to the nanosecond
+    /// 3. whether the directory is currently [available](SeqDirState::available())
+    ///
+    /// The path, config and availability timestamp are *not* included. The hash is FNV-1a, so the
+    /// same state produces the same fingerprint across processes, platforms and compiler versions.
+    pub fn state_fingerprint(&self) -> u64 {
+        let discriminant: u8 = match self.seq_dir {
+            SeqDirState::Complete(..) => 0,
+            SeqDirState::Transferring(..) => 1,
+            SeqDirState::Sequencing(..) => 2,
+            SeqDirState::Failed(..) => 3,
+        };
+        let since = self.since();
+        let mut bytes = vec![discriminant];
+        bytes.extend(since.timestamp().to_le_bytes());
+        bytes.extend(since.timestamp_subsec_nanos().to_le_bytes());
+        bytes.push(u8::from(self.seq_dir.available()));
+        fnv1a(&bytes)
+    }
+}
+
+/// 64-bit FNV-1a hash of `bytes`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(PRIME)
+    })
+}
+
+#[derive(Clone, Debug)]
+/// Builder for a [DirManager]
+///
+/// See [DirManager::builder].
+pub struct DirManagerBuilder {
+    path: PathBuf,
+    config: SeqDirConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl DirManagerBuilder {
+    /// Use `config` for the managed [SeqDir].
+    ///
+    /// Overwrites any previously set options.
+    pub fn config(self, config: SeqDirConfig) -> Self {
+        DirManagerBuilder { config, ..self }
+    }
+
+    /// Set how directory availability is determined
+    pub fn availability_probe(mut self, probe: AvailabilityProbe) -> Self {
+        self.config.availability_probe = probe;
+        self
+    }
+
+    /// Use `clock` for every timestamp, see [with_clock](DirManager::with_clock())
+    pub fn clock<C: Clock + 'static>(self, clock: C) -> Self {
+        DirManagerBuilder {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    /// Construct the DirManager.
+    ///
+    /// As with [new](DirManager::new()), the directory is polled once before returning.
+    pub fn build(self) -> Result<DirManager, SeqDirError> {
+        let seq_dir = SeqDir::from_path(self.path)?.with_config(self.config);
+        Ok(DirManager::from_seq_dir_with_clock(seq_dir, self.clock))
+    }
+}
+
+/// Files read while transitioning that [poll_with_retry](DirManager::poll_with_retry()) probes
+const RETRY_PROBED_FILES: [&str; 6] = [
+    COPY_COMPLETE_TXT,
+    RTA_COMPLETE_TXT,
+    SEQUENCE_COMPLETE_TXT,
+    RUN_COMPLETION_STATUS_XML,
+    RUN_INFO_XML,
+    RUN_PARAMS_XML,
+];
+
+/// Stat `root` and the [RETRY_PROBED_FILES] under it, returning the first error other than a
+/// missing file.
+fn probe_run_files(root: &Path) -> std::io::Result<()> {
+    std::fs::metadata(root)?;
+    for name in RETRY_PROBED_FILES {
+        match std::fs::metadata(root.join(name)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Call `probe` until it succeeds or fails with an error that is not
+/// [transient](is_transient), at most `retries` + 1 times, sleeping for `backoff` between calls.
+///
+/// Returns the result of the last call.
+fn retry_transient<F: FnMut() -> std::io::Result<()>>(
+    retries: u32,
+    backoff: Duration,
+    mut probe: F,
+) -> std::io::Result<()> {
+    let mut result = probe();
+    for _ in 0..retries {
+        match result {
+            Err(ref e) if is_transient(e) => std::thread::sleep(backoff),
+            _ => break,
+        }
+        result = probe();
+    }
+    result
+}
+
+/// Returns true if an IO error is likely to resolve itself if retried.
+fn is_transient(err: &std::io::Error) -> bool {
+    // EIO and ESTALE have no stable ErrorKind, so check the raw codes
+    #[cfg(target_os = "linux")]
+    const ESTALE: i32 = 116;
+    #[cfg(not(target_os = "linux"))]
+    const ESTALE: i32 = 70;
+    const EIO: i32 = 5;
+
+    matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted)
+        || (cfg!(unix) && matches!(err.raw_os_error(), Some(EIO) | Some(ESTALE)))
 }
 
 #[doc(hidden)]
@@ -464,6 +1298,11 @@ fn _default() -> SeqDirState {
         run_info: PathBuf::new(),
         run_params: PathBuf::new(),
         run_completion: PathBuf::new(),
+        config: SeqDirConfig::default(),
+        #[cfg(any(test, feature = "test-util"))]
+        mock: None,
+        #[cfg(feature = "archive")]
+        archive: None,
     };
     SeqDirState::Sequencing(SequencingSeqDir {
         seq_dir,
@@ -474,12 +1313,18 @@ fn _default() -> SeqDirState {
 
 #[cfg(test)]
 mod tests {
-    use std::{path::PathBuf, str::FromStr};
+    use std::{path::PathBuf, str::FromStr, time::Duration};
 
-    use super::{DirManager, SeqDirState};
+    use super::{
+        is_transient, retry_transient, AccessLevel, AvailabilityProbe, DirManager, SeqDirState,
+        SeqDirStateKind,
+    };
+    use crate::test_dir::TestDir;
+    use crate::{SeqDir, SeqDirConfig};
 
     const COMPLETE: &str = "test_data/seq_complete/";
     const FAILED: &str = "test_data/seq_failed/";
+    const RUNPARAMS_STATUS: &str = "test_data/seq_runparams_status/";
     const TRANSFERRING: &str = "test_data/seq_transferring/";
 
     #[test]
@@ -510,6 +1355,463 @@ mod tests {
         };
     }
 
+    #[test]
+    fn serializes_age() {
+        let manager = DirManager::new(COMPLETE).unwrap();
+        let since = *manager.since();
+        let value = serde_json::to_value(
+            manager
+                .state()
+                .with_age()
+                .at(since + chrono::Duration::seconds(90)),
+        )
+        .unwrap();
+        assert_eq!(value["state"], "Complete");
+        assert_eq!(value["age_seconds"], 90);
+        assert!(value.get("since").is_some());
+        // a reference time before the state was entered does not produce a negative age
+        let state = manager
+            .state()
+            .with_age()
+            .at(since - chrono::Duration::seconds(5));
+        assert_eq!(state.age_seconds(), 0);
+    }
+
+    #[cfg(feature = "notify")]
+    #[test]
+    fn watch_notifications() {
+        use crate::{COPY_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT};
+
+        let root = TestDir::new("watch");
+        let timeout = Duration::from_secs(5);
+        let rx = DirManager::new(&root)
+            .unwrap()
+            .watch(Duration::from_millis(10))
+            .unwrap();
+        std::fs::write(root.join(SEQUENCE_COMPLETE_TXT), "").unwrap();
+        assert!(matches!(
+            rx.recv_timeout(timeout).unwrap(),
+            SeqDirState::Transferring(..)
+        ));
+        std::fs::write(root.join(COPY_COMPLETE_TXT), "").unwrap();
+        assert!(matches!(
+            rx.recv_timeout(timeout).unwrap(),
+            SeqDirState::Complete(..)
+        ));
+        assert!(rx.recv_timeout(timeout).is_err());
+
+        let rx = DirManager::new(COMPLETE)
+            .unwrap()
+            .watch(Duration::from_millis(10))
+            .unwrap();
+        assert_eq!(rx.iter().count(), 1);
+    }
+
+    #[test]
+    fn poll_until_terminal() {
+        use crate::mock::MockBuilder;
+        use crate::{SeqDirError, COPY_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT};
+
+        let interval = Duration::from_millis(1);
+        let mut complete = DirManager::new(COMPLETE).unwrap();
+        assert!(complete
+            .poll_until_terminal(interval, None)
+            .unwrap()
+            .is_terminal());
+
+        let mock = MockBuilder::new();
+        let seq_dir = SeqDir::mock(PathBuf::from("/does/not/exist"), mock.clone());
+        let mut manager = DirManager::from_seq_dir(seq_dir);
+        assert!(matches!(
+            manager.poll_until_terminal(interval, Some(Duration::from_millis(20))),
+            Err(SeqDirError::Timeout(..))
+        ));
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            mock.add_file(SEQUENCE_COMPLETE_TXT);
+            mock.add_file(COPY_COMPLETE_TXT);
+        });
+        let state = manager
+            .poll_until_terminal(interval, Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(matches!(state, SeqDirState::Complete(..)));
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn transition_callbacks() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::mock::MockBuilder;
+        use crate::{COPY_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT};
+
+        let mock = MockBuilder::new();
+        let seq_dir = SeqDir::mock(PathBuf::from("/does/not/exist"), mock.clone());
+        let mut manager = DirManager::from_seq_dir(seq_dir);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let record = Arc::clone(&seen);
+        manager.on_transition(move |previous, new| {
+            let kind = |s: &SeqDirState| std::mem::discriminant(s);
+            record.lock().unwrap().push((kind(previous), kind(new)));
+        });
+        let sequencing = std::mem::discriminant(manager.state());
+        manager.poll();
+        mock.set_available(false);
+        manager.poll();
+        mock.set_available(true);
+        assert!(seen.lock().unwrap().is_empty());
+
+        mock.add_file(SEQUENCE_COMPLETE_TXT);
+        let transferring = std::mem::discriminant(manager.poll());
+        manager.poll();
+        mock.add_file(COPY_COMPLETE_TXT);
+        let complete = std::mem::discriminant(manager.poll());
+        manager.poll();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [(sequencing, transferring), (transferring, complete)]
+        );
+    }
+
+    #[test]
+    fn records_history() {
+        use crate::{COPY_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT};
+
+        let root = TestDir::new("history");
+        let mut manager = DirManager::with_history(&root).unwrap();
+        manager.poll();
+        std::fs::write(root.join(SEQUENCE_COMPLETE_TXT), "").unwrap();
+        manager.poll();
+        manager.poll();
+        std::fs::write(root.join(COPY_COMPLETE_TXT), "").unwrap();
+        manager.poll();
+
+        let kinds: Vec<_> = manager.history().iter().map(|(_, kind)| *kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                SeqDirStateKind::Sequencing,
+                SeqDirStateKind::Transferring,
+                SeqDirStateKind::Complete
+            ]
+        );
+        assert_eq!(manager.history()[2].0, *manager.since());
+        assert!(DirManager::new("test_data/seq_complete")
+            .unwrap()
+            .history()
+            .is_empty());
+    }
+
+    #[test]
+    fn unavailable_since() {
+        use std::sync::Arc;
+
+        use chrono::{Duration, TimeZone, Utc};
+
+        use crate::mock::{MockBuilder, MockClock};
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let mock = MockBuilder::new();
+        let seq_dir = SeqDir::mock(PathBuf::from("/does/not/exist"), mock.clone());
+        let mut manager = DirManager::from_seq_dir_with_clock(seq_dir, Arc::new(clock.clone()));
+        assert_eq!(manager.state().unavailable_since(), None);
+
+        clock.advance(Duration::minutes(5));
+        mock.set_available(false);
+        assert_eq!(
+            manager.poll().unavailable_since(),
+            Some(start + Duration::minutes(5))
+        );
+        clock.advance(Duration::minutes(5));
+        assert_eq!(
+            manager.poll().unavailable_since(),
+            Some(start + Duration::minutes(5))
+        );
+        mock.set_available(true);
+        assert_eq!(manager.poll().unavailable_since(), None);
+        assert_eq!(*manager.since(), start);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        for path in [COMPLETE, TRANSFERRING] {
+            let state = DirManager::new(path).unwrap().state().clone();
+            let json = serde_json::to_string(&state).unwrap();
+            let restored: SeqDirState = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored, state);
+
+            let mut manager = DirManager::from_state(restored);
+            assert_eq!(manager.since(), state.since());
+            assert_eq!(manager.poll(), &state);
+        }
+    }
+
+    #[test]
+    fn elapsed() {
+        use chrono::{Duration, TimeZone, Utc};
+
+        use crate::mock::MockClock;
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let manager = DirManager::with_clock(TRANSFERRING, clock.clone()).unwrap();
+        assert_eq!(manager.elapsed(), Duration::zero());
+        clock.advance(Duration::hours(2));
+        assert_eq!(manager.elapsed(), Duration::hours(2));
+        assert!(manager.state().elapsed() > Duration::hours(2));
+
+        let manager = DirManager::new(TRANSFERRING).unwrap();
+        assert!(manager.elapsed() < Duration::minutes(1));
+        clock.set(Utc::now() + Duration::days(1));
+        let future = DirManager::with_clock(TRANSFERRING, clock).unwrap();
+        assert_eq!(future.state().elapsed(), Duration::zero());
+    }
+
+    #[test]
+    fn event_iter_terminal() {
+        let events: Vec<SeqDirState> = DirManager::new(COMPLETE)
+            .unwrap()
+            .into_event_iter(Duration::from_millis(1))
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].is_terminal());
+    }
+
+    #[test]
+    fn event_iter_yields_transitions() {
+        use crate::mock::MockBuilder;
+        use crate::{SeqDir, COPY_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT};
+
+        let mock = MockBuilder::new();
+        let seq_dir = SeqDir::mock(PathBuf::from("/does/not/exist"), mock.clone());
+        let events = DirManager::from_seq_dir(seq_dir).into_event_iter(Duration::from_millis(1));
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            mock.add_file(SEQUENCE_COMPLETE_TXT);
+            std::thread::sleep(Duration::from_millis(20));
+            mock.add_file(COPY_COMPLETE_TXT);
+        });
+        let events: Vec<SeqDirState> = events.collect();
+        writer.join().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], SeqDirState::Transferring(..)));
+        assert!(matches!(events[1], SeqDirState::Complete(..)));
+    }
+
+    #[test]
+    fn spawn_watcher() {
+        use crate::mock::MockBuilder;
+        use crate::{SeqDir, COPY_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT};
+
+        let mock = MockBuilder::new();
+        let seq_dir = SeqDir::mock(PathBuf::from("/does/not/exist"), mock.clone());
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        let watcher =
+            DirManager::from_seq_dir(seq_dir).spawn_watcher_bounded(tx, Duration::from_millis(1));
+        mock.add_file(SEQUENCE_COMPLETE_TXT);
+        assert!(matches!(rx.recv().unwrap(), SeqDirState::Transferring(..)));
+        mock.add_file(COPY_COMPLETE_TXT);
+        assert!(matches!(rx.recv().unwrap(), SeqDirState::Complete(..)));
+        watcher.join().unwrap();
+        assert!(rx.recv().is_err());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        DirManager::new(COMPLETE)
+            .unwrap()
+            .spawn_watcher(tx, Duration::from_millis(1))
+            .join()
+            .unwrap();
+        assert_eq!(rx.iter().count(), 1);
+    }
+
+    #[test]
+    fn transfer_phase() {
+        use super::TransferPhase;
+        use crate::mock::MockBuilder;
+        use crate::{SeqDir, RTA_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT};
+
+        let mock = MockBuilder::new().file(SEQUENCE_COMPLETE_TXT);
+        let seq_dir = SeqDir::mock(PathBuf::from("/does/not/exist"), mock.clone());
+        let mut manager = DirManager::from_seq_dir(seq_dir);
+        assert_eq!(
+            manager.state().transfer_phase(),
+            Some(TransferPhase::Basecalling)
+        );
+        mock.add_file(RTA_COMPLETE_TXT);
+        assert_eq!(
+            manager.poll().transfer_phase(),
+            Some(TransferPhase::Copying)
+        );
+        assert_eq!(
+            DirManager::new(COMPLETE).unwrap().state().transfer_phase(),
+            None
+        );
+    }
+
+    #[test]
+    fn sort_by_recency() {
+        use std::cmp::Ordering;
+
+        // Complete takes `since` from CopyComplete.txt, which is older than any fresh state
+        let complete = DirManager::new(COMPLETE).unwrap();
+        let transferring = DirManager::new(TRANSFERRING).unwrap();
+        assert_eq!(
+            complete.state().cmp_by_since(transferring.state()),
+            Ordering::Less
+        );
+        assert_eq!(
+            complete.state().cmp_by_since(complete.state()),
+            Ordering::Equal
+        );
+        let mut managers = vec![complete.clone(), transferring.clone()];
+        DirManager::sort_by_recency(&mut managers);
+        assert_eq!(managers[0].state(), transferring.state());
+        assert_eq!(managers[1].state(), complete.state());
+    }
+
+    #[test]
+    fn with_clock() {
+        use chrono::{Duration, TimeZone, Utc};
+
+        use super::Availability;
+        use crate::mock::MockClock;
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let mut manager = DirManager::with_clock(TRANSFERRING, clock.clone()).unwrap();
+        assert_eq!(*manager.since(), start);
+        assert_eq!(
+            manager.state().availablity(),
+            &Availability::Available(start)
+        );
+        clock.advance(Duration::minutes(5));
+        manager
+            .inner_mut()
+            .set_root(PathBuf::from_str("/does/not/exist").unwrap());
+        manager.poll();
+        assert_eq!(
+            manager.state().availablity(),
+            &Availability::Unavailable(start + Duration::minutes(5))
+        );
+        assert_eq!(*manager.since(), start);
+        assert_eq!(manager.with_age().age_seconds(), 300);
+        clock.advance(Duration::minutes(5));
+        manager
+            .inner_mut()
+            .set_root(PathBuf::from_str(TRANSFERRING).unwrap());
+        assert!(manager.check_available());
+        assert_eq!(
+            manager.state().availablity(),
+            &Availability::Available(start + Duration::minutes(10))
+        );
+
+        let manager = DirManager::builder(TRANSFERRING)
+            .clock(MockClock::new(start))
+            .build()
+            .unwrap();
+        assert_eq!(*manager.since(), start);
+    }
+
+    #[test]
+    fn state_fingerprint() {
+        // Complete dirs take `since` from CopyComplete.txt, so a fresh manager matches
+        let first = DirManager::new(COMPLETE).unwrap();
+        let second = DirManager::new(COMPLETE).unwrap();
+        assert_eq!(first.state_fingerprint(), second.state_fingerprint());
+        assert_eq!(first.state_fingerprint(), first.clone().state_fingerprint());
+
+        let mut manager = DirManager::new(FAILED).unwrap();
+        let before = manager.state_fingerprint();
+        assert_ne!(before, first.state_fingerprint());
+        manager
+            .inner_mut()
+            .set_root(PathBuf::from_str("/does/not/exist").unwrap());
+        manager.poll();
+        assert_ne!(manager.state_fingerprint(), before);
+    }
+
+    #[test]
+    fn lane_availability() {
+        let manager = DirManager::new(COMPLETE).unwrap();
+        assert_eq!(
+            manager.state().lane_availability(),
+            vec![(1, true), (2, true), (3, true), (4, true)]
+        );
+        let manager = DirManager::new(FAILED).unwrap();
+        assert!(manager.state().lane_availability().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn evicted_lane_is_unavailable() {
+        let root = TestDir::new("evicted_lane");
+        let basecalls = root.join("Data/Intensities/BaseCalls");
+        std::fs::create_dir_all(basecalls.join("L001")).unwrap();
+        let _ = std::os::unix::fs::symlink(root.join("evicted"), basecalls.join("L002"));
+        let lanes = DirManager::new(&root).unwrap().state().lane_availability();
+        assert_eq!(lanes, vec![(1, true), (2, false)]);
+    }
+
+    #[test]
+    fn failed_from_run_params() {
+        let config = SeqDirConfig {
+            completion_from_run_params: true,
+            ..Default::default()
+        };
+        let manager = DirManager::with_config(RUNPARAMS_STATUS, config).unwrap();
+        assert!(matches!(manager.state(), SeqDirState::Failed(..)));
+        let manager = DirManager::new(RUNPARAMS_STATUS).unwrap();
+        assert!(matches!(manager.state(), SeqDirState::Sequencing(..)));
+    }
+
+    #[test]
+    fn untrusted_failed_is_not_failed() {
+        let config = SeqDirConfig {
+            trust_completion_status: false,
+            ..Default::default()
+        };
+        // FAILED has no SequenceComplete.txt, so without its completion status it looks active
+        let manager = DirManager::with_config(FAILED, config).unwrap();
+        match manager.state() {
+            SeqDirState::Sequencing(..) => {}
+            x => panic!("expected SeqDirState::Sequencing, got {x:?}"),
+        };
+    }
+
+    #[test]
+    fn availability_probes() {
+        let probe = AvailabilityProbe::RequiresRunInfo;
+        assert!(probe.probe(COMPLETE));
+        assert!(!probe.probe("test_data/"));
+        assert!(AvailabilityProbe::Readable.probe("test_data/"));
+        assert!(!AvailabilityProbe::Readable.probe("test_data/nope"));
+        assert!(AvailabilityProbe::FullAccess.probe("test_data/seq_complete"));
+        assert!(!AvailabilityProbe::FullAccess.probe("test_data/nope"));
+
+        let manager = DirManager::builder(COMPLETE)
+            .availability_probe(probe)
+            .build()
+            .unwrap();
+        assert!(manager.state().available());
+        let manager = DirManager::builder("test_data/")
+            .availability_probe(probe)
+            .build()
+            .unwrap();
+        assert!(!manager.state().available());
+        assert!(!manager.inner().is_available());
+    }
+
+    #[test]
+    fn access_levels() {
+        assert_eq!(AccessLevel::of(COMPLETE), AccessLevel::Full);
+        assert_eq!(AccessLevel::of("test_data/nope"), AccessLevel::Denied);
+        assert_eq!(
+            SeqDir::from_path(COMPLETE).unwrap().access_level(),
+            AccessLevel::Full
+        );
+    }
+
     #[test]
     fn goes_to_unavailable() {
         // you cannot manage a directory that doesn't exist
@@ -518,13 +1820,17 @@ mod tests {
             SeqDirState::Complete(..) => {}
             x => panic!("expected SeqDirState::Available, got {x:?}"),
         };
-        manager.inner_mut().root = PathBuf::from_str("/dev/null").unwrap();
+        manager
+            .inner_mut()
+            .set_root(PathBuf::from_str("/dev/null").unwrap());
         manager.poll();
         match manager.seq_dir.dir().is_available() {
             false => {}
             true => panic!("expected false"),
         };
-        manager.inner_mut().root = PathBuf::from_str(COMPLETE).unwrap();
+        manager
+            .inner_mut()
+            .set_root(PathBuf::from_str(COMPLETE).unwrap());
         manager.poll();
         match manager.state() {
             SeqDirState::Complete(..) => {}
@@ -532,6 +1838,36 @@ mod tests {
         };
     }
 
+    #[test]
+    fn deleted_is_terminal() {
+        let mut manager = DirManager::new(COMPLETE).unwrap();
+        assert!(!manager.is_deleted());
+        manager
+            .inner_mut()
+            .set_root(PathBuf::from_str("/dev/null").unwrap());
+        manager.mark_deleted();
+        let deleted_at = *manager.deleted_at().unwrap();
+        manager.poll();
+        // the last state is kept, including its availability
+        assert!(manager.state().available());
+        assert!(matches!(manager.state(), SeqDirState::Complete(..)));
+        manager.mark_deleted();
+        assert_eq!(manager.deleted_at(), Some(&deleted_at));
+
+        // waiting for a terminal state stops at deletion
+        let mut manager = DirManager::new(TRANSFERRING).unwrap();
+        manager.mark_deleted();
+        let interval = Duration::from_millis(1);
+        assert!(matches!(
+            manager.poll_until_terminal(interval, None),
+            Ok(SeqDirState::Transferring(..))
+        ));
+        assert_eq!(manager.clone().into_event_iter(interval).count(), 0);
+        let (tx, rx) = std::sync::mpsc::channel();
+        manager.spawn_watcher(tx, interval).join().unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
     #[test]
     fn transferring_to_complete() {
         let copy_complete = PathBuf::from_str(TRANSFERRING)
@@ -551,6 +1887,80 @@ mod tests {
         };
     }
 
+    #[test]
+    fn poll_with_retry() {
+        let mut manager = DirManager::new(COMPLETE).unwrap();
+        manager
+            .inner_mut()
+            .set_root(PathBuf::from_str("/dev/null/nope").unwrap());
+        manager.poll_with_retry(3, Duration::from_secs(60));
+        assert!(!manager.state().available());
+        manager
+            .inner_mut()
+            .set_root(PathBuf::from_str(COMPLETE).unwrap());
+        match manager.poll_with_retry(3, Duration::from_millis(1)) {
+            SeqDirState::Complete(..) => {}
+            x => panic!("expected SeqDirState::Complete, got {x:?}"),
+        };
+        assert!(manager.state().available());
+    }
+
+    #[test]
+    fn retries_transient_probes() {
+        let eio = || std::io::Error::from_raw_os_error(5);
+        let mut calls = 0;
+        let result = retry_transient(3, Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 3 {
+                Err(eio())
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result = retry_transient(2, Duration::from_millis(1), || {
+            calls += 1;
+            Err(eio())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result = retry_transient(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(std::io::ErrorKind::NotFound.into())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn transient_errors() {
+        assert!(is_transient(&std::io::Error::from_raw_os_error(5)));
+        assert!(is_transient(&std::io::ErrorKind::TimedOut.into()));
+        assert!(!is_transient(&std::io::ErrorKind::NotFound.into()));
+        assert!(!is_transient(&std::io::ErrorKind::PermissionDenied.into()));
+    }
+
+    #[test]
+    fn mixed_path_sources() {
+        let sources: Vec<Box<dyn AsRef<std::path::Path>>> = vec![
+            Box::new(COMPLETE),
+            Box::new(PathBuf::from(FAILED)),
+            Box::new(String::from(TRANSFERRING)),
+        ];
+        let managers = sources
+            .iter()
+            .map(|p| DirManager::new_boxed((**p).as_ref()))
+            .collect::<Result<Vec<DirManager>, _>>()
+            .unwrap();
+        assert_eq!(managers.len(), 3);
+        assert_eq!(managers[1].inner().root(), PathBuf::from(FAILED));
+    }
+
     #[test]
     fn test_serialize_to_json() {
         use serde_json;
@@ -0,0 +1,73 @@
+//! Load the core metadata files of a sequencing directory together
+//!
+//! A [RunMetadata] bundles the parsed RunInfo.xml, RunParameters.xml and RunCompletionStatus.xml
+//! of a run. Loading it never fails: files that are missing or cannot be parsed are left as None,
+//! see [validate_metadata](crate::SeqDir::validate_metadata()) to find out why.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CompletionStatus, RunInfo, RunParameters, SeqDir};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+/// The parsed metadata files of a sequencing directory
+pub struct RunMetadata {
+    pub run_info: Option<RunInfo>,
+    pub run_params: Option<RunParameters>,
+    /// See [get_completion_status](crate::SeqDir::get_completion_status())
+    pub completion_status: Option<CompletionStatus>,
+}
+
+impl RunMetadata {
+    /// Returns true if none of the metadata files could be loaded
+    pub fn is_empty(&self) -> bool {
+        self.run_info.is_none() && self.run_params.is_none() && self.completion_status.is_none()
+    }
+}
+
+impl From<&SeqDir> for RunMetadata {
+    fn from(seq_dir: &SeqDir) -> Self {
+        RunMetadata {
+            run_info: seq_dir.read_run_info().ok(),
+            run_params: seq_dir.read_run_params().ok(),
+            completion_status: seq_dir.get_completion_status().and_then(Result::ok),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunMetadata;
+    use crate::SeqDir;
+
+    const COMPLETE: &str = "test_data/seq_complete/";
+    const NOVASEQ_A: &str = "test_data/20240110_A00123_0042_AHABCDEFXY/";
+    const CORRUPT: &str = "test_data/seq_corrupt/";
+
+    #[test]
+    fn loads_all() {
+        let metadata = SeqDir::from_path(COMPLETE).unwrap().metadata();
+        assert_eq!(metadata.run_info.unwrap().run_id, "20231231_foo_ABCXYZ");
+        assert!(metadata.run_params.is_some());
+        assert!(metadata.completion_status.is_some());
+    }
+
+    #[test]
+    fn partial() {
+        let metadata = SeqDir::from_path(NOVASEQ_A).unwrap().metadata();
+        assert!(metadata.run_info.is_some());
+        assert!(metadata.run_params.is_none());
+        assert!(metadata.completion_status.is_none());
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(
+            serde_json::from_str::<RunMetadata>(&json).unwrap(),
+            metadata
+        );
+    }
+
+    #[test]
+    fn malformed_is_none() {
+        let seq_dir = SeqDir::from_path(CORRUPT).unwrap();
+        assert!(seq_dir.read_run_info().is_err());
+        assert!(seq_dir.metadata().run_info.is_none());
+    }
+}
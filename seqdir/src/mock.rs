@@ -0,0 +1,201 @@
+//! In-memory sequencing directories for testing
+//!
+//! Enabled by the `test-util` feature. A mocked [SeqDir] does not consult the filesystem for its
+//! sentinel files, availability or RunCompletionStatus.xml. Instead, it reads them from a
+//! [MockBuilder]. Clones of a MockBuilder share their state, so a test can keep a handle and
+//! update it between polls to drive a [DirManager](crate::DirManager) through its states.
+//!
+//! Everything else (lanes, RunInfo.xml, etc.) still reads from `root`.
+//!
+//! A [MockClock] can likewise stand in for the system clock of a DirManager.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use chrono::{DateTime, Utc};
+
+use crate::manager::Clock;
+use crate::{CompletionStatus, SeqDir};
+
+#[derive(Debug)]
+struct MockState {
+    files: HashSet<String>,
+    completion_status: Option<CompletionStatus>,
+    available: bool,
+}
+
+#[derive(Clone, Debug)]
+/// The mocked state of a [SeqDir]
+///
+/// By default, no files exist, there is no completion status, and the directory is available.
+pub struct MockBuilder {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Default for MockBuilder {
+    fn default() -> Self {
+        MockBuilder {
+            state: Arc::new(Mutex::new(MockState {
+                files: HashSet::new(),
+                completion_status: None,
+                available: true,
+            })),
+        }
+    }
+}
+
+/// MockBuilders are equal if they share the same state
+impl PartialEq for MockBuilder {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl MockBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare that `name` exists, relative to the root of the sequencing directory
+    pub fn file<S: Into<String>>(self, name: S) -> Self {
+        self.add_file(name);
+        self
+    }
+
+    /// Use `status` as the parsed contents of RunCompletionStatus.xml
+    pub fn completion_status(self, status: CompletionStatus) -> Self {
+        self.set_completion_status(Some(status));
+        self
+    }
+
+    /// Set whether the directory is available
+    pub fn available(self, available: bool) -> Self {
+        self.set_available(available);
+        self
+    }
+
+    /// Declare that `name` exists, updating every SeqDir using this mock
+    pub fn add_file<S: Into<String>>(&self, name: S) {
+        self.lock().files.insert(name.into());
+    }
+
+    /// Declare that `name` no longer exists, updating every SeqDir using this mock
+    pub fn remove_file(&self, name: &str) {
+        self.lock().files.remove(name);
+    }
+
+    /// Replace the completion status, updating every SeqDir using this mock
+    pub fn set_completion_status(&self, status: Option<CompletionStatus>) {
+        self.lock().completion_status = status;
+    }
+
+    /// Replace the availability, updating every SeqDir using this mock
+    pub fn set_available(&self, available: bool) {
+        self.lock().available = available;
+    }
+
+    pub(crate) fn has_file(&self, name: &str) -> bool {
+        self.lock().files.contains(name)
+    }
+
+    pub(crate) fn get_completion_status(&self) -> Option<CompletionStatus> {
+        self.lock().completion_status.clone()
+    }
+
+    pub(crate) fn is_available(&self) -> bool {
+        self.lock().available
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A [Clock] that only moves when told to.
+///
+/// Clones share the same time, so a test can keep a handle after passing a MockClock to
+/// [with_clock](crate::DirManager::with_clock()).
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    /// Create a clock stopped at `now`
+    pub fn new(now: DateTime<Utc>) -> Self {
+        MockClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Set the current time
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = now;
+    }
+
+    /// Move the current time forward by `duration`
+    pub fn advance(&self, duration: chrono::Duration) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        *now += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl SeqDir {
+    /// Create a SeqDir whose sentinel files, availability and completion status come from `mock`.
+    ///
+    /// Unlike [from_path](SeqDir::from_path()), `root` does not need to exist.
+    pub fn mock(root: PathBuf, mock: MockBuilder) -> Self {
+        SeqDir {
+            mock: Some(mock),
+            ..Self::from_dir_unchecked(root)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::MockBuilder;
+    use crate::{
+        CompletionStatus, DirManager, Message, SeqDir, SeqDirState, COPY_COMPLETE_TXT,
+        SEQUENCE_COMPLETE_TXT,
+    };
+
+    #[test]
+    fn mocked_probes() {
+        let mock = MockBuilder::new()
+            .file(COPY_COMPLETE_TXT)
+            .completion_status(CompletionStatus::CompletedAsPlanned(Message {
+                run_id: String::from("mock"),
+                message: None,
+            }));
+        let seq_dir = SeqDir::mock(PathBuf::from("/does/not/exist"), mock.clone());
+        assert!(seq_dir.is_available());
+        assert!(seq_dir.is_copy_complete());
+        assert!(!seq_dir.is_sequence_complete());
+        assert!(!seq_dir.is_failed().unwrap());
+        mock.set_available(false);
+        assert!(seq_dir.is_unavailable());
+    }
+
+    #[test]
+    fn drives_state_machine() {
+        let mock = MockBuilder::new();
+        let seq_dir = SeqDir::mock(PathBuf::from("/does/not/exist"), mock.clone());
+        let mut manager = DirManager::from_seq_dir(seq_dir);
+        assert!(matches!(manager.state(), SeqDirState::Sequencing(..)));
+        mock.add_file(SEQUENCE_COMPLETE_TXT);
+        assert!(matches!(manager.poll(), SeqDirState::Transferring(..)));
+        mock.add_file(COPY_COMPLETE_TXT);
+        assert!(matches!(manager.poll(), SeqDirState::Complete(..)));
+        assert!(manager.state().available());
+    }
+}
@@ -0,0 +1,200 @@
+//! Monitor many sequencing directories at once
+//!
+//! [MultiDirManager] is a thin collection of [DirManagers](DirManager) that are all polled
+//! together. It is serializable as a sequence of the managed [SeqDirStates](SeqDirState) so that
+//! the state of every run can be emitted as a single event.
+//!
+//! With the `json` feature, the same sequence, written as JSON by `MultiDirManager::save`, can be
+//! read back by `MultiDirManager::load` so that a long-running service can resume monitoring
+//! after a restart without losing the `since` timestamps of its runs.
+
+#[cfg(feature = "json")]
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde::ser::SerializeSeq;
+use serde::{Serialize, Serializer};
+
+use crate::{DirManager, SeqDirError, SeqDirState};
+
+#[derive(Clone, Default)]
+/// A collection of [DirManagers](DirManager) that are polled together.
+pub struct MultiDirManager {
+    managers: Vec<DirManager>,
+}
+
+impl MultiDirManager {
+    /// Construct an empty MultiDirManager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start managing the sequencing directory at `path`.
+    ///
+    /// Errors if a [DirManager] cannot be constructed for `path`.
+    pub fn add<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SeqDirError> {
+        self.managers.push(DirManager::new(path)?);
+        Ok(())
+    }
+
+    /// Start managing an already-constructed [DirManager]
+    pub fn push(&mut self, manager: DirManager) {
+        self.managers.push(manager);
+    }
+
+    /// Write the states of all managed directories as a JSON sequence.
+    ///
+    /// Only the states are written: custom [Clocks](crate::Clock) and
+    /// [SeqDirConfigs](crate::SeqDirConfig) are not, nor is whether a directory was
+    /// [marked deleted](DirManager::mark_deleted()).
+    #[cfg(feature = "json")]
+    pub fn save<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        serde_json::to_writer(writer, self).map_err(std::io::Error::from)
+    }
+
+    /// Read states written by [save](MultiDirManager::save()) and resume managing them.
+    ///
+    /// Every directory is polled once before returning, so states reflect anything that
+    /// happened in the meantime. A directory that no longer exists keeps its saved state but
+    /// becomes [unavailable](SeqDirState::available()).
+    #[cfg(feature = "json")]
+    pub fn load<R: Read>(reader: R) -> Result<Self, SeqDirError> {
+        let states: Vec<SeqDirState> =
+            serde_json::from_reader(reader).map_err(std::io::Error::from)?;
+        let mut multi = MultiDirManager {
+            managers: states.into_iter().map(DirManager::from_state).collect(),
+        };
+        multi.poll();
+        Ok(multi)
+    }
+
+    /// Poll every managed directory, possibly updating their states.
+    pub fn poll(&mut self) {
+        self.managers.iter_mut().for_each(|m| {
+            m.poll();
+        });
+    }
+
+    /// Returns the number of managed directories
+    pub fn len(&self) -> usize {
+        self.managers.len()
+    }
+
+    /// Returns true if no directories are managed
+    pub fn is_empty(&self) -> bool {
+        self.managers.is_empty()
+    }
+
+    /// Returns an iterator over the managed [DirManagers](DirManager)
+    pub fn iter(&self) -> std::slice::Iter<'_, DirManager> {
+        self.managers.iter()
+    }
+
+    /// Returns an iterator over the current state of every managed directory
+    pub fn states(&self) -> impl Iterator<Item = &SeqDirState> {
+        self.managers.iter().map(DirManager::state)
+    }
+
+    /// Returns the states of all directories that are currently available.
+    ///
+    /// Uses the cached [Availability](crate::manager::Availability); directories are not
+    /// re-probed.
+    pub fn available_states(&self) -> Vec<&SeqDirState> {
+        self.states().filter(|s| s.available()).collect()
+    }
+
+    /// Serialize the states of all currently available directories as a sequence.
+    ///
+    /// Like [available_states](MultiDirManager::available_states()), this does not re-probe
+    /// availability. Can be used with `#[serde(serialize_with = "...")]`.
+    pub fn serialize_available<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let available = self.available_states();
+        let mut seq = serializer.serialize_seq(Some(available.len()))?;
+        for state in available {
+            seq.serialize_element(state)?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for MultiDirManager {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for state in self.states() {
+            seq.serialize_element(state)?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultiDirManager;
+    #[cfg(feature = "json")]
+    use crate::test_dir::TestDir;
+    #[cfg(feature = "json")]
+    use crate::SeqDirState;
+
+    const COMPLETE: &str = "test_data/seq_complete/";
+    const FAILED: &str = "test_data/seq_failed/";
+
+    #[test]
+    fn serialize_available_only() {
+        let mut multi = MultiDirManager::new();
+        multi.add(COMPLETE).unwrap();
+        multi.add(FAILED).unwrap();
+        assert_eq!(multi.len(), 2);
+        assert_eq!(multi.available_states().len(), 2);
+
+        let mut unavailable = multi.managers.pop().unwrap();
+        unavailable
+            .state_mut()
+            .dir_mut()
+            .set_root("/dev/null/nope".into());
+        unavailable.state_mut().check_available();
+        multi.push(unavailable);
+        assert_eq!(multi.available_states().len(), 1);
+
+        let mut json = Vec::new();
+        multi
+            .serialize_available(&mut serde_json::Serializer::new(&mut json))
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 1);
+        assert_eq!(value[0]["state"], "Complete");
+
+        let all = serde_json::to_value(&multi).unwrap();
+        assert_eq!(all.as_array().unwrap().len(), 2);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn save_and_load() {
+        let dir = TestDir::new("multi_save");
+        let mut multi = MultiDirManager::new();
+        multi.add(COMPLETE).unwrap();
+        multi.add(FAILED).unwrap();
+        multi.add(&dir).unwrap();
+        let since: Vec<_> = multi.states().map(SeqDirState::since).collect();
+
+        let mut saved = Vec::new();
+        multi.save(&mut saved).unwrap();
+        // the directory disappears while the states are saved
+        let root = dir.to_path_buf();
+        drop(dir);
+        let loaded = MultiDirManager::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(
+            loaded.states().map(SeqDirState::since).collect::<Vec<_>>(),
+            since
+        );
+        let states: Vec<_> = loaded.states().collect();
+        assert!(matches!(states[0], SeqDirState::Complete(..)));
+        assert!(matches!(states[1], SeqDirState::Failed(..)));
+        assert!(states[..2].iter().all(|s| s.available()));
+        assert_eq!(states[2].dir().root(), root);
+        assert!(!states[2].available());
+
+        assert!(MultiDirManager::load("[{}]".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! Read the cycle plan from recipe and configuration files
+//!
+//! HiSeq instruments execute a chemistry recipe, written to `Recipe/<flowcell>.xml`, and record
+//! their effective settings in `Config/Effective.cfg`. If a run was customized, these are the
+//! definitive cycle plan and RunInfo.xml may disagree with them.
+//!
+//! The cycle count of a recipe is the number of `Incorporation` steps in its `Protocol`, one per
+//! cycle. The cycle count of Effective.cfg is taken from a `NumCycles` or `TotalCycles` key,
+//! written as `key = value` or `key value`.
+
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+use crate::SeqDirError;
+
+pub const RECIPE_DIR: &str = "Recipe";
+pub const CONFIG_DIR: &str = "Config";
+pub const EFFECTIVE_CFG: &str = "Effective.cfg";
+
+const INCORPORATION: &str = "Incorporation";
+const PROTOCOL: &str = "Protocol";
+const CYCLE_KEYS: [&str; 2] = ["NumCycles", "TotalCycles"];
+
+/// Count the cycles planned by a recipe XML file.
+///
+/// Returns None if the recipe has no `Protocol` or no `Incorporation` steps.
+pub fn parse_recipe_cycles<P: AsRef<Path>>(path: P) -> Result<Option<u16>, SeqDirError> {
+    let raw_contents = read_to_string(&path)?;
+    let doc = roxmltree::Document::parse(&raw_contents).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Could not parse as XML: {e}"),
+        )
+    })?;
+    let Some(protocol) = doc.descendants().find(|elem| elem.has_tag_name(PROTOCOL)) else {
+        return Ok(None);
+    };
+    let cycles = protocol
+        .descendants()
+        .filter(|elem| elem.has_tag_name(INCORPORATION))
+        .count();
+    Ok((cycles > 0).then(|| u16::try_from(cycles).unwrap_or(u16::MAX)))
+}
+
+/// Read the planned cycle count from an Effective.cfg file.
+///
+/// Returns None if no cycle count key is present.
+pub fn parse_effective_cfg_cycles<P: AsRef<Path>>(path: P) -> Result<Option<u16>, SeqDirError> {
+    for line in read_to_string(&path)?.lines() {
+        let line = line.trim();
+        let Some((key, value)) = line
+            .split_once('=')
+            .or_else(|| line.split_once(char::is_whitespace))
+        else {
+            continue;
+        };
+        if CYCLE_KEYS
+            .iter()
+            .any(|k| key.trim().eq_ignore_ascii_case(k))
+        {
+            return Ok(Some(value.trim().parse::<u16>()?));
+        }
+    }
+    Ok(None)
+}
+
+/// Find the planned cycle count in the recipe or configuration files of a sequencing directory.
+///
+/// Recipe XML files are consulted first, in order of file name, then Config/Effective.cfg.
+/// Returns None if neither is present or specifies a cycle count.
+pub fn find_recipe_cycles<P: AsRef<Path>>(dir: P) -> Result<Option<u16>, SeqDirError> {
+    for recipe in recipe_files(dir.as_ref()) {
+        if let Some(cycles) = parse_recipe_cycles(recipe)? {
+            return Ok(Some(cycles));
+        }
+    }
+    let cfg = dir.as_ref().join(CONFIG_DIR).join(EFFECTIVE_CFG);
+    if cfg.is_file() {
+        return parse_effective_cfg_cycles(cfg);
+    }
+    Ok(None)
+}
+
+fn recipe_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(dir.join(RECIPE_DIR)) else {
+        return Vec::new();
+    };
+    let mut recipes: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+        })
+        .collect();
+    recipes.sort_unstable();
+    recipes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_recipe_cycles, parse_effective_cfg_cycles, parse_recipe_cycles};
+
+    const HISEQ: &str = "test_data/seq_hiseq";
+
+    #[test]
+    fn parses_recipe_and_cfg() {
+        assert_eq!(
+            parse_recipe_cycles("test_data/seq_hiseq/Recipe/H2KLMNBCX2.xml").unwrap(),
+            Some(6)
+        );
+        assert_eq!(
+            parse_effective_cfg_cycles("test_data/seq_hiseq/Config/Effective.cfg").unwrap(),
+            Some(6)
+        );
+        assert_eq!(
+            parse_effective_cfg_cycles("test_data/seq_complete/SampleSheet.csv").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_recipe_cycles() {
+        assert_eq!(find_recipe_cycles(HISEQ).unwrap(), Some(6));
+        assert_eq!(find_recipe_cycles("test_data/seq_complete").unwrap(), None);
+    }
+}
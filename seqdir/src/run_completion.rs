@@ -3,7 +3,8 @@
 //! This module enables parsing on RunCompletionStatus.xml into [CompletionStatus] structs.
 //! Each CompletionStatus variant wraps a [Message] that contains at minimum a the associated
 //! sequencing run id, and may also include an optional message, which is parsed from the
-//! ErrorDescription tag.
+//! ErrorDescription tag. Tag contents are trimmed, and an ErrorDescription of `None`, `N/A` or
+//! nothing at all is treated as no message.
 //!
 //! CompletionStatus and Message are both readily serializable so they can be treated as
 //! emitted events by higher-level implementations.
@@ -13,13 +14,13 @@ use std::path::Path;
 use std::{fs::File, io::Read};
 
 use roxmltree;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 const RUN_ID: &str = "RunId";
 const COMPLETION_STATUS: &str = "CompletionStatus";
 const ERROR_DESCRIPTION: &str = "ErrorDescription";
 
-#[derive(Clone, Debug, Serialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 /// A RunCompletionStatus message.
 ///
 /// Consists of a run_id and optional message content.
@@ -40,35 +41,117 @@ impl Display for Message {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Serialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "completion_status")]
 /// The completion status of a run as extracted from RunCompletionStatus.xml
 pub enum CompletionStatus {
     CompletedAsPlanned(Message),
     ExceptionEndedEarly(Message),
     UserEndedEarly(Message),
+    /// The run is still in progress
+    Running(Message),
     Other(Message),
 }
 
+impl CompletionStatus {
+    /// Construct the variant named by `status`, wrapping `message`.
+    ///
+    /// Unrecognized statuses are mapped to `Other`.
+    pub(crate) fn from_parts(status: &str, message: Message) -> Self {
+        match status {
+            "CompletedAsPlanned" => CompletionStatus::CompletedAsPlanned(message),
+            "ExceptionEndedEarly" => CompletionStatus::ExceptionEndedEarly(message),
+            "UserEndedEarly" => CompletionStatus::UserEndedEarly(message),
+            "Running" => CompletionStatus::Running(message),
+            _ => CompletionStatus::Other(message),
+        }
+    }
+
+    /// Returns a reference to the wrapped [Message]
+    pub fn message(&self) -> &Message {
+        match self {
+            Self::CompletedAsPlanned(m)
+            | Self::ExceptionEndedEarly(m)
+            | Self::UserEndedEarly(m)
+            | Self::Running(m)
+            | Self::Other(m) => m,
+        }
+    }
+
+    /// Returns true unless the run completed as planned or is still running
+    pub fn is_failed(&self) -> bool {
+        !matches!(self, Self::CompletedAsPlanned(..) | Self::Running(..))
+    }
+
+    /// Render a minimal RunCompletionStatus.xml that [parse_run_completion] reads back as `self`.
+    ///
+    /// ErrorDescription is only written if there is a message. Text is escaped, but it is still
+    /// trimmed when parsed, and a message of `None` or `N/A` parses as no message, so such
+    /// statuses do not round-trip exactly.
+    pub fn to_xml(&self) -> String {
+        let message = self.message();
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<RunCompletionStatus>\n  \
+             <{COMPLETION_STATUS}>{}</{COMPLETION_STATUS}>\n  <{RUN_ID}>{}</{RUN_ID}>\n",
+            self.name(),
+            escape_xml(&message.run_id),
+        );
+        if let Some(text) = &message.message {
+            xml.push_str(&format!(
+                "  <{ERROR_DESCRIPTION}>{}</{ERROR_DESCRIPTION}>\n",
+                escape_xml(text)
+            ));
+        }
+        xml.push_str("</RunCompletionStatus>\n");
+        xml
+    }
+
+    /// The name of the variant, as written in RunCompletionStatus.xml
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ExceptionEndedEarly(..) => "ExceptionEndedEarly",
+            Self::UserEndedEarly(..) => "UserEndedEarly",
+            Self::CompletedAsPlanned(..) => "CompletedAsPlanned",
+            Self::Running(..) => "Running",
+            Self::Other(..) => "Other",
+        }
+    }
+}
+
 impl Display for CompletionStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let (var_str, message) = match self {
-            Self::ExceptionEndedEarly(m) => ("ExceptionEndedEarly", m),
-            Self::UserEndedEarly(m) => ("UserEndedEarly", m),
-            Self::CompletedAsPlanned(m) => ("CompletedAsPlanned", m),
-            Self::Other(m) => ("Other", m),
-        };
-        write!(f, "{} : {}", var_str, message)
+        write!(f, "{} : {}", self.name(), self.message())
     }
 }
 
+/// Escape the characters that may not appear literally in XML text
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// Attempts to parse a file in the format of RunCompletionStatus.xml
 ///
 /// Returns a [CompletionStatus] wrapping the associated [Message]
 pub fn parse_run_completion<P: AsRef<Path>>(path: P) -> Result<CompletionStatus, std::io::Error> {
-    let mut handle = File::open(&path)?;
+    parse_run_completion_reader(File::open(&path)?)
+}
+
+/// Like [parse_run_completion], but reads from any source, e.g. an open file or XML already in
+/// memory as a byte slice.
+pub fn parse_run_completion_reader<R: Read>(
+    mut reader: R,
+) -> Result<CompletionStatus, std::io::Error> {
     let mut raw_contents = String::new();
-    handle.read_to_string(&mut raw_contents)?;
+    reader.read_to_string(&mut raw_contents)?;
     let doc = roxmltree::Document::parse(&raw_contents).map_err(|e| {
         std::io::Error::new(
             std::io::ErrorKind::InvalidData,
@@ -76,35 +159,25 @@ pub fn parse_run_completion<P: AsRef<Path>>(path: P) -> Result<CompletionStatus,
         )
     })?;
 
-    let run_id = match doc.descendants().find(|elem| elem.has_tag_name(RUN_ID)) {
+    let run_id = match tag_text(&doc, RUN_ID) {
         None => {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 "missing RunId tag",
             ))
         }
-        Some(node) => match node.text() {
-            None => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "RunId tag is empty",
-                ))
-            }
-            Some(id) => id,
-        },
-    }
-    .to_string();
-
-    let message_txt = match doc
-        .descendants()
-        .find(|elem| elem.has_tag_name(ERROR_DESCRIPTION))
-    {
-        Some(node) => match node.text() {
-            None => None,
-            Some("None") => None,
-            Some(text) => Some(text.to_string()),
-        },
-        None => None,
+        Some(None) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "RunId tag is empty",
+            ))
+        }
+        Some(Some(id)) => id.to_string(),
+    };
+
+    let message_txt = match tag_text(&doc, ERROR_DESCRIPTION).flatten() {
+        None | Some("None") | Some("N/A") => None,
+        Some(text) => Some(text.to_string()),
     };
 
     let message = Message {
@@ -112,35 +185,41 @@ pub fn parse_run_completion<P: AsRef<Path>>(path: P) -> Result<CompletionStatus,
         message: message_txt,
     };
 
-    match doc
-        .descendants()
-        .find(|elem| elem.has_tag_name(COMPLETION_STATUS))
-    {
+    match tag_text(&doc, COMPLETION_STATUS) {
         None => Err(std::io::Error::new(
             std::io::ErrorKind::InvalidData,
             "missing CompletionStatus tag",
         )),
-        Some(node) => match node.text() {
-            Some("CompletedAsPlanned") => Ok(CompletionStatus::CompletedAsPlanned(message)),
-            Some("ExceptionEndedEarly") => Ok(CompletionStatus::ExceptionEndedEarly(message)),
-            Some("UserEndedEarly") => Ok(CompletionStatus::UserEndedEarly(message)),
-            Some(_) => Ok(CompletionStatus::Other(message)),
-            None => Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "CompletionStatus tag is empty",
-            )),
-        },
+        Some(Some(status)) => Ok(CompletionStatus::from_parts(status, message)),
+        Some(None) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "CompletionStatus tag is empty",
+        )),
     }
 }
 
+/// Find the first `tag` element and return its trimmed text.
+///
+/// Returns None if there is no such element, and Some(None) if it is empty or whitespace-only.
+fn tag_text<'a>(doc: &'a roxmltree::Document, tag: &str) -> Option<Option<&'a str>> {
+    doc.descendants()
+        .find(|elem| elem.has_tag_name(tag))
+        .map(|node| node.text().map(str::trim).filter(|text| !text.is_empty()))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_run_completion;
     use super::CompletionStatus;
+    use super::{parse_run_completion, parse_run_completion_reader};
 
     const COMPLETED_RCS: &str = "test_data/seq_complete/RunCompletionStatus.xml";
     const FAILED_RCS: &str = "test_data/seq_failed/RunCompletionStatus.xml";
     const GARBAGE_RCS: &str = "test_data/seq_corrupt/RunCompletionStatus.xml";
+    const WHITESPACE_RUN_ID_RCS: &str = "test_data/run_completion/whitespace_run_id.xml";
+    const WHITESPACE_STATUS_RCS: &str = "test_data/run_completion/whitespace_status.xml";
+    const NA_DESCRIPTION_RCS: &str = "test_data/run_completion/na_description.xml";
+    const EMPTY_DESCRIPTION_RCS: &str = "test_data/run_completion/empty_description.xml";
+    const RUNNING_RCS: &str = "test_data/run_completion/running.xml";
 
     #[test]
     fn parse_completed() {
@@ -168,10 +247,79 @@ mod tests {
         }
     }
 
-    // TODO fuzz
+    #[test]
+    fn parse_running() {
+        let completion_status = parse_run_completion(RUNNING_RCS).unwrap();
+        assert!(matches!(completion_status, CompletionStatus::Running(..)));
+        assert!(!completion_status.is_failed());
+        assert_eq!(
+            completion_status.to_string(),
+            "Running : 20231231_foo_ABCXYZ : None"
+        );
+        assert!(parse_run_completion(FAILED_RCS).unwrap().is_failed());
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        use super::Message;
+
+        let message = |text: Option<&str>| Message {
+            run_id: String::from("20231231_foo_ABCXYZ"),
+            message: text.map(String::from),
+        };
+        let statuses = [
+            parse_run_completion(COMPLETED_RCS).unwrap(),
+            parse_run_completion(FAILED_RCS).unwrap(),
+            CompletionStatus::UserEndedEarly(message(Some("stopped <early> & then some"))),
+            CompletionStatus::Running(message(None)),
+            CompletionStatus::Other(message(Some("?"))),
+        ];
+        for status in statuses {
+            let xml = status.to_xml();
+            assert_eq!(
+                parse_run_completion_reader(xml.as_bytes()).unwrap(),
+                status,
+                "{xml}"
+            );
+        }
+        assert!(!CompletionStatus::Running(message(None))
+            .to_xml()
+            .contains("ErrorDescription"));
+    }
+
+    #[test]
+    fn whitespace_tags() {
+        assert!(parse_run_completion(WHITESPACE_RUN_ID_RCS).is_err());
+        assert!(parse_run_completion(WHITESPACE_STATUS_RCS).is_err());
+        match parse_run_completion(NA_DESCRIPTION_RCS).unwrap() {
+            CompletionStatus::CompletedAsPlanned(message) => {
+                assert_eq!(message.message, None);
+                assert_eq!(message.run_id, "20231231_foo_ABCXYZ");
+            }
+            _ => panic!("expected CompletedAsPlanned variant"),
+        }
+        let status = parse_run_completion(EMPTY_DESCRIPTION_RCS).unwrap();
+        assert!(matches!(status, CompletionStatus::UserEndedEarly(..)));
+        assert_eq!(status.message().message, None);
+    }
+
     #[test]
     fn bad_message_does_not_panic() {
         assert!(parse_run_completion(GARBAGE_RCS).is_err());
+        let raw = std::fs::read(FAILED_RCS).unwrap();
+        for len in 0..raw.len() {
+            let _ = parse_run_completion_reader(&raw[..len]);
+        }
+    }
+
+    #[test]
+    fn parse_from_reader() {
+        let raw = std::fs::read(COMPLETED_RCS).unwrap();
+        assert_eq!(
+            parse_run_completion_reader(raw.as_slice()).unwrap(),
+            parse_run_completion(COMPLETED_RCS).unwrap()
+        );
+        assert!(parse_run_completion_reader(&b"<RunId>foo</RunId>"[..]).is_err());
     }
 
     #[test]
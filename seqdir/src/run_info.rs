@@ -0,0 +1,369 @@
+//! Parse RunInfo.xml
+//!
+//! This module enables parsing of RunInfo.xml into a [RunInfo] struct, which describes the
+//! planned structure of a run: its identifiers and the reads that make it up.
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::{fs::File, io::Read};
+
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+
+const RUN: &str = "Run";
+const FLOWCELL: &str = "Flowcell";
+const INSTRUMENT: &str = "Instrument";
+const READ: &str = "Read";
+const FLOWCELL_LAYOUT: &str = "FlowcellLayout";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// A single read as described by RunInfo.xml
+pub struct ReadInfo {
+    /// The `Number` attribute, which need not match the read's position in [RunInfo::reads]
+    pub number: u8,
+    pub num_cycles: u16,
+    pub is_indexed_read: bool,
+    /// Whether the read is sequenced in the reverse-complement workflow (`IsReverseComplement`)
+    pub is_reverse_complement: bool,
+    /// Every attribute of the Read element, verbatim
+    ///
+    /// Useful for platform-specific attributes that are not otherwise parsed.
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// The contents of RunInfo.xml
+pub struct RunInfo {
+    pub run_id: String,
+    pub run_number: Option<u32>,
+    pub flowcell: String,
+    pub instrument: String,
+    pub reads: Vec<ReadInfo>,
+    /// None if RunInfo.xml has no FlowcellLayout element, or if any of LaneCount, SurfaceCount,
+    /// SwathCount or TileCount is missing or invalid.
+    #[serde(default)]
+    pub layout: Option<FlowcellLayout>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// The geometry of a flowcell, from the FlowcellLayout element of RunInfo.xml
+pub struct FlowcellLayout {
+    pub lane_count: u8,
+    pub surface_count: u8,
+    pub swath_count: u8,
+    pub tile_count: u16,
+    /// Only present on some platforms, e.g. NextSeq 500/550
+    pub section_per_lane: Option<u8>,
+    /// Only present on some platforms, e.g. NextSeq 500/550
+    pub lane_per_section: Option<u8>,
+}
+
+impl FlowcellLayout {
+    /// Returns the number of tiles on the whole flowcell, across every lane, surface, swath and
+    /// section
+    pub fn total_tiles(&self) -> u64 {
+        u64::from(self.lane_count)
+            * u64::from(self.surface_count)
+            * u64::from(self.swath_count)
+            * u64::from(self.tile_count)
+            * u64::from(self.section_per_lane.unwrap_or(1))
+    }
+}
+
+impl RunInfo {
+    /// Returns the [FlowcellLayout] of the run, if known. See [layout](RunInfo::layout).
+    pub fn flowcell_layout(&self) -> Option<FlowcellLayout> {
+        self.layout
+    }
+
+    /// Returns the reads sorted by their `Number` attribute.
+    ///
+    /// RunInfo.xml usually lists reads in order, but [reads](RunInfo::reads) preserves the
+    /// document order, so prefer this when the sequencing order matters.
+    pub fn reads_ordered(&self) -> Vec<&ReadInfo> {
+        let mut reads: Vec<&ReadInfo> = self.reads.iter().collect();
+        reads.sort_by_key(|r| r.number);
+        reads
+    }
+
+    /// Returns the 1-based cycles of each read, keyed by read number, in sequencing order.
+    ///
+    /// A read with no cycles has an empty range.
+    pub fn cycle_ranges(&self) -> Vec<(u8, RangeInclusive<u16>)> {
+        let mut start = 1u16;
+        self.reads_ordered()
+            .into_iter()
+            .map(|r| {
+                let end = start.saturating_add(r.num_cycles);
+                let range = start..=end.saturating_sub(1);
+                start = end;
+                (r.number, range)
+            })
+            .collect()
+    }
+
+    /// Returns the number of cycles planned across all reads, including index reads.
+    ///
+    /// Saturates at `u16::MAX` rather than overflowing.
+    pub fn total_cycles(&self) -> u16 {
+        sum_cycles(self.reads.iter())
+    }
+
+    /// Returns the number of cycles planned across non-index reads.
+    ///
+    /// Saturates at `u16::MAX` rather than overflowing.
+    pub fn data_cycles(&self) -> u16 {
+        sum_cycles(self.reads.iter().filter(|r| !r.is_indexed_read))
+    }
+
+    /// Returns true if the run has exactly two non-index reads.
+    pub fn is_paired_end(&self) -> bool {
+        self.reads.iter().filter(|r| !r.is_indexed_read).count() == 2
+    }
+}
+
+fn sum_cycles<'a>(reads: impl Iterator<Item = &'a ReadInfo>) -> u16 {
+    reads.fold(0, |acc, r| acc.saturating_add(r.num_cycles))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// A flowcell identifier, along with the instrument side it was loaded on (if known).
+///
+/// Dual-flowcell instruments (e.g. NovaSeq) prefix the flowcell id in the run folder name with
+/// the side, `A` or `B`.
+pub struct Flowcell {
+    pub id: String,
+    pub side: Option<char>,
+}
+
+impl Flowcell {
+    /// Construct a Flowcell from its id and the name of the run folder.
+    ///
+    /// The side is parsed from the last underscore-separated component of `folder_name`, which
+    /// must be `A` or `B` followed by exactly `id`. Otherwise, side is None.
+    pub fn from_run_folder(id: &str, folder_name: &str) -> Self {
+        let side = folder_name
+            .rsplit('_')
+            .next()
+            .and_then(|last| last.strip_suffix(id))
+            .and_then(|prefix| match prefix {
+                "A" => Some('A'),
+                "B" => Some('B'),
+                _ => None,
+            });
+        Flowcell {
+            id: id.to_string(),
+            side,
+        }
+    }
+}
+
+fn invalid_data<E>(error: E) -> std::io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
+
+/// Find the text of the first descendant of `node` named `tag`
+fn child_text<'a>(node: &Node<'a, '_>, tag: &str) -> Result<&'a str, std::io::Error> {
+    node.descendants()
+        .find(|elem| elem.has_tag_name(tag))
+        .ok_or_else(|| invalid_data(format!("missing {tag} tag")))?
+        .text()
+        .ok_or_else(|| invalid_data(format!("{tag} tag is empty")))
+}
+
+/// Get and parse a required attribute of `node`
+fn parse_attribute<T: std::str::FromStr>(node: &Node, attr: &str) -> Result<T, std::io::Error> {
+    node.attribute(attr)
+        .ok_or_else(|| invalid_data(format!("missing {attr} attribute")))?
+        .parse::<T>()
+        .map_err(|_| invalid_data(format!("invalid {attr} attribute")))
+}
+
+fn parse_read(node: &Node) -> Result<ReadInfo, std::io::Error> {
+    Ok(ReadInfo {
+        number: parse_attribute(node, "Number")?,
+        num_cycles: parse_attribute(node, "NumCycles")?,
+        is_indexed_read: node.attribute("IsIndexedRead") == Some("Y"),
+        is_reverse_complement: node.attribute("IsReverseComplement") == Some("Y"),
+        attributes: node
+            .attributes()
+            .map(|attr| (attr.name().to_string(), attr.value().to_string()))
+            .collect(),
+    })
+}
+
+fn parse_layout(node: &Node) -> Option<FlowcellLayout> {
+    Some(FlowcellLayout {
+        lane_count: parse_attribute(node, "LaneCount").ok()?,
+        surface_count: parse_attribute(node, "SurfaceCount").ok()?,
+        swath_count: parse_attribute(node, "SwathCount").ok()?,
+        tile_count: parse_attribute(node, "TileCount").ok()?,
+        section_per_lane: parse_attribute(node, "SectionPerLane").ok(),
+        lane_per_section: parse_attribute(node, "LanePerSection").ok(),
+    })
+}
+
+/// Attempts to parse a file in the format of RunInfo.xml
+pub fn parse_run_info<P: AsRef<Path>>(path: P) -> Result<RunInfo, std::io::Error> {
+    read_run_info(File::open(&path)?)
+}
+
+/// Like [parse_run_info], but reads from an already-opened source.
+pub fn read_run_info<R: Read>(mut reader: R) -> Result<RunInfo, std::io::Error> {
+    let mut raw_contents = String::new();
+    reader.read_to_string(&mut raw_contents)?;
+    let doc = roxmltree::Document::parse(&raw_contents)
+        .map_err(|e| invalid_data(format!("Could not parse as XML: {e}")))?;
+
+    let run = doc
+        .descendants()
+        .find(|elem| elem.has_tag_name(RUN))
+        .ok_or_else(|| invalid_data("missing Run tag"))?;
+
+    let run_id = run
+        .attribute("Id")
+        .ok_or_else(|| invalid_data("missing Id attribute"))?
+        .to_string();
+    let run_number = run.attribute("Number").and_then(|n| n.parse().ok());
+
+    let reads = run
+        .descendants()
+        .filter(|elem| elem.has_tag_name(READ))
+        .map(|elem| parse_read(&elem))
+        .collect::<Result<Vec<ReadInfo>, std::io::Error>>()?;
+    let layout = run
+        .descendants()
+        .find(|elem| elem.has_tag_name(FLOWCELL_LAYOUT))
+        .and_then(|elem| parse_layout(&elem));
+
+    Ok(RunInfo {
+        run_id,
+        run_number,
+        flowcell: child_text(&run, FLOWCELL)?.to_string(),
+        instrument: child_text(&run, INSTRUMENT)?.to_string(),
+        reads,
+        layout,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_run_info, Flowcell};
+
+    const COMPLETE_RI: &str = "test_data/seq_complete/RunInfo.xml";
+    const SINGLE_END_RI: &str = "test_data/seq_transferring/RunInfo.xml";
+    const NOVASEQ_RI: &str = "test_data/20240110_A00123_0042_AHABCDEFXY/RunInfo.xml";
+    const EMPTY_RI: &str = "test_data/seq_sequencing/RunInfo.xml";
+    const OUT_OF_ORDER_RI: &str = "test_data/run_info/reads_out_of_order.xml";
+
+    #[test]
+    fn parse_complete() {
+        let run_info = parse_run_info(COMPLETE_RI).unwrap();
+        assert_eq!(run_info.run_id, "20231231_foo_ABCXYZ");
+        assert_eq!(run_info.run_number, Some(1));
+        assert_eq!(run_info.flowcell, "ABCXYZ");
+        assert_eq!(run_info.instrument, "foo");
+        assert_eq!(run_info.reads.len(), 4);
+        assert!(run_info.reads[1].is_indexed_read);
+        assert_eq!(run_info.reads[3].num_cycles, 15);
+    }
+
+    #[test]
+    fn read_attributes() {
+        let run_info = parse_run_info(COMPLETE_RI).unwrap();
+        assert!(!run_info.reads[1].is_reverse_complement);
+        assert!(run_info.reads[2].is_reverse_complement);
+        assert_eq!(
+            run_info.reads[2]
+                .attributes
+                .get("NumCycles")
+                .map(String::as_str),
+            Some("6")
+        );
+        assert_eq!(run_info.reads[2].attributes.len(), 4);
+        // attribute absent
+        let run_info = parse_run_info(SINGLE_END_RI).unwrap();
+        assert!(!run_info.reads[2].is_reverse_complement);
+    }
+
+    #[test]
+    fn flowcell_layout() {
+        let layout = parse_run_info(COMPLETE_RI)
+            .unwrap()
+            .flowcell_layout()
+            .unwrap();
+        assert_eq!(layout.lane_count, 4);
+        assert_eq!(layout.surface_count, 2);
+        assert_eq!(layout.tile_count, 2);
+        assert_eq!(layout.section_per_lane, None);
+        let layout = parse_run_info(SINGLE_END_RI)
+            .unwrap()
+            .flowcell_layout()
+            .unwrap();
+        assert_eq!(layout.swath_count, 3);
+        assert_eq!(layout.section_per_lane, Some(3));
+        assert_eq!(layout.lane_per_section, Some(2));
+        assert_eq!(layout.total_tiles(), 864);
+        let layout = parse_run_info(NOVASEQ_RI)
+            .unwrap()
+            .flowcell_layout()
+            .unwrap();
+        assert_eq!((layout.lane_count, layout.tile_count), (2, 78));
+    }
+
+    #[test]
+    fn paired_end() {
+        assert!(parse_run_info(COMPLETE_RI).unwrap().is_paired_end());
+        // dual-indexed, but only one template read
+        let run_info = parse_run_info(SINGLE_END_RI).unwrap();
+        assert_eq!(run_info.reads.len(), 3);
+        assert!(!run_info.is_paired_end());
+    }
+
+    #[test]
+    fn total_and_data_cycles() {
+        let run_info = parse_run_info(COMPLETE_RI).unwrap();
+        assert_eq!(run_info.total_cycles(), 42);
+        assert_eq!(run_info.data_cycles(), 30);
+        let mut run_info = parse_run_info(SINGLE_END_RI).unwrap();
+        assert_eq!(run_info.total_cycles(), 91);
+        assert_eq!(run_info.data_cycles(), 75);
+        for read in &mut run_info.reads {
+            read.num_cycles = u16::MAX / 2;
+        }
+        assert_eq!(run_info.total_cycles(), u16::MAX);
+        run_info.reads.clear();
+        assert_eq!(run_info.total_cycles(), 0);
+    }
+
+    #[test]
+    fn reads_out_of_order() {
+        let run_info = parse_run_info(OUT_OF_ORDER_RI).unwrap();
+        assert_eq!(run_info.reads[0].number, 3);
+        let numbers: Vec<u8> = run_info.reads_ordered().iter().map(|r| r.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4]);
+        assert_eq!(
+            run_info.cycle_ranges(),
+            vec![(1, 1..=151), (2, 152..=159), (3, 160..=167), (4, 168..=318)]
+        );
+    }
+
+    #[test]
+    fn flowcell_side() {
+        let flowcell = Flowcell::from_run_folder("HABCDEFXY", "20240110_A00123_0042_BHABCDEFXY");
+        assert_eq!(flowcell.side, Some('B'));
+        let flowcell = Flowcell::from_run_folder("ABCXYZ", "20231231_foo_ABCXYZ");
+        assert_eq!(flowcell.side, None);
+        let flowcell = Flowcell::from_run_folder("HABCDEFXY", "20240110_A00123_0042_CHABCDEFXY");
+        assert_eq!(flowcell.side, None);
+    }
+
+    #[test]
+    fn empty_does_not_panic() {
+        assert!(parse_run_info(EMPTY_RI).is_err());
+    }
+}
@@ -0,0 +1,514 @@
+//! Parse RunParameters.xml
+//!
+//! The schema of RunParameters.xml varies widely between platforms and control software
+//! versions, so parsing is tolerant: every field is optional, and elements are looked up by any
+//! of several known names.
+
+use std::path::Path;
+use std::{fs::File, io::Read};
+
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+
+use crate::{CompletionStatus, Message, COPY_COMPLETE_TXT, RTA_COMPLETE_TXT};
+
+const RTA_VERSION: [&str; 2] = ["RtaVersion", "RTAVersion"];
+const APPLICATION_VERSION: [&str; 2] = ["ApplicationVersion", "SoftwareVersion"];
+const SECONDARY_ANALYSIS: [&str; 2] = ["SecondaryAnalysisWorkflow", "SecondaryAnalysisPlatform"];
+const PLATFORM: [&str; 3] = ["InstrumentType", "ApplicationName", "Application"];
+const RUN_ID: [&str; 2] = ["RunId", "RunID"];
+const COMPLETION_STATUS: [&str; 3] = ["CompletionStatus", "RunCompletionStatus", "RunStatus"];
+const ERROR_DESCRIPTION: [&str; 1] = ["ErrorDescription"];
+const REAGENT_KIT_VERSION: [&str; 1] = ["ReagentKitVersion"];
+
+// Consumables are described either by prefixed elements (e.g. NovaSeq `FlowCellPartNumber`) or
+// by an RFID tag containing unprefixed elements (e.g. MiSeq `FlowcellRFIDTag/PartNumber`).
+const SERIAL_NUMBER: [&str; 2] = ["SerialBarcode", "SerialNumber"];
+const PART_NUMBER: [&str; 1] = ["PartNumber"];
+const LOT_NUMBER: [&str; 1] = ["LotNumber"];
+const EXPIRATION_DATE: [&str; 2] = ["ExpirationDate", "Expirationdate"];
+const FLOWCELL_PREFIXES: [&str; 2] = ["FlowCell", "Flowcell"];
+const FLOWCELL_TAGS: [&str; 1] = ["FlowcellRFIDTag"];
+const BUFFER_PREFIXES: [&str; 1] = ["Buffer"];
+const BUFFER_TAGS: [&str; 1] = ["PR2BottleRFIDTag"];
+const REAGENT_PREFIXES: [&str; 2] = ["Sbs", "Reagent"];
+const REAGENT_TAGS: [&str; 1] = ["ReagentKitRFIDTag"];
+const CLUSTER_PREFIXES: [&str; 1] = ["Cluster"];
+
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+/// The family of instrument a run was sequenced on
+pub enum Platform {
+    MiSeq,
+    /// NextSeq 500 and 550
+    NextSeq,
+    /// NextSeq 1000 and 2000, which have a different directory layout than other NextSeqs
+    NextSeq2000,
+    NovaSeq,
+    ISeq,
+    HiSeq,
+    #[default]
+    Unknown,
+}
+
+impl Platform {
+    /// Identify the platform from an instrument type or control software name, e.g. `NovaSeq6000`
+    /// or `NextSeq 1000/2000 Control Software`. Matching is case-insensitive.
+    pub fn from_name(name: &str) -> Platform {
+        let name = name.to_ascii_lowercase();
+        if name.contains("nextseq 1000") || name.contains("nextseq 2000") {
+            Platform::NextSeq2000
+        } else if name.contains("nextseq") {
+            Platform::NextSeq
+        } else if name.contains("miseq") {
+            Platform::MiSeq
+        } else if name.contains("novaseq") {
+            Platform::NovaSeq
+        } else if name.contains("hiseq") {
+            Platform::HiSeq
+        } else if name.contains("iseq") {
+            Platform::ISeq
+        } else {
+            Platform::Unknown
+        }
+    }
+
+    /// The sentinel file the platform typically writes once a run is finished.
+    ///
+    /// Newer platforms write CopyComplete.txt once the run has been copied to its output
+    /// folder. MiSeq, NextSeq 500/550 and HiSeq never write it, and only signal completion
+    /// with RTAComplete.txt. CopyComplete.txt is assumed for an unknown platform.
+    pub fn completion_marker(&self) -> &'static str {
+        match self {
+            Platform::MiSeq | Platform::NextSeq | Platform::HiSeq => RTA_COMPLETE_TXT,
+            Platform::NextSeq2000 | Platform::NovaSeq | Platform::ISeq | Platform::Unknown => {
+                COPY_COMPLETE_TXT
+            }
+        }
+    }
+
+    /// A placeholder size, in bytes, of the basecalls written for one tile in one cycle.
+    ///
+    /// These are rough round numbers, not measurements or published figures, and real runs may
+    /// differ by an order of magnitude. They only serve as a fallback for
+    /// [estimate_final_size](crate::SeqDir::estimate_final_size()) before any cycle has been
+    /// written.
+    pub fn bytes_per_tile_cycle(&self) -> u64 {
+        match self {
+            Platform::NovaSeq | Platform::HiSeq => 1_000_000,
+            Platform::NextSeq2000 => 500_000,
+            Platform::MiSeq => 400_000,
+            Platform::ISeq => 300_000,
+            Platform::NextSeq => 200_000,
+            Platform::Unknown => 500_000,
+        }
+    }
+}
+
+/// Known instrument serial number prefixes, with the platform they identify.
+///
+/// | Prefix                    | Platform                         |
+/// |---------------------------|----------------------------------|
+/// | `M`                       | MiSeq                            |
+/// | `NB`, `NS`                | NextSeq 500/550                  |
+/// | `VH`, `VL`                | NextSeq 1000/2000                |
+/// | `A`, `LH`                 | NovaSeq 6000, NovaSeq X          |
+/// | `FS`                      | iSeq 100                         |
+/// | `D`, `E`, `J`, `K`, `SN`  | HiSeq 2500, X, 3000, 4000, 2000  |
+///
+/// A prefix only matches if it is followed by a digit, so e.g. MiniSeq serials (`MN…`) are not
+/// mistaken for MiSeq. To support a new prefix, add it here.
+pub const INSTRUMENT_PREFIXES: [(&str, Platform); 13] = [
+    ("M", Platform::MiSeq),
+    ("NB", Platform::NextSeq),
+    ("NS", Platform::NextSeq),
+    ("VH", Platform::NextSeq2000),
+    ("VL", Platform::NextSeq2000),
+    ("A", Platform::NovaSeq),
+    ("LH", Platform::NovaSeq),
+    ("FS", Platform::ISeq),
+    ("D", Platform::HiSeq),
+    ("E", Platform::HiSeq),
+    ("J", Platform::HiSeq),
+    ("K", Platform::HiSeq),
+    ("SN", Platform::HiSeq),
+];
+
+/// Identify the platform from an instrument serial number, e.g. `A00123`, using
+/// [INSTRUMENT_PREFIXES].
+///
+/// Returns None if no known prefix matches.
+pub fn platform_from_serial(serial: &str) -> Option<Platform> {
+    let serial = serial.trim();
+    INSTRUMENT_PREFIXES
+        .iter()
+        .find(|(prefix, _)| {
+            serial
+                .get(..prefix.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+                && serial[prefix.len()..].starts_with(|c: char| c.is_ascii_digit())
+        })
+        .map(|(_, platform)| *platform)
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+/// The contents of RunParameters.xml
+pub struct RunParameters {
+    /// Detected from InstrumentType, falling back to the control software name
+    #[serde(default)]
+    pub instrument_type: Platform,
+    pub rta_version: Option<String>,
+    /// The version of the instrument control software, e.g. `1.7.5`
+    #[serde(default)]
+    pub application_version: Option<String>,
+    /// The on-instrument secondary analysis workflow, e.g. `GenerateFASTQ`
+    pub secondary_analysis: Option<String>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    /// The raw completion status, on platforms that record it here, e.g. `CompletedAsPlanned`
+    #[serde(default)]
+    pub completion_status: Option<String>,
+    #[serde(default)]
+    pub error_description: Option<String>,
+    #[serde(default)]
+    pub consumables: Consumables,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+/// Identifiers of a single consumable. Fields that are not recorded are None.
+pub struct Consumable {
+    pub serial_number: Option<String>,
+    pub part_number: Option<String>,
+    pub lot_number: Option<String>,
+    /// As written by the instrument, the format varies by platform
+    pub expiration_date: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+/// The consumables loaded for a run
+pub struct Consumables {
+    pub flowcell: Consumable,
+    /// The buffer cartridge (NovaSeq) or PR2 bottle (MiSeq)
+    pub buffer: Consumable,
+    /// The SBS cartridge (NovaSeq) or reagent kit (MiSeq)
+    pub reagent: Consumable,
+    /// The cluster cartridge, on platforms with a separate one
+    pub cluster: Consumable,
+    /// e.g. `Version3`
+    pub reagent_kit_version: Option<String>,
+}
+
+impl RunParameters {
+    /// Interpret the recorded completion status as a [CompletionStatus].
+    ///
+    /// Returns None unless both a run id and a completion status are present.
+    pub fn completion_status(&self) -> Option<CompletionStatus> {
+        let message = Message {
+            run_id: self.run_id.clone()?,
+            message: self
+                .error_description
+                .clone()
+                .filter(|description| description != "None"),
+        };
+        Some(CompletionStatus::from_parts(
+            self.completion_status.as_deref()?,
+            message,
+        ))
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+/// The software versions that produced a run. Versions are recorded as written by the
+/// instrument, e.g. `v3.4.4` or `2.6.2.1`.
+pub struct SoftwareVersions {
+    /// The instrument control software, e.g. NovaSeq Control Software
+    pub control_software: Option<String>,
+    pub rta: Option<String>,
+}
+
+impl SoftwareVersions {
+    /// Returns true if the RTA version is known and at least `min_rta`.
+    ///
+    /// See [parse_version] for how the recorded version is interpreted. An RTA version that is
+    /// missing or cannot be interpreted never satisfies the requirement.
+    pub fn satisfies(&self, min_rta: &semver::Version) -> bool {
+        self.rta
+            .as_deref()
+            .and_then(parse_version)
+            .is_some_and(|rta| rta >= *min_rta)
+    }
+}
+
+/// Leniently interpret an instrument software version as a semantic version.
+///
+/// Illumina versions do not follow semver: they may have a `v` prefix, fewer than three
+/// components (`3.10`), or four (`2.6.2.1`). Missing components are taken to be 0 and
+/// components after the third, which are build numbers, are ignored. Returns None if the first
+/// three components are not all numeric.
+pub fn parse_version(version: &str) -> Option<semver::Version> {
+    let version = version.trim();
+    let version = version
+        .strip_prefix(['v', 'V'])
+        .unwrap_or(version)
+        .split_whitespace()
+        .next()?;
+    let mut components = version.split('.').map(str::parse::<u64>);
+    let major = components.next()?.ok()?;
+    let minor = components.next().transpose().ok()?.unwrap_or(0);
+    let patch = components.next().transpose().ok()?.unwrap_or(0);
+    Some(semver::Version::new(major, minor, patch))
+}
+
+/// Find the trimmed, non-empty text of the first descendant of `node` matching any of `tags`.
+///
+/// Tags are tried in order of preference.
+pub(crate) fn find_text<S: AsRef<str>>(node: Node, tags: &[S]) -> Option<String> {
+    tags.iter().find_map(|tag| {
+        node.descendants()
+            .find(|elem| elem.has_tag_name(tag.as_ref()))
+            .and_then(|node| node.text())
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// Find a consumable by its prefixed elements, falling back to an RFID tag container
+fn find_consumable(doc: &Document, prefixes: &[&str], containers: &[&str]) -> Consumable {
+    let container = doc
+        .descendants()
+        .find(|elem| containers.iter().any(|tag| elem.has_tag_name(*tag)));
+    let lookup = |suffixes: &[&str]| {
+        let prefixed: Vec<String> = prefixes
+            .iter()
+            .flat_map(|prefix| {
+                suffixes
+                    .iter()
+                    .map(move |suffix| format!("{prefix}{suffix}"))
+            })
+            .collect();
+        find_text(doc.root(), &prefixed).or_else(|| find_text(container?, suffixes))
+    };
+    Consumable {
+        serial_number: lookup(&SERIAL_NUMBER),
+        part_number: lookup(&PART_NUMBER),
+        lot_number: lookup(&LOT_NUMBER),
+        expiration_date: lookup(&EXPIRATION_DATE),
+    }
+}
+
+/// Identify the [Platform] from the first of `PLATFORM` that names a known platform
+fn find_platform(doc: &Document) -> Platform {
+    PLATFORM
+        .iter()
+        .filter_map(|tag| find_text(doc.root(), &[tag]))
+        .map(|name| Platform::from_name(&name))
+        .find(|platform| *platform != Platform::Unknown)
+        .unwrap_or_default()
+}
+
+/// Attempts to parse a file in the format of RunParameters.xml
+pub fn parse_run_params<P: AsRef<Path>>(path: P) -> Result<RunParameters, std::io::Error> {
+    let mut handle = File::open(&path)?;
+    let mut raw_contents = String::new();
+    handle.read_to_string(&mut raw_contents)?;
+    let doc = roxmltree::Document::parse(&raw_contents).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Could not parse as XML: {e}"),
+        )
+    })?;
+
+    Ok(RunParameters {
+        instrument_type: find_platform(&doc),
+        rta_version: find_text(doc.root(), &RTA_VERSION),
+        application_version: find_text(doc.root(), &APPLICATION_VERSION),
+        secondary_analysis: find_text(doc.root(), &SECONDARY_ANALYSIS),
+        run_id: find_text(doc.root(), &RUN_ID),
+        completion_status: find_text(doc.root(), &COMPLETION_STATUS),
+        error_description: find_text(doc.root(), &ERROR_DESCRIPTION),
+        consumables: Consumables {
+            flowcell: find_consumable(&doc, &FLOWCELL_PREFIXES, &FLOWCELL_TAGS),
+            buffer: find_consumable(&doc, &BUFFER_PREFIXES, &BUFFER_TAGS),
+            reagent: find_consumable(&doc, &REAGENT_PREFIXES, &REAGENT_TAGS),
+            cluster: find_consumable(&doc, &CLUSTER_PREFIXES, &[]),
+            reagent_kit_version: find_text(doc.root(), &REAGENT_KIT_VERSION),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_run_params, parse_version, platform_from_serial, Platform, SoftwareVersions,
+    };
+    use crate::{CompletionStatus, COPY_COMPLETE_TXT, RTA_COMPLETE_TXT};
+
+    const COMPLETE_RP: &str = "test_data/seq_complete/RunParameters.xml";
+    const NEXTSEQ2000_RP: &str = "test_data/seq_nextseq2000/RunParameters.xml";
+    const STATUS_RP: &str = "test_data/seq_runparams_status/RunParameters.xml";
+    const MISEQ_RP: &str = "test_data/seq_partial/RunParameters.xml";
+    const EMPTY_RP: &str = "test_data/seq_sequencing/RunParameters.xml";
+
+    #[test]
+    fn parse_complete() {
+        let run_params = parse_run_params(COMPLETE_RP).unwrap();
+        assert_eq!(run_params.rta_version.as_deref(), Some("v3.4.4"));
+        assert_eq!(run_params.secondary_analysis, None);
+        assert_eq!(run_params.completion_status(), None);
+        assert_eq!(run_params.instrument_type, Platform::NovaSeq);
+    }
+
+    #[test]
+    fn novaseq_consumables() {
+        let consumables = parse_run_params(COMPLETE_RP).unwrap().consumables;
+        assert_eq!(
+            consumables.flowcell.serial_number.as_deref(),
+            Some("ABCXYZ")
+        );
+        assert_eq!(consumables.flowcell.lot_number.as_deref(), Some("20500123"));
+        assert_eq!(
+            consumables.flowcell.expiration_date.as_deref(),
+            Some("12/1/2024 12:00:00 AM")
+        );
+        assert_eq!(consumables.buffer.part_number.as_deref(), Some("20015876"));
+        assert_eq!(consumables.reagent.lot_number.as_deref(), Some("20530123"));
+        assert_eq!(consumables.cluster.lot_number.as_deref(), Some("20520123"));
+        assert_eq!(consumables.reagent_kit_version, None);
+    }
+
+    #[test]
+    fn miseq_consumables() {
+        let run_params = parse_run_params(MISEQ_RP).unwrap();
+        assert_eq!(run_params.instrument_type, Platform::MiSeq);
+        let consumables = run_params.consumables;
+        assert_eq!(
+            consumables.flowcell.serial_number.as_deref(),
+            Some("000000000-ABCDE")
+        );
+        assert_eq!(
+            consumables.flowcell.part_number.as_deref(),
+            Some("15028382")
+        );
+        assert_eq!(consumables.flowcell.lot_number, None);
+        assert_eq!(consumables.buffer.part_number.as_deref(), Some("15041807"));
+        assert_eq!(
+            consumables.reagent.serial_number.as_deref(),
+            Some("MS7654321-600V3")
+        );
+        assert_eq!(consumables.cluster, Default::default());
+        assert_eq!(consumables.reagent_kit_version.as_deref(), Some("Version3"));
+    }
+
+    #[test]
+    fn platform_from_name() {
+        assert_eq!(Platform::from_name("NextSeq 2000"), Platform::NextSeq2000);
+        assert_eq!(
+            Platform::from_name("NextSeq 1000/2000 Control Software"),
+            Platform::NextSeq2000
+        );
+        assert_eq!(
+            Platform::from_name("NextSeq Control Software"),
+            Platform::NextSeq
+        );
+        assert_eq!(Platform::from_name("miseq"), Platform::MiSeq);
+        assert_eq!(
+            Platform::from_name("HiSeq Control Software"),
+            Platform::HiSeq
+        );
+        assert_eq!(Platform::from_name("iSeq 100"), Platform::ISeq);
+        assert_eq!(Platform::from_name("foo"), Platform::Unknown);
+    }
+
+    #[test]
+    fn completion_markers() {
+        let marker = |rp| {
+            parse_run_params(rp)
+                .unwrap()
+                .instrument_type
+                .completion_marker()
+        };
+        assert_eq!(marker(MISEQ_RP), RTA_COMPLETE_TXT);
+        assert_eq!(marker(COMPLETE_RP), COPY_COMPLETE_TXT);
+        assert_eq!(marker(NEXTSEQ2000_RP), COPY_COMPLETE_TXT);
+        assert_eq!(Platform::Unknown.completion_marker(), COPY_COMPLETE_TXT);
+    }
+
+    #[test]
+    fn platform_from_serials() {
+        assert_eq!(platform_from_serial("A00123"), Some(Platform::NovaSeq));
+        assert_eq!(platform_from_serial("LH00123"), Some(Platform::NovaSeq));
+        assert_eq!(platform_from_serial("VH00123"), Some(Platform::NextSeq2000));
+        assert_eq!(platform_from_serial("NB551234"), Some(Platform::NextSeq));
+        assert_eq!(platform_from_serial("m00123"), Some(Platform::MiSeq));
+        assert_eq!(platform_from_serial("MN00123"), None);
+        assert_eq!(platform_from_serial("foo"), None);
+        assert_eq!(platform_from_serial(""), None);
+    }
+
+    #[test]
+    fn parse_completion_status() {
+        let run_params = parse_run_params(STATUS_RP).unwrap();
+        let Some(CompletionStatus::ExceptionEndedEarly(message)) = run_params.completion_status()
+        else {
+            panic!("expected ExceptionEndedEarly")
+        };
+        assert_eq!(message.run_id, "20240115_M00123_0007_000000000-ABCDE");
+        assert_eq!(
+            message.message.as_deref(),
+            Some("Fluidics error at cycle 12")
+        );
+    }
+
+    #[test]
+    fn parse_nextseq2000() {
+        let run_params = parse_run_params(NEXTSEQ2000_RP).unwrap();
+        assert_eq!(run_params.instrument_type, Platform::NextSeq2000);
+        assert_eq!(run_params.rta_version.as_deref(), Some("3.10.30"));
+        assert_eq!(
+            run_params.secondary_analysis.as_deref(),
+            Some("GenerateFASTQ")
+        );
+    }
+
+    #[test]
+    fn parse_application_version() {
+        let versions = [COMPLETE_RP, MISEQ_RP, NEXTSEQ2000_RP, EMPTY_RP].map(|rp| {
+            parse_run_params(rp)
+                .ok()
+                .and_then(|p| p.application_version)
+        });
+        assert_eq!(
+            versions.iter().map(Option::as_deref).collect::<Vec<_>>(),
+            [Some("1.7.5"), Some("2.6.2.1"), Some("1.4.1.39716"), None]
+        );
+    }
+
+    #[test]
+    fn lenient_versions() {
+        let version = |s| parse_version(s).map(|v| v.to_string());
+        assert_eq!(version("v3.4.4").as_deref(), Some("3.4.4"));
+        assert_eq!(version("2.6.2.1").as_deref(), Some("2.6.2"));
+        assert_eq!(version("3.10").as_deref(), Some("3.10.0"));
+        assert_eq!(version(" 1.18.54 ").as_deref(), Some("1.18.54"));
+        assert_eq!(version("unknown"), None);
+        assert_eq!(version("3.x.1"), None);
+    }
+
+    #[test]
+    fn satisfies_min_rta() {
+        let versions = SoftwareVersions {
+            control_software: None,
+            rta: Some("v3.4.4.1".to_string()),
+        };
+        assert!(versions.satisfies(&semver::Version::new(3, 4, 4)));
+        assert!(versions.satisfies(&semver::Version::new(2, 9, 0)));
+        assert!(!versions.satisfies(&semver::Version::new(3, 10, 0)));
+        assert!(!SoftwareVersions::default().satisfies(&semver::Version::new(0, 0, 0)));
+    }
+
+    #[test]
+    fn empty_does_not_panic() {
+        assert!(parse_run_params(EMPTY_RP).is_err());
+    }
+}
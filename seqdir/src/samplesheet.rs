@@ -0,0 +1,392 @@
+//! Parse SampleSheet.csv
+//!
+//! Sample sheets are INI-like: a `[Section]` line is followed by comma-separated rows until the
+//! next section. Both the bcl2fastq (v1) and BCLConvert (v2) formats are supported. Rows are
+//! kept verbatim apart from trailing empty columns, which spreadsheet editors add freely.
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{RunInfo, SeqDirError};
+
+const READS: &str = "Reads";
+const DATA: &str = "Data";
+const BCLCONVERT_DATA: &str = "BCLConvert_Data";
+const BCLCONVERT_SECTIONS: [&str; 2] = ["BCLConvert_Settings", BCLCONVERT_DATA];
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// A section of a sample sheet, e.g. `[Header]`
+pub struct Section {
+    /// The section name, without brackets
+    pub name: String,
+    /// The non-empty rows of the section, with trailing empty columns removed
+    pub rows: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// The contents of SampleSheet.csv
+pub struct SampleSheet {
+    pub sections: Vec<Section>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// The read lengths listed in the `[Reads]` section of a sample sheet
+pub struct ReadLengths {
+    /// Lengths of the non-index reads, in order
+    pub reads: Vec<u16>,
+    /// Lengths of the index reads, in order.
+    ///
+    /// None if the sample sheet does not specify them, which is the case for v1 sample sheets.
+    pub index_reads: Option<Vec<u16>>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// A read whose length differs between RunInfo.xml and the sample sheet
+pub struct ReadDiscrepancy {
+    pub is_indexed_read: bool,
+    /// 1-based position among reads of the same kind, e.g. 2 for the second index read
+    pub ordinal: usize,
+    /// The length according to RunInfo.xml, None if RunInfo.xml has no such read
+    pub run_info: Option<u16>,
+    /// The length according to the sample sheet, None if the sample sheet has no such read
+    pub samplesheet: Option<u16>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+/// A row of the `[Data]` (or `[BCLConvert_Data]`) section of a sample sheet
+pub struct Sample {
+    pub sample_id: String,
+    pub sample_name: Option<String>,
+    pub index: Option<String>,
+    pub index2: Option<String>,
+    pub sample_project: Option<String>,
+    /// The lane the sample is loaded on, None if it applies to every lane
+    pub lane: Option<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+/// The demultiplexer a sample sheet is written for
+pub enum DemuxTool {
+    /// A v1 sample sheet with a legacy `[Data]` section
+    Bcl2Fastq,
+    /// A v2 sample sheet with `[BCLConvert_Settings]` and/or `[BCLConvert_Data]` sections
+    BclConvert,
+    /// Neither, or both, kinds of section are present
+    Unknown,
+}
+
+impl SampleSheet {
+    /// Returns the rows of the first section named `name`, if present. Names are matched exactly.
+    pub fn section(&self, name: &str) -> Option<&[String]> {
+        self.sections
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.rows.as_slice())
+    }
+
+    /// Infer which demultiplexer the sample sheet targets from its sections.
+    ///
+    /// A sheet with both a legacy `[Data]` section and BCLConvert sections is ambiguous, and
+    /// returns [DemuxTool::Unknown] rather than guessing.
+    pub fn demux_tool(&self) -> DemuxTool {
+        let bclconvert = BCLCONVERT_SECTIONS
+            .iter()
+            .any(|name| self.section(name).is_some());
+        match (bclconvert, self.section(DATA).is_some()) {
+            (true, false) => DemuxTool::BclConvert,
+            (false, true) => DemuxTool::Bcl2Fastq,
+            _ => DemuxTool::Unknown,
+        }
+    }
+
+    /// Parse the samples listed in the `[Data]` section, or `[BCLConvert_Data]` for v2 sheets.
+    ///
+    /// The first row is the header, and columns are found by name, ignoring case. Rows may have
+    /// fewer or more columns than the header; missing and empty values are None. Values may be
+    /// double-quoted, as written by spreadsheet software, to contain commas. Rows without a
+    /// Sample_ID are skipped. Returns an empty list if there is no data section.
+    pub fn samples(&self) -> Result<Vec<Sample>, SeqDirError> {
+        let Some(rows) = self.section(DATA).or_else(|| self.section(BCLCONVERT_DATA)) else {
+            return Ok(Vec::new());
+        };
+        let Some((header, rows)) = rows.split_first() else {
+            return Ok(Vec::new());
+        };
+        let header = split_fields(header);
+        let column = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+        let columns = [
+            column("Sample_ID"),
+            column("Sample_Name"),
+            column("index"),
+            column("index2"),
+            column("Sample_Project"),
+            column("Lane"),
+        ];
+        let mut samples = Vec::new();
+        for row in rows {
+            let fields = split_fields(row);
+            let [id, name, index, index2, project, lane] = columns.map(|c| {
+                c.and_then(|i| fields.get(i))
+                    .filter(|f| !f.is_empty())
+                    .map(|f| f.to_string())
+            });
+            let Some(sample_id) = id else {
+                continue;
+            };
+            let lane = lane
+                .map(|l| {
+                    l.parse::<u8>().map_err(|_| {
+                        SeqDirError::SampleSheet(format!("invalid lane for {sample_id}: {l}"))
+                    })
+                })
+                .transpose()?;
+            samples.push(Sample {
+                sample_id,
+                sample_name: name,
+                index,
+                index2,
+                sample_project: project,
+                lane,
+            });
+        }
+        Ok(samples)
+    }
+
+    /// Parse the `[Reads]` section.
+    ///
+    /// v1 sample sheets list one read length per row; v2 sample sheets list `Read1Cycles`,
+    /// `Index1Cycles`, etc. as key-value rows.
+    pub fn read_lengths(&self) -> Result<ReadLengths, SeqDirError> {
+        let rows = self
+            .section(READS)
+            .ok_or_else(|| SeqDirError::SampleSheet(format!("missing [{READS}] section")))?;
+        let mut reads = Vec::new();
+        let mut index_reads = Vec::new();
+        for row in rows {
+            let mut fields = row.split(',').map(str::trim);
+            let first = fields.next().unwrap_or_default();
+            let length = match fields.next() {
+                Some(length) => length,
+                None => first,
+            };
+            let length = length.parse::<u16>().map_err(|_| {
+                SeqDirError::SampleSheet(format!("invalid read length in [{READS}]: {row}"))
+            })?;
+            if first.starts_with("Index") {
+                index_reads.push(length);
+            } else {
+                reads.push(length);
+            }
+        }
+        let is_v2 = rows.iter().any(|row| row.contains(','));
+        Ok(ReadLengths {
+            reads,
+            index_reads: is_v2.then_some(index_reads),
+        })
+    }
+}
+
+/// Compare the reads of `run_info` with the read lengths of a sample sheet.
+///
+/// Reads are compared in order of their read number. Index reads are only compared if the sample
+/// sheet specifies them.
+pub fn compare_reads(run_info: &RunInfo, lengths: &ReadLengths) -> Vec<ReadDiscrepancy> {
+    let expected = |indexed: bool| -> Vec<u16> {
+        run_info
+            .reads_ordered()
+            .into_iter()
+            .filter(|r| r.is_indexed_read == indexed)
+            .map(|r| r.num_cycles)
+            .collect()
+    };
+    let mut discrepancies = diff_lengths(false, &expected(false), &lengths.reads);
+    if let Some(index_reads) = &lengths.index_reads {
+        discrepancies.extend(diff_lengths(true, &expected(true), index_reads));
+    }
+    discrepancies
+}
+
+fn diff_lengths(
+    is_indexed_read: bool,
+    run_info: &[u16],
+    samplesheet: &[u16],
+) -> Vec<ReadDiscrepancy> {
+    (0..run_info.len().max(samplesheet.len()))
+        .map(|i| (i, run_info.get(i).copied(), samplesheet.get(i).copied()))
+        .filter(|(_, expected, found)| expected != found)
+        .map(|(i, run_info, samplesheet)| ReadDiscrepancy {
+            is_indexed_read,
+            ordinal: i + 1,
+            run_info,
+            samplesheet,
+        })
+        .collect()
+}
+
+/// Split a row into trimmed fields, allowing double-quoted fields to contain commas.
+///
+/// Within a quoted field, `""` is a literal quote.
+fn split_fields(row: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Attempts to parse a file in the format of SampleSheet.csv
+pub fn parse_samplesheet<P: AsRef<Path>>(path: P) -> Result<SampleSheet, SeqDirError> {
+    let raw_contents = read_to_string(&path)?;
+    let mut sections: Vec<Section> = Vec::new();
+    for line in raw_contents.trim_start_matches('\u{feff}').lines() {
+        let row = line.trim_end().trim_end_matches(',');
+        if let Some(name) = row.strip_prefix('[') {
+            let name = name.split_once(']').map_or(name, |(name, _)| name);
+            sections.push(Section {
+                name: name.trim().to_string(),
+                rows: Vec::new(),
+            });
+        } else if let Some(section) = sections.last_mut() {
+            if !row.trim().is_empty() {
+                section.rows.push(row.to_string());
+            }
+        }
+    }
+    Ok(SampleSheet { sections })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compare_reads, parse_samplesheet, split_fields, DemuxTool, ReadDiscrepancy, SampleSheet,
+        Section,
+    };
+    use crate::run_info::parse_run_info;
+    use crate::SeqDirError;
+
+    const V2_SS: &str = "test_data/seq_complete/SampleSheet.csv";
+    const V1_SS: &str = "test_data/20240110_A00123_0042_AHABCDEFXY/SampleSheet.csv";
+    const EMPTY_SS: &str = "test_data/seq_failed/SampleSheet.csv";
+    const RAGGED_SS: &str = "test_data/samplesheet/ragged.csv";
+
+    #[test]
+    fn parses_sections() {
+        let samplesheet = parse_samplesheet(V2_SS).unwrap();
+        assert_eq!(samplesheet.sections.len(), 4);
+        assert_eq!(
+            samplesheet.section("Header").unwrap()[0],
+            "FileFormatVersion,2"
+        );
+        assert_eq!(samplesheet.section("BCLConvert_Data").unwrap().len(), 3);
+        assert!(samplesheet.section("Data").is_none());
+    }
+
+    #[test]
+    fn parses_samples() {
+        let samples = parse_samplesheet(V2_SS).unwrap().samples().unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[1].sample_id, "sample_2");
+        assert_eq!(samples[1].index2.as_deref(), Some("CCATGG"));
+        assert_eq!(samples[1].lane, Some(1));
+        assert_eq!(samples[1].sample_project, None);
+        let samples = parse_samplesheet(V1_SS).unwrap().samples().unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].sample_project.as_deref(), Some("project"));
+        assert_eq!(samples[0].lane, None);
+        let samples = parse_samplesheet(EMPTY_SS).unwrap().samples().unwrap();
+        assert!(samples.is_empty());
+    }
+
+    #[test]
+    fn parses_ragged_samples() {
+        let samplesheet = parse_samplesheet(RAGGED_SS).unwrap();
+        assert_eq!(samplesheet.section("Reads").unwrap(), ["151", "151"]);
+        let samples = samplesheet.samples().unwrap();
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0].sample_name.as_deref(), Some("Sample, one"));
+        assert_eq!(samples[0].sample_project.as_deref(), Some("proj"));
+        assert_eq!(samples[1].lane, Some(2));
+        assert_eq!(samples[1].sample_name, None);
+        assert_eq!(samples[1].index2, None);
+        assert_eq!(samples[2].sample_id, "S3");
+        assert_eq!(samples[2].index, None);
+    }
+
+    #[test]
+    fn splits_quoted_fields() {
+        assert_eq!(
+            split_fields(r#"a, "b,c" ,"d ""e""""#),
+            ["a", "b,c", r#"d "e""#]
+        );
+        assert_eq!(split_fields(",x,"), ["", "x", ""]);
+    }
+
+    #[test]
+    fn read_lengths() {
+        let lengths = parse_samplesheet(V2_SS).unwrap().read_lengths().unwrap();
+        assert_eq!(lengths.reads, vec![15, 15]);
+        assert_eq!(lengths.index_reads, Some(vec![6, 6]));
+        let lengths = parse_samplesheet(V1_SS).unwrap().read_lengths().unwrap();
+        assert_eq!(lengths.reads, vec![101, 101]);
+        assert_eq!(lengths.index_reads, None);
+        assert!(matches!(
+            parse_samplesheet(EMPTY_SS).unwrap().read_lengths(),
+            Err(SeqDirError::SampleSheet(..))
+        ));
+    }
+
+    #[test]
+    fn detects_demux_tool() {
+        assert_eq!(
+            parse_samplesheet(V2_SS).unwrap().demux_tool(),
+            DemuxTool::BclConvert
+        );
+        assert_eq!(
+            parse_samplesheet(V1_SS).unwrap().demux_tool(),
+            DemuxTool::Bcl2Fastq
+        );
+        assert_eq!(
+            parse_samplesheet(EMPTY_SS).unwrap().demux_tool(),
+            DemuxTool::Unknown
+        );
+        let section = |name: &str| Section {
+            name: name.to_string(),
+            rows: Vec::new(),
+        };
+        let ambiguous = SampleSheet {
+            sections: vec![section("Data"), section("BCLConvert_Settings")],
+        };
+        assert_eq!(ambiguous.demux_tool(), DemuxTool::Unknown);
+    }
+
+    #[test]
+    fn compares_reads() {
+        let run_info = parse_run_info("test_data/20240110_A00123_0042_AHABCDEFXY/RunInfo.xml");
+        let lengths = parse_samplesheet(V1_SS).unwrap().read_lengths().unwrap();
+        let discrepancies = compare_reads(&run_info.unwrap(), &lengths);
+        assert_eq!(discrepancies.len(), 2);
+        assert_eq!(
+            discrepancies[1],
+            ReadDiscrepancy {
+                is_indexed_read: false,
+                ordinal: 2,
+                run_info: Some(151),
+                samplesheet: Some(101),
+            }
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Parse the contents of sentinel files
+//!
+//! Sentinel files such as CopyComplete.txt are usually only checked for existence, but some
+//! platforms write a timestamp or message into them. This module extracts what it can.
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+
+use crate::SeqDirError;
+
+/// Timestamp formats that have been observed in sentinel files, in order of preference.
+const TIMESTAMP_FORMATS: [&str; 5] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%m/%d/%Y %H:%M:%S%.f",
+    "%m/%d/%Y,%H:%M:%S%.f",
+    "%m/%d/%Y %I:%M:%S %p",
+];
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// The contents of CopyComplete.txt
+pub struct CopyComplete {
+    /// When the copy completed.
+    ///
+    /// Parsed from the file contents if possible, otherwise the file's modification time.
+    pub completed_at: Option<DateTime<Utc>>,
+    /// The raw file contents
+    pub raw: String,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// A lock file left in a sequencing directory by an external process
+pub struct LockInfo {
+    pub path: PathBuf,
+    /// Time since the lock file was last modified.
+    ///
+    /// Zero if the modification time is in the future.
+    pub age: Duration,
+}
+
+/// Attempt to parse a timestamp from the contents of a sentinel file.
+///
+/// Timestamps without an offset are assumed to be UTC. Only the first line is considered.
+pub fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let line = raw.lines().next()?.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(line) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    TIMESTAMP_FORMATS.iter().find_map(|fmt| {
+        NaiveDateTime::parse_and_remainder(line, fmt)
+            .ok()
+            .map(|(dt, _)| dt.and_utc())
+    })
+}
+
+/// Attempt to extract the RTA version from the contents of RTAComplete.txt.
+///
+/// Older versions of RTA write a line such as `11/1/2019,11:48:37.890,Illumina RTA 1.18.54`,
+/// while RTA3 writes a small XML document with a `<Version>RTA 3.4.4</Version>` element.
+/// In both cases only the version number (e.g. `1.18.54`) is returned.
+pub fn parse_rta_version(raw: &str) -> Option<String> {
+    let text = match roxmltree::Document::parse(raw.trim()) {
+        Ok(doc) => doc
+            .descendants()
+            .find(|elem| elem.has_tag_name("Version"))?
+            .text()?
+            .to_string(),
+        Err(_) => raw
+            .lines()
+            .next()?
+            .split(',')
+            .find(|s| s.contains("RTA"))?
+            .to_string(),
+    };
+    text.split_once("RTA")
+        .map_or(text.as_str(), |(_, version)| version)
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+}
+
+/// Read a CopyComplete.txt file.
+///
+/// Falls back to the file's modification time if the contents do not contain a timestamp.
+pub fn parse_copy_complete<P: AsRef<Path>>(path: P) -> Result<CopyComplete, SeqDirError> {
+    let raw = read_to_string(&path)?;
+    let completed_at = match parse_timestamp(&raw) {
+        Some(dt) => Some(dt),
+        None => std::fs::metadata(&path)?
+            .modified()
+            .ok()
+            .map(DateTime::<Utc>::from),
+    };
+    Ok(CopyComplete { completed_at, raw })
+}
+
+/// Inspect a lock file.
+///
+/// Returns None if `path` does not exist.
+pub fn read_lock<P: AsRef<Path>>(path: P) -> Result<Option<LockInfo>, SeqDirError> {
+    let path = path.as_ref();
+    let modified = match std::fs::metadata(path) {
+        Ok(meta) => meta.modified()?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(Some(LockInfo {
+        path: path.to_owned(),
+        age: SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{parse_copy_complete, parse_rta_version, parse_timestamp, read_lock};
+    use crate::test_dir::TestDir;
+
+    const COMPLETE_CC: &str = "test_data/seq_complete/CopyComplete.txt";
+    const FAILED_CC: &str = "test_data/seq_failed/CopyComplete.txt";
+
+    #[test]
+    fn parse_timestamps() {
+        let expected = Utc.with_ymd_and_hms(2024, 1, 13, 2, 0, 0).unwrap();
+        assert_eq!(parse_timestamp("2024-01-13T02:00:00Z"), Some(expected));
+        assert_eq!(parse_timestamp("2024-01-13 02:00:00\n"), Some(expected));
+        assert_eq!(parse_timestamp("01/13/2024 2:00:00 AM"), Some(expected));
+        assert_eq!(
+            parse_timestamp("1/13/2024,02:00:00.000,Illumina RTA 3.4.4"),
+            Some(expected)
+        );
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("copy complete"), None);
+    }
+
+    #[test]
+    fn parse_rta_versions() {
+        assert_eq!(
+            parse_rta_version("11/1/2019,11:48:37.890,Illumina RTA 1.18.54\r\n").as_deref(),
+            Some("1.18.54")
+        );
+        assert_eq!(
+            parse_rta_version(
+                "<?xml version=\"1.0\"?><RTAComplete><Version>RTA 3.4.4</Version></RTAComplete>"
+            )
+            .as_deref(),
+            Some("3.4.4")
+        );
+        assert_eq!(parse_rta_version(""), None);
+        assert_eq!(parse_rta_version("11/1/2019,11:48:37.890"), None);
+    }
+
+    #[test]
+    fn falls_back_to_mtime() {
+        let copy_complete = parse_copy_complete(COMPLETE_CC).unwrap();
+        assert!(copy_complete.raw.is_empty());
+        assert!(copy_complete.completed_at.is_some());
+    }
+
+    #[test]
+    fn parses_contents() {
+        let copy_complete = parse_copy_complete(FAILED_CC).unwrap();
+        assert_eq!(
+            copy_complete.completed_at,
+            Some(Utc.with_ymd_and_hms(2024, 1, 13, 2, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn reads_lock() {
+        use std::time::{Duration, SystemTime};
+
+        let root = TestDir::new("lock");
+        let path = root.join(".processing.lock");
+        assert!(read_lock(&path).unwrap().is_none());
+        let lock = std::fs::File::create(&path).unwrap();
+        lock.set_modified(SystemTime::now() - Duration::from_secs(600))
+            .unwrap();
+        let info = read_lock(&path).unwrap().unwrap();
+        assert_eq!(info.path, path);
+        assert!(info.age >= Duration::from_secs(600));
+    }
+}
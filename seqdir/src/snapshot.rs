@@ -0,0 +1,153 @@
+//! Point-in-time snapshots of sequencing directories
+//!
+//! A [SeqDirSnapshot] captures the observed structure of a [SeqDir](crate::SeqDir) into owned
+//! data. Once taken, a snapshot never touches the filesystem again, so later changes to the
+//! directory do not affect it. Snapshots can be serialized and deserialized for reproducible
+//! reporting.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::lane::{Cycle, Lane};
+use crate::CompletionStatus;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// A cycle as observed when the snapshot was taken
+pub struct CycleSnapshot {
+    pub cycle_num: u16,
+    pub bcl_count: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// A lane as observed when the snapshot was taken
+pub struct LaneSnapshot {
+    pub lane_num: u8,
+    pub cycles: Vec<CycleSnapshot>,
+    pub filter_count: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// An owned, read-only view of a sequencing directory at a point in time.
+///
+/// Methods mirror the queries available on a live [SeqDir](crate::SeqDir), but operate only on
+/// captured data.
+pub struct SeqDirSnapshot {
+    pub(crate) root: PathBuf,
+    pub(crate) taken_at: DateTime<Utc>,
+    pub(crate) copy_complete: bool,
+    pub(crate) rta_complete: bool,
+    pub(crate) sequence_complete: bool,
+    pub(crate) completion_status: Option<CompletionStatus>,
+    pub(crate) lanes: Vec<LaneSnapshot>,
+}
+
+impl<P: AsRef<Path>> From<&Cycle<P>> for CycleSnapshot {
+    fn from(value: &Cycle<P>) -> Self {
+        CycleSnapshot {
+            cycle_num: value.cycle_num,
+            bcl_count: value.bcls.len(),
+        }
+    }
+}
+
+impl<P: AsRef<Path>> From<&Lane<P>> for LaneSnapshot {
+    fn from(value: &Lane<P>) -> Self {
+        LaneSnapshot {
+            lane_num: value.lane_num,
+            cycles: value.iter_cycles().map(CycleSnapshot::from).collect(),
+            filter_count: value.filters().len(),
+        }
+    }
+}
+
+impl SeqDirSnapshot {
+    /// Returns reference to the root of the sequencing directory at the time of the snapshot
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Timestamp of when the snapshot was taken
+    pub fn taken_at(&self) -> &DateTime<Utc> {
+        &self.taken_at
+    }
+
+    /// Returns true if CopyComplete.txt existed.
+    pub fn is_copy_complete(&self) -> bool {
+        self.copy_complete
+    }
+
+    /// Returns true if RTAComplete.txt existed.
+    pub fn is_rta_complete(&self) -> bool {
+        self.rta_complete
+    }
+
+    /// Returns true if SequenceComplete.txt existed.
+    pub fn is_sequence_complete(&self) -> bool {
+        self.sequence_complete
+    }
+
+    /// Returns true if SequenceComplete.txt did not exist
+    pub fn is_sequencing(&self) -> bool {
+        !self.sequence_complete
+    }
+
+    /// Returns the parsed RunCompletionStatus.xml, if it existed
+    pub fn get_completion_status(&self) -> Option<&CompletionStatus> {
+        self.completion_status.as_ref()
+    }
+
+    /// Returns true if the captured completion status indicates failure.
+    ///
+    /// Returns false if there was no RunCompletionStatus.xml.
+    pub fn is_failed(&self) -> bool {
+        self.completion_status
+            .as_ref()
+            .is_some_and(CompletionStatus::is_failed)
+    }
+
+    /// Returns the captured lanes
+    pub fn lanes(&self) -> &[LaneSnapshot] {
+        &self.lanes
+    }
+
+    /// Returns the total number of (C)BCLs across all lanes and cycles
+    pub fn bcl_count(&self) -> usize {
+        self.lanes
+            .iter()
+            .flat_map(|l| l.cycles.iter())
+            .map(|c| c.bcl_count)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SeqDir;
+
+    use super::SeqDirSnapshot;
+
+    const COMPLETE: &str = "test_data/seq_complete/";
+    const FAILED: &str = "test_data/seq_failed/";
+
+    #[test]
+    fn snapshot_complete() {
+        let snapshot = SeqDir::from_path(COMPLETE).unwrap().snapshot().unwrap();
+        assert!(snapshot.is_copy_complete());
+        assert!(!snapshot.is_failed());
+        assert_eq!(snapshot.lanes().len(), 4);
+        assert!(snapshot.lanes().iter().all(|l| l.cycles.len() == 42));
+        assert_eq!(snapshot.bcl_count(), 336);
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let snapshot = SeqDir::from_path(FAILED).unwrap().snapshot().unwrap();
+        assert!(snapshot.is_failed());
+        assert!(snapshot.lanes().is_empty());
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: SeqDirSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, restored);
+    }
+}
@@ -0,0 +1,66 @@
+//! Final reports for monitored runs
+//!
+//! A [RunSummary] bundles the final [SeqDirState] of a run with the most commonly reported
+//! details about it. Building a summary never fails: details that cannot be determined are left
+//! as None.
+
+use serde::Serialize;
+
+use crate::{CompletionStatus, SeqDirState};
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+/// A report on a sequencing directory and its state.
+pub struct RunSummary {
+    /// The state of the directory when the summary was built
+    pub state: SeqDirState,
+    /// The run id, from RunInfo.xml or RunCompletionStatus.xml
+    pub run_id: Option<String>,
+    /// The parsed RunCompletionStatus.xml
+    pub completion_status: Option<CompletionStatus>,
+    /// See [percent_complete](crate::SeqDir::percent_complete())
+    pub percent_complete: Option<f32>,
+}
+
+impl From<SeqDirState> for RunSummary {
+    fn from(state: SeqDirState) -> Self {
+        let dir = state.dir();
+        let completion_status = dir.get_completion_status().and_then(Result::ok);
+        let run_id = dir.read_run_info().ok().map(|r| r.run_id).or_else(|| {
+            completion_status
+                .as_ref()
+                .map(|status| status.message().run_id.clone())
+        });
+        let percent_complete = dir.percent_complete().ok();
+        RunSummary {
+            run_id,
+            completion_status,
+            percent_complete,
+            state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DirManager;
+
+    const COMPLETE: &str = "test_data/seq_complete/";
+    const FAILED: &str = "test_data/seq_failed/";
+
+    #[test]
+    fn summarize_complete() {
+        let summary = DirManager::new(COMPLETE).unwrap().into_summary();
+        assert_eq!(summary.run_id.as_deref(), Some("20231231_foo_ABCXYZ"));
+        assert_eq!(summary.percent_complete, Some(100.0));
+        assert!(summary.completion_status.is_some());
+        serde_json::to_string(&summary).unwrap();
+    }
+
+    #[test]
+    fn summarize_failed() {
+        // RunInfo.xml is empty, so details come from RunCompletionStatus.xml
+        let summary = DirManager::new(FAILED).unwrap().into_summary();
+        assert_eq!(summary.run_id.as_deref(), Some("20231231_bar_ABCXYZ"));
+        assert_eq!(summary.percent_complete, None);
+    }
+}
@@ -0,0 +1,503 @@
+//! Sanity checks for sequencing directories
+//!
+//! Validations in this module never fail fast. Each check reports its outcome so that every
+//! problem with a directory can be surfaced at once.
+
+use std::fmt::Display;
+use std::fs::read_to_string;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::lane::{detect_lanes, first_incomplete_cycle};
+use crate::{
+    CompletionStatus, DirManager, SeqDir, SeqDirError, SeqDirState, COPY_COMPLETE_TXT,
+    RTA_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT,
+};
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "status", content = "reason")]
+/// The outcome of validating a metadata file.
+pub enum MetadataStatus {
+    Ok,
+    Malformed(String),
+    Missing,
+}
+
+impl MetadataStatus {
+    /// Returns true if the file is present and well-formed
+    pub fn is_ok(&self) -> bool {
+        matches!(self, MetadataStatus::Ok)
+    }
+}
+
+/// Check that the file at `path` is well-formed XML whose root element is `root_tag`.
+pub fn validate_xml<P: AsRef<Path>>(path: P, root_tag: &str) -> MetadataStatus {
+    let raw_contents = match read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return MetadataStatus::Missing,
+        Err(e) => return MetadataStatus::Malformed(e.to_string()),
+    };
+    let doc = match roxmltree::Document::parse(&raw_contents) {
+        Ok(doc) => doc,
+        Err(e) => return MetadataStatus::Malformed(format!("could not parse as XML: {e}")),
+    };
+    let root = doc.root_element().tag_name().name();
+    if root == root_tag {
+        MetadataStatus::Ok
+    } else {
+        MetadataStatus::Malformed(format!("expected root element {root_tag}, found {root}"))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "issue")]
+/// A problem found by [health_check](SeqDir::health_check()).
+pub enum HealthIssue {
+    /// The root directory is not available
+    Unavailable,
+    /// A metadata file exists but could not be parsed
+    MalformedMetadata { path: PathBuf, reason: String },
+    /// RunCompletionStatus.xml reports that the run [failed](CompletionStatus::is_failed())
+    Failed { status: CompletionStatus },
+    /// The lane and cycle directories could not be read
+    Structure { reason: String },
+    /// The run is copy complete, but cycles expected by RunInfo.xml are missing or empty
+    MissingCycles {
+        first_missing: u16,
+        total_cycles: u16,
+    },
+    /// A cycle mixes BCL formats or compression. See [Cycle::is_consistent](crate::lane::Cycle::is_consistent()).
+    InconsistentCycle { lane: u8, cycle: u16 },
+    /// A lane has fewer filter files than the other lanes
+    PartialFilters {
+        lane: u8,
+        found: usize,
+        expected: usize,
+    },
+    /// The flowcell in RunInfo.xml does not match the run id
+    FlowcellMismatch { flowcell: String, run_id: String },
+}
+
+impl Display for HealthIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unavailable => write!(f, "directory is unavailable"),
+            Self::MalformedMetadata { path, reason } => {
+                write!(f, "{} is malformed: {reason}", path.display())
+            }
+            Self::Failed { status } => write!(f, "run did not complete as planned: {status}"),
+            Self::Structure { reason } => write!(f, "cannot read lanes: {reason}"),
+            Self::MissingCycles {
+                first_missing,
+                total_cycles,
+            } => write!(
+                f,
+                "cycle {first_missing} of {total_cycles} is missing or empty"
+            ),
+            Self::InconsistentCycle { lane, cycle } => {
+                write!(f, "lane {lane} cycle {cycle} mixes formats or compression")
+            }
+            Self::PartialFilters {
+                lane,
+                found,
+                expected,
+            } => write!(f, "lane {lane} has {found} of {expected} filter files"),
+            Self::FlowcellMismatch { flowcell, run_id } => {
+                write!(f, "flowcell {flowcell} does not match run id {run_id}")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+/// The aggregated outcome of every check performed by [health_check](SeqDir::health_check()).
+pub struct HealthReport {
+    /// True if no issues were found
+    pub ok: bool,
+    pub issues: Vec<HealthIssue>,
+}
+
+impl From<Vec<HealthIssue>> for HealthReport {
+    fn from(issues: Vec<HealthIssue>) -> Self {
+        HealthReport {
+            ok: issues.is_empty(),
+            issues,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+/// The cycle directories of one lane compared to the planned cycles, see
+/// [validate_cycles](SeqDir::validate_cycles()).
+pub struct LaneCycles {
+    pub lane_num: u8,
+    /// The planned number of cycles
+    pub expected: u16,
+    /// The highest cycle number on disk, or 0 if there are no cycle directories
+    pub found: u16,
+    /// Cycle numbers below `found` that have no directory, in ascending order
+    pub gaps: Vec<u16>,
+    /// True if every expected cycle has a directory
+    pub complete: bool,
+}
+
+impl LaneCycles {
+    /// Returns true if the lane stops before the last expected cycle
+    pub fn is_short(&self) -> bool {
+        self.found < self.expected
+    }
+
+    /// Returns true if cycles are missing from the middle of the lane
+    pub fn has_gaps(&self) -> bool {
+        !self.gaps.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+/// The outcome of [validate_cycles](SeqDir::validate_cycles()), one entry per lane.
+pub struct CycleValidation {
+    /// In order of lane number
+    pub lanes: Vec<LaneCycles>,
+}
+
+impl CycleValidation {
+    /// Returns true if there is at least one lane and every lane is complete
+    pub fn is_complete(&self) -> bool {
+        !self.lanes.is_empty() && self.lanes.iter().all(|l| l.complete)
+    }
+
+    /// Returns the lanes that are not complete
+    pub fn incomplete_lanes(&self) -> impl Iterator<Item = &LaneCycles> {
+        self.lanes.iter().filter(|l| !l.complete)
+    }
+}
+
+pub(crate) fn validate_cycles(seq_dir: &SeqDir) -> Result<CycleValidation, SeqDirError> {
+    let expected = seq_dir.planned_cycles()?;
+    let mut lanes: Vec<LaneCycles> = detect_lanes(seq_dir.try_root()?)?
+        .iter()
+        .map(|lane| {
+            let found = lane.cycle_range().map_or(0, |(_, max)| max);
+            let gaps = lane.missing_cycles();
+            LaneCycles {
+                lane_num: lane.lane_num,
+                expected,
+                found,
+                complete: found >= expected && gaps.is_empty(),
+                gaps,
+            }
+        })
+        .collect();
+    lanes.sort_by_key(|l| l.lane_num);
+    Ok(CycleValidation { lanes })
+}
+
+/// Returns true if the last component of `run_id` ends with `flowcell`
+fn flowcell_matches(run_id: &str, flowcell: &str) -> bool {
+    run_id
+        .rsplit('_')
+        .next()
+        .is_some_and(|last| last.ends_with(flowcell))
+}
+
+pub(crate) fn health_check(seq_dir: &SeqDir) -> HealthReport {
+    if !seq_dir.is_available() {
+        return HealthReport::from(vec![HealthIssue::Unavailable]);
+    }
+    let mut issues = Vec::new();
+
+    for (path, status) in seq_dir.validate_metadata() {
+        if let MetadataStatus::Malformed(reason) = status {
+            issues.push(HealthIssue::MalformedMetadata { path, reason });
+        }
+    }
+    // parse errors are already reported as malformed metadata
+    if let Some(Ok(status)) = seq_dir.get_completion_status() {
+        if status.is_failed() {
+            issues.push(HealthIssue::Failed { status });
+        }
+    }
+
+    match detect_lanes(seq_dir.root()) {
+        Err(e) => issues.push(HealthIssue::Structure {
+            reason: e.to_string(),
+        }),
+        Ok(lanes) => {
+            for lane in &lanes {
+                issues.extend(lane.iter_cycles().filter(|c| !c.is_consistent()).map(|c| {
+                    HealthIssue::InconsistentCycle {
+                        lane: lane.lane_num,
+                        cycle: c.cycle_num,
+                    }
+                }));
+            }
+            let expected = lanes.iter().map(|l| l.filters().len()).max().unwrap_or(0);
+            issues.extend(
+                lanes
+                    .iter()
+                    .filter(|l| l.filters().len() < expected)
+                    .map(|l| HealthIssue::PartialFilters {
+                        lane: l.lane_num,
+                        found: l.filters().len(),
+                        expected,
+                    }),
+            );
+        }
+    }
+
+    if let Ok(run_info) = seq_dir.read_run_info() {
+        if !flowcell_matches(&run_info.run_id, &run_info.flowcell) {
+            issues.push(HealthIssue::FlowcellMismatch {
+                flowcell: run_info.flowcell.clone(),
+                run_id: run_info.run_id.clone(),
+            });
+        }
+        let total_cycles = seq_dir
+            .planned_cycles()
+            .unwrap_or_else(|_| run_info.total_cycles());
+        if seq_dir.is_copy_complete() {
+            match first_incomplete_cycle(seq_dir.root(), total_cycles) {
+                Ok(Some(first_missing)) => issues.push(HealthIssue::MissingCycles {
+                    first_missing,
+                    total_cycles,
+                }),
+                Ok(None) => {}
+                Err(e) => issues.push(HealthIssue::Structure {
+                    reason: e.to_string(),
+                }),
+            }
+        }
+    }
+
+    HealthReport::from(issues)
+}
+
+/// Label a line of [validate_report]
+fn line<D: Display>(label: &str, value: D) -> String {
+    format!("{label:<20}{value}")
+}
+
+pub(crate) fn validate_report(seq_dir: &SeqDir) -> String {
+    let mut lines = vec![line("Directory:", seq_dir.root().display())];
+    if !seq_dir.is_available() {
+        lines.push(line("State:", "Unavailable"));
+        return lines.join("\n");
+    }
+
+    let state = match DirManager::from_seq_dir(seq_dir.clone()).state() {
+        SeqDirState::Complete(..) => "Complete",
+        SeqDirState::Transferring(..) => "Transferring",
+        SeqDirState::Sequencing(..) => "Sequencing",
+        SeqDirState::Failed(..) => "Failed",
+    };
+    lines.push(line("State:", state));
+
+    let run_info = seq_dir.read_run_info();
+    let run_id = run_info.as_ref().map_or("unknown", |r| r.run_id.as_str());
+    lines.push(line("Run id:", run_id));
+    let platform = seq_dir
+        .platform()
+        .map_or(String::from("unknown"), |p| format!("{p:?}"));
+    lines.push(line("Platform:", platform));
+
+    let cycles = match (seq_dir.planned_cycles(), seq_dir.first_incomplete_cycle()) {
+        (Ok(total), Ok(first_incomplete)) => match first_incomplete {
+            None => format!("all {total} present"),
+            Some(cycle) => format!("{total} planned, cycle {cycle} is the first incomplete"),
+        },
+        (Err(e), _) => format!("unknown ({e})"),
+        (_, Err(e)) => format!("unknown ({e})"),
+    };
+    lines.push(line("Cycles:", cycles));
+
+    let completion = match seq_dir.get_completion_status() {
+        Some(Ok(status)) => status.to_string(),
+        Some(Err(e)) => format!("unreadable ({e})"),
+        None => String::from("none"),
+    };
+    lines.push(line("Completion status:", completion));
+
+    let missing: Vec<String> = seq_dir
+        .validate_metadata()
+        .into_iter()
+        .filter(|(_, status)| *status == MetadataStatus::Missing)
+        .filter_map(|(path, _)| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .chain(
+            [COPY_COMPLETE_TXT, RTA_COMPLETE_TXT, SEQUENCE_COMPLETE_TXT]
+                .into_iter()
+                .filter(|f| !seq_dir.has_file(f))
+                .map(String::from),
+        )
+        .collect();
+    let missing = if missing.is_empty() {
+        String::from("none")
+    } else {
+        missing.join(", ")
+    };
+    lines.push(line("Missing files:", missing));
+
+    let report = health_check(seq_dir);
+    if report.ok {
+        lines.push(line("Issues:", "none"));
+    } else {
+        lines.push(line("Issues:", report.issues.len()));
+        lines.extend(report.issues.iter().map(|issue| format!("  - {issue}")));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flowcell_matches, validate_xml, HealthIssue, MetadataStatus};
+    use crate::mock::MockBuilder;
+    use crate::test_dir::TestDir;
+    use crate::SeqDir;
+
+    const COMPLETE_RI: &str = "test_data/seq_complete/RunInfo.xml";
+    const EMPTY_RI: &str = "test_data/seq_sequencing/RunInfo.xml";
+    const GARBAGE_RCS: &str = "test_data/seq_corrupt/RunCompletionStatus.xml";
+
+    #[test]
+    fn validates_cycles() {
+        let validation = SeqDir::from_path("test_data/seq_complete")
+            .unwrap()
+            .validate_cycles()
+            .unwrap();
+        assert_eq!(validation.lanes.len(), 4);
+        assert!(validation.is_complete());
+
+        let root = TestDir::new("validate_cycles");
+        std::fs::copy(COMPLETE_RI, root.join(crate::RUN_INFO_XML)).unwrap();
+        let basecalls = root.join("Data/Intensities/BaseCalls");
+        let cycles = (1..=42).filter(|c| *c != 20).map(|c| (1, c));
+        for (lane, cycle) in cycles.chain((1..=30).map(|c| (2, c))) {
+            let dir = basecalls.join(format!("L00{lane}/C{cycle}.1"));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(format!("L00{lane}_1.cbcl")), "").unwrap();
+        }
+        let validation = SeqDir::from_path(&root).unwrap().validate_cycles().unwrap();
+        assert!(!validation.is_complete());
+        let [gapped, short] = validation.lanes.as_slice() else {
+            panic!("expected two lanes")
+        };
+        assert!(gapped.has_gaps() && !gapped.is_short());
+        assert_eq!(gapped.gaps, [20]);
+        assert!(short.is_short() && !short.has_gaps());
+        assert_eq!((short.found, short.expected), (30, 42));
+        assert_eq!(validation.incomplete_lanes().count(), 2);
+    }
+
+    #[test]
+    fn validates_root_element() {
+        assert_eq!(validate_xml(COMPLETE_RI, "RunInfo"), MetadataStatus::Ok);
+        assert!(matches!(
+            validate_xml(COMPLETE_RI, "RunParameters"),
+            MetadataStatus::Malformed(..)
+        ));
+    }
+
+    #[test]
+    fn reports_bad_files() {
+        assert!(matches!(
+            validate_xml(EMPTY_RI, "RunInfo"),
+            MetadataStatus::Malformed(..)
+        ));
+        assert!(matches!(
+            validate_xml(GARBAGE_RCS, "RunCompletionStatus"),
+            MetadataStatus::Malformed(..)
+        ));
+        assert_eq!(
+            validate_xml("test_data/nope.xml", "RunInfo"),
+            MetadataStatus::Missing
+        );
+    }
+
+    #[test]
+    fn matches_flowcell() {
+        assert!(flowcell_matches(
+            "20240110_A00123_0042_AHABCDEFXY",
+            "HABCDEFXY"
+        ));
+        assert!(flowcell_matches(
+            "20240102_M00123_0042_000000000-ABCDE",
+            "000000000-ABCDE"
+        ));
+        assert!(!flowcell_matches(
+            "20240110_A00123_0042_AHABCDEFXY",
+            "HZZZZZZXY"
+        ));
+        assert!(!flowcell_matches("", "HABCDEFXY"));
+    }
+
+    #[test]
+    fn healthy_run() {
+        let report = SeqDir::from_path("test_data/seq_complete/")
+            .unwrap()
+            .health_check();
+        assert!(report.ok, "{:?}", report.issues);
+        serde_json::to_string(&report).unwrap();
+    }
+
+    #[test]
+    fn validate_report() {
+        let report = SeqDir::from_path("test_data/seq_complete/")
+            .unwrap()
+            .validate_report();
+        assert!(report.contains("Complete"));
+        assert!(report.contains("20231231_foo_ABCXYZ"));
+        assert!(report.contains("NovaSeq"));
+        assert!(report
+            .lines()
+            .any(|l| l.ends_with("none") && l.starts_with("Issues:")));
+
+        let report = SeqDir::from_path("test_data/20240110_A00123_0042_AHABCDEFXY/")
+            .unwrap()
+            .validate_report();
+        assert!(report.contains("Sequencing"));
+        assert!(report.contains("RunParameters.xml, RunCompletionStatus.xml, CopyComplete.txt"));
+
+        let report = SeqDir::mock(
+            "/does/not/exist".into(),
+            MockBuilder::new().available(false),
+        )
+        .validate_report();
+        assert_eq!(report.lines().count(), 2);
+
+        // HiSeq recipes override the cycles in RunInfo.xml
+        let report = SeqDir::from_path("test_data/seq_hiseq/")
+            .unwrap()
+            .validate_report();
+        let cycles = report.lines().find(|l| l.starts_with("Cycles:")).unwrap();
+        assert!(cycles.contains(" 6 planned"), "{cycles}");
+    }
+
+    #[test]
+    fn unhealthy_runs() {
+        let report = SeqDir::from_path("test_data/seq_failed/")
+            .unwrap()
+            .health_check();
+        assert!(!report.ok);
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| matches!(i, HealthIssue::Failed { .. })));
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| matches!(i, HealthIssue::MalformedMetadata { .. })));
+
+        // lane directories without cycles
+        let report = SeqDir::from_path("test_data/seq_transferring/")
+            .unwrap()
+            .health_check();
+        assert!(matches!(
+            report.issues.as_slice(),
+            [
+                HealthIssue::MalformedMetadata { .. },
+                HealthIssue::Structure { .. }
+            ]
+        ));
+    }
+}
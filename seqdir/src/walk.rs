@@ -0,0 +1,90 @@
+//! Bounded walks over a sequencing directory tree
+//!
+//! Run folders are shallow: the deepest standard files, e.g.
+//! `Data/Intensities/BaseCalls/L001/C1.1/L001_1.cbcl`, are six levels below the root. A walk
+//! that is not bounded can be sent a long way off course by a stray symlink into a large shared
+//! directory, so every recursive helper in this crate uses [walk_files], which stops at a
+//! maximum depth and never follows symlinks.
+
+use std::fs::{read_dir, DirEntry};
+use std::path::Path;
+
+/// The default maximum depth of a walk, which leaves some headroom over the standard layout
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// List the regular files under `start`, descending at most `max_depth` levels.
+///
+/// Files directly in `start` are at depth 1, so a `max_depth` of 1 lists only those. Symlinks,
+/// including symlinked directories, are skipped, as are entries and subdirectories that cannot
+/// be read. Entries are returned in no particular order.
+///
+/// Errors if `start` itself cannot be read.
+pub fn walk_files<P: AsRef<Path>>(start: P, max_depth: usize) -> std::io::Result<Vec<DirEntry>> {
+    let mut files = Vec::new();
+    if max_depth == 0 {
+        return Ok(files);
+    }
+    let mut pending = vec![(read_dir(start)?, 1)];
+    while let Some((entries, depth)) = pending.pop() {
+        for entry in entries.filter_map(Result::ok) {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_file() {
+                files.push(entry);
+            } else if file_type.is_dir() && depth < max_depth {
+                if let Ok(entries) = read_dir(entry.path()) {
+                    pending.push((entries, depth + 1));
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::walk_files;
+    #[cfg(unix)]
+    use crate::test_dir::TestDir;
+
+    fn walked(max_depth: usize) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = walk_files("test_data/seq_nextseq2000", max_depth)
+            .unwrap()
+            .iter()
+            .map(|e| e.path())
+            .collect();
+        paths.sort_unstable();
+        paths
+    }
+
+    #[test]
+    fn bounds_depth() {
+        assert!(walked(0).is_empty());
+        let top = walked(1);
+        assert!(top
+            .iter()
+            .all(|p| p.parent().unwrap().ends_with("seq_nextseq2000")));
+        assert!(top.iter().any(|p| p.ends_with("RunInfo.xml")));
+        let all = walked(usize::MAX);
+        assert!(all.len() > top.len());
+        assert!(all
+            .iter()
+            .any(|p| p.ends_with("Analysis/1/Data/Reports/Demultiplex_Stats.csv")));
+        assert!(walk_files("test_data/does_not_exist", 1).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_symlinks() {
+        let root = TestDir::new("walk_symlink");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/file.txt"), "").unwrap();
+        std::os::unix::fs::symlink(std::env::current_dir().unwrap(), root.join("link")).unwrap();
+        let files = walk_files(&root, usize::MAX).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path(), root.join("sub/file.txt"));
+    }
+}